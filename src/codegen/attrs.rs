@@ -0,0 +1,44 @@
+use crate::session::{Flags, Hardening, Session};
+
+/// What the MIR of a function shows about it, for deriving its attributes.
+#[derive(Default, Clone, Copy)]
+pub struct FnFacts {
+    /// The function makes no calls and contains no inline assembly.
+    pub leaf: bool,
+    /// The function contains a loop, which may never exit.
+    pub loops: bool,
+    /// The function contains a runtime check, which aborts when it fails.
+    pub rt_checks: bool,
+}
+
+/// The LLVM function attributes a function gets, from the session's options and what is known
+/// about the function. Only attributes that are guaranteed to hold are applied, the optimizer
+/// infers the rest itself.
+pub fn fn_attrs(session: &Session, facts: FnFacts) -> Vec<&'static str> {
+    let mut attrs = Vec::new();
+
+    if session.profile.optimize {
+        if facts.leaf {
+            attrs.push("norecurse");
+            //Without loops or calls, only a failed runtime check keeps it from returning
+            if !facts.loops && !facts.rt_checks {
+                attrs.push("willreturn");
+            }
+        }
+    } else {
+        //LLVM requires optnone functions to also be noinline
+        attrs.extend(["noinline", "optnone"]);
+    }
+
+    if session.hardening.contains(&Hardening::StackProtectorStrong) {
+        attrs.push("sspstrong");
+    } else if session.hardening.contains(&Hardening::StackProtector) {
+        attrs.push("ssp");
+    }
+
+    if session.flags.contains(&Flags::Sanitize) {
+        attrs.extend(["sanitize_address", "sanitize_memory", "sanitize_thread"]);
+    }
+
+    attrs
+}
@@ -0,0 +1,86 @@
+use inkwell::{
+    module::Module,
+    targets::TargetData,
+    types::AnyType,
+    values::{AnyValue, BasicValueEnum, GlobalValue, UnnamedAddress},
+};
+
+/// Lists every global of the optimized module with its size, alignment and linkage, followed by a
+/// hex dump of its bytes if it is a constant string, or else its initializer.
+pub fn dump_globals(module: &Module, target_data: &TargetData) -> String {
+    let mut out = String::new();
+    let mut total = 0;
+    let mut count = 0;
+    let mut unnamed = 0;
+    for global in module.get_globals() {
+        //Unnamed globals are numbered in order, as in the IR
+        let mut name = global.get_name().to_string_lossy().into_owned();
+        if name.is_empty() {
+            name = unnamed.to_string();
+            unnamed += 1;
+        }
+
+        let tp = global.as_pointer_value().get_type().get_element_type();
+        let size = target_data.get_abi_size(&tp as &dyn AnyType);
+        total += size;
+        count += 1;
+
+        out.push_str(&format!(
+            "@{name} size={size} align={} linkage={}{}{}\n",
+            global.get_alignment(),
+            format!("{:?}", global.get_linkage()).to_lowercase(),
+            match global.get_unnamed_address() {
+                UnnamedAddress::None => "",
+                UnnamedAddress::Local => " local_unnamed_addr",
+                UnnamedAddress::Global => " unnamed_addr",
+            },
+            if global.is_constant() { " constant" } else { "" },
+        ));
+        match string_bytes(global, size) {
+            Some(bytes) => out.push_str(&hex_dump(&bytes)),
+            None => {
+                if let Some(init) = global.get_initializer() {
+                    out.push_str(&format!("    = {}\n", init.print_to_string().to_string()));
+                }
+            }
+        }
+    }
+    out.push_str(&format!("{count} globals, {total} bytes\n"));
+    out
+}
+
+/// The bytes of a constant string, padded with the NULs that `LLVMGetAsString` stops at.
+fn string_bytes(global: GlobalValue, size: u64) -> Option<Vec<u8>> {
+    let BasicValueEnum::ArrayValue(array) = global.get_initializer()? else {
+        return None;
+    };
+    if !array.is_const_string() {
+        return None;
+    }
+    let mut bytes = array.get_string_constant()?.to_bytes().to_vec();
+    bytes.resize(size as usize, 0);
+    Some(bytes)
+}
+
+fn hex_dump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (i, line) in bytes.chunks(16).enumerate() {
+        let hex = line
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let text = line
+            .iter()
+            .map(|byte| {
+                if byte.is_ascii_graphic() || *byte == b' ' {
+                    *byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+        out.push_str(&format!("    {:04x}: {hex:<47} |{text}|\n", i * 16));
+    }
+    out
+}
@@ -1,70 +1,143 @@
+use indexmap::IndexMap;
 use inkwell::{
     basic_block::BasicBlock,
+    memory_buffer::MemoryBuffer,
+    targets::{
+        CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine, TargetTriple,
+    },
+    OptimizationLevel,
     builder::Builder,
     context::Context,
     debug_info::{DWARFEmissionKind, DWARFSourceLanguage},
     module::FlagBehavior,
-    module::Module,
-    passes::PassManagerSubType,
+    module::{Linkage, Module},
+    DLLStorageClass,
+    passes::PassBuilderOptions,
     types::{AnyTypeEnum, BasicMetadataTypeEnum, FunctionType, BasicTypeEnum, BasicType as InkwellBasicType},
-    values::{BasicValueEnum, FunctionValue, PointerValue},
-    AddressSpace,
+    values::{BasicValueEnum, CallableValue, FunctionValue, GlobalValue, IntValue, PointerValue},
+    AddressSpace, GlobalVisibility,
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    error::Error,
+    ffi::OsString,
+    io::Write,
+    path::{Path, PathBuf},
+    process::Stdio,
 };
-use std::{collections::HashMap, error::Error, fs::OpenOptions};
-use strum::IntoEnumIterator;
 
 use crate::{
-    errors::{raise_error, raise_error_multi, ErrorType},
+    errors::{raise_error, raise_error_multi, raise_error_no_pos, ErrorType},
     mir,
     parser::nodes::{Node, NodeType, OpType},
     types::{
-        builtins::init_builtins, init_extern_fns, BasicType, BuiltinTypes, Trait, TraitType, Type, Lifetime, CustomTypeInternals,
+        builtins::{
+            args::{ARGC_GLOBAL, ARGV_GLOBAL},
+            init_builtins,
+            slice::slice_type,
+            str::{build_str, str_type},
+        },
+        check_float_literal, check_int_literal, init_extern_fns, method_trait, operator_trait, registry::TypeRegistry, BasicType, StdFunctions, Trait, TraitCode, TraitType, Type, Lifetime, CustomTypeInternals, Signedness,
     },
-    utils::{FileInfo, Position},
-    Flags,
+    session::{CrateType, Emit, Hardening, LinkMode, Pgo, Session},
+    utils::{define_rt_error, global_string, Position},
 };
 
+use self::attrs::FnFacts;
+use self::globals::dump_globals;
+pub use self::size::print_size;
+
+mod attrs;
+mod globals;
+mod size;
+
 #[derive(Clone, Debug)]
 pub struct BindingTags {
     pub is_mut: bool,
 }
 
+type Binding<'a> = (Option<PointerValue<'a>>, Type<'a>, BindingTags);
+
+/// The innermost scope is last, and is searched first.
 pub struct Namespace<'a> {
-    bindings: HashMap<String, (Option<PointerValue<'a>>, Type<'a>, BindingTags)>,
+    scopes: Vec<HashMap<String, Binding<'a>>>,
 }
 
 #[derive(Clone)]
 pub struct CurFunctionState<'a> {
     pub cur_block: Option<BasicBlock<'a>>,
+    /// Whether the block being built has been terminated by a `return`, `break` or `continue`.
     pub returned: bool,
     pub rettp: Type<'a>,
+    /// The return type annotation, or the whole function if it implicitly returns void.
+    pub rettp_pos: Position,
+}
+
+/// Where `continue` and `break` branch to in a loop.
+struct LoopBlocks<'a> {
+    start: BasicBlock<'a>,
+    exit: BasicBlock<'a>,
+    /// Whether any `break` branches to `exit`, which otherwise has no predecessors.
+    broken: bool,
 }
 
+/// In source order, so functions are declared in the same order on every run.
 pub type CodegenFunctions<'a> =
-    HashMap<String, (Node, (Vec<Type<'a>>, Type<'a>), Option<FunctionValue<'a>>)>; //(args, (code, (args, rettp), function)
+    IndexMap<String, (Node, (Vec<Type<'a>>, Type<'a>), Option<FunctionValue<'a>>)>; //(args, (code, (args, rettp), function)
+
+
+const KESTREL_MAIN: &str = "__kestrel_main";
+
+/// Everything a compilation produces. The IR and MIR are kept in memory, while the object and
+/// executable are written by the external assembler and linker.
+#[allow(dead_code)]
+pub struct Artifacts {
+    pub llvm_ir: String,
+    pub mir: String,
+    pub object: PathBuf,
+    pub executable: Option<PathBuf>,
+    pub library: Option<PathBuf>,
+    /// The listing of the module's globals, with `--emit globals`.
+    pub globals: Option<String>,
+}
 
-pub type CustomTypes<'a> = HashMap<String, Type<'a>>;
+impl Artifacts {
+    /// The final output: the executable or library, or the object file if nothing was linked.
+    pub fn output(&self) -> &Path {
+        self.executable
+            .as_ref()
+            .or(self.library.as_ref())
+            .unwrap_or(&self.object)
+    }
+}
 
 pub struct CodeGen<'a> {
     pub context: &'a Context,
     pub module: Module<'a>,
     pub builder: Builder<'a>,
-    pub info: &'a FileInfo<'a>,
+    pub session: &'a Session<'a>,
     dibuilder: inkwell::debug_info::DebugInfoBuilder<'a>,
     pub block: Option<BasicBlock<'a>>,
 
     pub cur_fnstate: Option<CurFunctionState<'a>>,
     pub cur_fn: Option<FunctionValue<'a>>,
 
-    pub builtins: BuiltinTypes<'a>,
-    pub types: CustomTypes<'a>,
+    pub types: TypeRegistry<'a>,
     pub extern_fns: HashMap<String, FunctionValue<'a>>,
+    pub std_fns: StdFunctions<'a>,
     pub functions: CodegenFunctions<'a>, //(args, (code, (args, rettp))
+    /// Functions whose bodies are still to be compiled, in the order they were first called.
+    fn_queue: VecDeque<String>,
+    /// Every function that has been queued, so that recursive calls do not queue it twice.
+    queued: HashSet<String>,
     namespaces: HashMap<FunctionValue<'a>, Namespace<'a>>,
-
-    pub flags: Vec<Flags>,
-    pub optimized: bool,
-    pub debug_mir: bool,
+    pub strings: HashMap<String, GlobalValue<'a>>,
+    /// The MIR of every checked function, separated by blank lines.
+    mir: String,
+    /// The `(line, col)` of each runtime check, indexed by the checks' calls to `kestrel_rt_error`.
+    pub rt_locations: Vec<(u32, u32)>,
+    /// The loops enclosing the code being compiled, innermost last.
+    loops: Vec<LoopBlocks<'a>>,
 }
 
 #[derive(Debug)]
@@ -75,7 +148,6 @@ pub struct Data<'a> {
 
 enum RefOptions {
     Normal,
-    Deref,
     Ref,
 }
 
@@ -84,61 +156,227 @@ struct ExprFlags {
 }
 
 impl<'a> CodeGen<'a> {
-    fn hoist_defs_in_fn(&mut self, ast: Vec<Node>) {
-        for node in ast.clone() {
-            match node.tp {
-                NodeType::Enum => {
-                    self.create_enum(node);
-                }
-                _ => {}
-            }
+    /// Registers the types defined by enums and type aliases, so they can be named before their
+    /// definitions. Enums come first, so that an alias may name any of them; an alias of an alias
+    /// must follow it.
+    fn hoist_type_defs(&mut self, ast: Vec<Node>) {
+        let (enums, aliases): (Vec<_>, Vec<_>) = ast
+            .into_iter()
+            .filter(|node| matches!(node.tp, NodeType::Enum | NodeType::TypeAlias))
+            .partition(|node| node.tp == NodeType::Enum);
+
+        for node in enums {
+            self.create_enum(node);
+        }
+        for node in aliases {
+            let aliasnode = node.data.get_data();
+            let name = aliasnode.raw.get("name").unwrap().clone();
+            let tp = self
+                .types
+                .resolve(&self.session.info, aliasnode.nodes.get("tp").unwrap());
+            self.types.register(name, tp, &node.pos, &self.session.info);
         }
     }
     fn compile(&mut self, ast: Vec<Node>) {
-        //Hoist definitions
+        //Collect the module's symbols, so items can reference each other regardless of order
+        let mut type_defs = Vec::new();
+        let mut fn_defs = Vec::new();
         for node in ast.clone() {
             match node.tp {
-                NodeType::Fn => {
-                    self.hoist_fn_def(node);
-                }
-                NodeType::Enum => {
-                    self.create_enum(node);
-                }
+                NodeType::Fn => fn_defs.push(node),
+                NodeType::Enum | NodeType::TypeAlias => type_defs.push(node),
                 _ => {
                     raise_error(
-                        "Only function or enum definitions are allowed at the module level.",
+                        "Only function, enum or type alias definitions are allowed at the module level.",
                         ErrorType::NonModuleLevelStatement,
                         &node.pos,
-                        self.info,
+                        &self.session.info,
                     );
                 }
             }
         }
+
+        //Types come first, since function signatures may name them
+        self.hoist_type_defs(type_defs);
+        for node in fn_defs {
+            self.hoist_fn_def(node);
+        }
         
-        if !self.functions.contains_key("main") {
+        //Libraries and objects have no entry point, their exports are what gets called
+        if self.session.crate_type == CrateType::Bin && !self.functions.contains_key("main") {
             self.add_main_skeleton();
         }
 
+        //Declare every function before compiling any body, so calls never have to compile their callee
+        self.declare_fns();
+
         for node in &ast {
             match node.tp {
                 NodeType::Fn => {
                     self.create_fn(node);
                 }
-                NodeType::Enum => {}
+                NodeType::Enum | NodeType::TypeAlias => {}
                 _ => {
                     unreachable!()
                 }
             }
         }
+
+        //Exported functions are compiled even if nothing here calls them
+        for (name, func) in &self.functions {
+            let fnnode = func.0.data.get_data();
+            if *fnnode.booleans.get("is_export").unwrap() && self.queued.insert(name.clone()) {
+                self.fn_queue.push_back(name.clone());
+            }
+        }
+
+        //Bodies are compiled one at a time, so (mutually) recursive calls only reference the declaration
+        while let Some(name) = self.fn_queue.pop_front() {
+            self.compile_fn_body(&name);
+        }
+    }
+
+    fn append_mir(&mut self, mir: &str) {
+        if !self.mir.is_empty() && !mir.is_empty() {
+            self.mir.push_str("\n\n");
+        }
+        self.mir.push_str(mir);
+    }
+
+    fn declare_fns(&mut self) {
+        let names = self
+            .functions
+            .keys()
+            .filter(|name| *name != "main")
+            .cloned()
+            .collect::<Vec<_>>();
+        for name in names {
+            let func = self.functions.get_mut(&name).unwrap();
+            let fn_tp = Self::create_fn_tp(self.context, &func.1 .0, &func.1 .1);
+            func.2 = Some(self.module.add_function(&name, fn_tp, None));
+        }
+    }
+
+    fn compile_fn_body(&mut self, name: &str) {
+        let func = self.functions.get(name).unwrap().clone();
+        let fnnode = func.0.data.get_data();
+        let func_rettp = func.1 .1.clone();
+        let fn_real = func.2.unwrap();
+
+        //Only exported functions are visible outside the module, and hidden ones only to the objects
+        //it is linked with
+        let visibility = match fnnode.raw.get("visibility").map(String::as_str) {
+            Some("hidden") => GlobalVisibility::Hidden,
+            Some("protected") => GlobalVisibility::Protected,
+            _ => GlobalVisibility::Default,
+        };
+        if !*fnnode.booleans.get("is_export").unwrap() {
+            fn_real.set_linkage(Linkage::Internal);
+        } else {
+            fn_real.as_global_value().set_visibility(visibility);
+            if is_msvc(self.module.get_triple().as_str().to_str().unwrap())
+                && visibility != GlobalVisibility::Hidden
+            {
+                fn_real
+                    .as_global_value()
+                    .set_dll_storage_class(DLLStorageClass::Export);
+            }
+        }
+
+        let basic_block = self.context.append_basic_block(fn_real, "");
+
+        self.hoist_type_defs(fnnode.nodearr.unwrap().clone());
+
+        // Mir check
+        let mut mir = mir::new(
+            self.session,
+            self.types.clone(),
+            self.functions.clone(),
+            self.std_fns.clone(),
+            name.into(),
+            func.0.pos.clone(),
+        );
+        mir.generate_params(fnnode.mapping.unwrap(), &func.1 .0);
+        let mut instructions = mir.generate(fnnode.nodearr.unwrap());
+        mir::check(&mut mir, &mut instructions, None, 0);
+        self.append_mir(&mir.output);
+        //
+
+        self.namespaces.insert(
+            fn_real,
+            Namespace {
+                scopes: vec![HashMap::new()],
+            },
+        );
+
+        self.builder.position_at_end(basic_block);
+        self.block = Some(basic_block);
+
+        self.cur_fnstate = Some(CurFunctionState {
+            cur_block: Some(basic_block),
+            returned: false,
+            rettp: func_rettp.clone(),
+            rettp_pos: fnnode.tp.as_ref().map_or(func.0.pos.clone(), |tp| tp.pos.clone()),
+        });
+        self.cur_fn = Some(fn_real);
+
+        //Each parameter is stored to an alloca, like a `let`. `void` parameters have no LLVM
+        //parameter, so they are skipped when counting.
+        let mut args = fn_real.get_param_iter();
+        for ((param, _), tp) in fnnode.mapping.unwrap().iter().zip(&func.1 .0) {
+            let name = param.data.get_data().raw.get("value").unwrap().clone();
+            let alloc = BasicTypeEnum::try_from(Self::kestrel_to_inkwell_tp(self.context, tp))
+                .ok()
+                .map(|inkwell_tp| {
+                    let alloc = self.build_entry_alloca(inkwell_tp);
+                    self.builder.build_store(alloc, args.next().unwrap());
+                    alloc
+                });
+            self.insert_binding(name, (alloc, tp.clone(), BindingTags { is_mut: false }));
+        }
+
+        //Compile code
+        let rt_checks = self.rt_locations.len();
+        self.compile_statements(fnnode.nodearr.unwrap());
+
+        //Whether the body has runtime checks is only known once it is compiled
+        self.add_attrs(
+            fn_real,
+            FnFacts {
+                leaf: mir::is_leaf(&instructions),
+                loops: mir::has_loop(&instructions),
+                rt_checks: self.rt_locations.len() > rt_checks,
+            },
+        );
+
+        if !self.cur_fnstate.as_ref().unwrap().returned
+            && func_rettp.basictype == BasicType::Void
+        {
+            self.builder.build_return(None);
+        } else if !self.cur_fnstate.as_ref().unwrap().returned
+            && func_rettp.basictype != BasicType::Void
+        {
+            raise_error(
+                &format!("Expected 'void', got '{}'", func_rettp.qualname()),
+                ErrorType::TypeMismatch,
+                &func.0.pos,
+                &self.session.info,
+            );
+        }
+        //
     }
 
     fn compile_statements(&mut self, ast: &Vec<Node>) -> Data<'a> {
         let mut res = Data {
             data: None,
-            tp: self.builtins.get(&BasicType::Void).unwrap().clone(),
+            tp: self.types.builtins.get(&BasicType::Void).unwrap().clone(),
         };
 
         for node in ast {
+            //Anything after a return is unreachable
+            if self.cur_fnstate.as_ref().unwrap().returned {
+                break;
+            }
             res = self.compile_expr(
                 node,
                 ExprFlags {
@@ -153,91 +391,60 @@ impl<'a> CodeGen<'a> {
     fn compile_expr(&mut self, node: &Node, flags: ExprFlags) -> Data<'a> {
         match node.tp {
             NodeType::Binary => self.compile_binary(node, flags),
-            NodeType::I32 => self.compile_i32(node, flags),
+            NodeType::I32 => self.compile_int(node, flags, BasicType::I32),
             NodeType::Identifier => self.compile_load(node, flags),
             NodeType::Let => self.compile_let(node, flags),
             NodeType::Store => self.compile_store(node, flags),
             NodeType::Reference => self.compile_reference(node, flags),
-            NodeType::I8 => self.compile_i8(node, flags),
-            NodeType::I16 => self.compile_i16(node, flags),
-            NodeType::I64 => self.compile_i64(node, flags),
-            NodeType::I128 => self.compile_i128(node, flags),
+            NodeType::I8 => self.compile_int(node, flags, BasicType::I8),
+            NodeType::I16 => self.compile_int(node, flags, BasicType::I16),
+            NodeType::I64 => self.compile_int(node, flags, BasicType::I64),
+            NodeType::I128 => self.compile_int(node, flags, BasicType::I128),
             NodeType::Bool => self.compile_bool(node, flags),
-            NodeType::U8 => self.compile_u8(node, flags),
-            NodeType::U16 => self.compile_u16(node, flags),
-            NodeType::U32 => self.compile_u32(node, flags),
-            NodeType::U64 => self.compile_u64(node, flags),
-            NodeType::U128 => self.compile_u128(node, flags),
+            NodeType::U8 => self.compile_int(node, flags, BasicType::U8),
+            NodeType::U16 => self.compile_int(node, flags, BasicType::U16),
+            NodeType::U32 => self.compile_int(node, flags, BasicType::U32),
+            NodeType::U64 => self.compile_int(node, flags, BasicType::U64),
+            NodeType::U128 => self.compile_int(node, flags, BasicType::U128),
+            NodeType::F32 => self.compile_float(node, flags, BasicType::F32),
+            NodeType::F64 => self.compile_float(node, flags, BasicType::F64),
             NodeType::Fn => {
                 raise_error(
                     "Nested function definitions are disallowed.",
                     ErrorType::NestedFnDef,
                     &node.pos,
-                    self.info,
+                    &self.session.info,
                 );
             }
             NodeType::Return => self.compile_return(node, flags),
             NodeType::Call => self.compile_call(node, flags),
             NodeType::Deref => self.compile_deref(node, flags),
             NodeType::Conditional => self.compile_if(node, flags),
-            NodeType::Enum => self.compile_enum(node, flags)
+            NodeType::Enum | NodeType::TypeAlias => self.compile_type_def(node, flags),
+            NodeType::Str => self.compile_str(node, flags),
+            NodeType::Unit => self.compile_unit(node, flags),
+            NodeType::RawPtr | NodeType::Slice => unreachable!(),
+            NodeType::Cast => self.compile_cast(node, flags),
+            NodeType::Unsafe => self.compile_unsafe(node, flags),
+            NodeType::While | NodeType::Loop => self.compile_loop(node, flags),
+            NodeType::Break | NodeType::Continue => self.compile_jump(node, flags),
+            NodeType::Macro => self.compile_macro(node, flags),
         }
     }
 
-    fn add_attrs(&mut self, function: FunctionValue) {
-        let mut attr: inkwell::attributes::Attribute = self.context.create_enum_attribute(
-            inkwell::attributes::Attribute::get_named_enum_kind_id("noinline"),
-            0,
-        );
-        function.add_attribute(inkwell::attributes::AttributeLoc::Function, attr);
-
-        attr = self.context.create_enum_attribute(
-            inkwell::attributes::Attribute::get_named_enum_kind_id("norecurse"),
-            0,
-        );
-        function.add_attribute(inkwell::attributes::AttributeLoc::Function, attr);
-
-        if !self.optimized {
-            attr = self.context.create_enum_attribute(
-                inkwell::attributes::Attribute::get_named_enum_kind_id("optnone"),
+    fn add_attrs(&mut self, function: FunctionValue, facts: FnFacts) {
+        for name in attrs::fn_attrs(self.session, facts) {
+            let attr = self.context.create_enum_attribute(
+                inkwell::attributes::Attribute::get_named_enum_kind_id(name),
                 0,
             );
             function.add_attribute(inkwell::attributes::AttributeLoc::Function, attr);
         }
-
-        //TODO: Ensure this is true
-        attr = self.context.create_enum_attribute(
-            inkwell::attributes::Attribute::get_named_enum_kind_id("willreturn"),
-            0,
-        );
-        function.add_attribute(inkwell::attributes::AttributeLoc::Function, attr);
-
-        for flag in &self.flags {
-            if flag == &Flags::Sanitize {
-                let mut attr = self.context.create_enum_attribute(
-                    inkwell::attributes::Attribute::get_named_enum_kind_id("sanitize_address"),
-                    0,
-                );
-                function.add_attribute(inkwell::attributes::AttributeLoc::Function, attr);
-
-                attr = self.context.create_enum_attribute(
-                    inkwell::attributes::Attribute::get_named_enum_kind_id("sanitize_memory"),
-                    0,
-                );
-                function.add_attribute(inkwell::attributes::AttributeLoc::Function, attr);
-
-                attr = self.context.create_enum_attribute(
-                    inkwell::attributes::Attribute::get_named_enum_kind_id("sanitize_thread"),
-                    0,
-                );
-                function.add_attribute(inkwell::attributes::AttributeLoc::Function, attr);
-            }
-        }
     }
 }
 
 impl<'a> CodeGen<'a> {
-    fn kestrel_to_inkwell_tp(context: &'a Context, tp: &Type<'a>) -> AnyTypeEnum<'a> {
+    pub fn kestrel_to_inkwell_tp(context: &'a Context, tp: &Type<'a>) -> AnyTypeEnum<'a> {
         match tp.basictype {
             BasicType::Bool => {
                 let inkwell_tp = context.bool_type();
@@ -311,13 +518,93 @@ impl<'a> CodeGen<'a> {
                     inkwell_tp.into()
                 }
             }
+            BasicType::F32 | BasicType::F64 => {
+                let inkwell_tp = if tp.basictype == BasicType::F32 {
+                    context.f32_type()
+                } else {
+                    context.f64_type()
+                };
+                if tp.ref_n > 0 {
+                    let mut inkwell_tp = inkwell_tp.ptr_type(AddressSpace::from(0u16));
+                    for _ in 1..tp.ref_n {
+                        inkwell_tp = inkwell_tp.ptr_type(AddressSpace::from(0u16));
+                    }
+                    inkwell_tp.into()
+                } else {
+                    inkwell_tp.into()
+                }
+            }
+            BasicType::Str => {
+                let inkwell_tp = str_type(context);
+                if tp.ref_n > 0 {
+                    let mut inkwell_tp = inkwell_tp.ptr_type(AddressSpace::from(0u16));
+                    for _ in 1..tp.ref_n {
+                        inkwell_tp = inkwell_tp.ptr_type(AddressSpace::from(0u16));
+                    }
+                    inkwell_tp.into()
+                } else {
+                    inkwell_tp.into()
+                }
+            }
+            BasicType::Slice => {
+                let element = match &tp.usertype {
+                    Some(CustomTypeInternals::Slice { element }) => element,
+                    _ => unreachable!(),
+                };
+                let element = Self::kestrel_to_inkwell_tp(context, element);
+                let inkwell_tp = slice_type(context, BasicTypeEnum::try_from(element).unwrap());
+                if tp.ref_n > 0 {
+                    let mut inkwell_tp = inkwell_tp.ptr_type(AddressSpace::from(0u16));
+                    for _ in 1..tp.ref_n {
+                        inkwell_tp = inkwell_tp.ptr_type(AddressSpace::from(0u16));
+                    }
+                    inkwell_tp.into()
+                } else {
+                    inkwell_tp.into()
+                }
+            }
             BasicType::Void => context.void_type().into(),
+            BasicType::RawPtr => {
+                let pointee = match &tp.usertype {
+                    Some(CustomTypeInternals::RawPtr { pointee, mutability: _ }) => pointee,
+                    _ => unreachable!(),
+                };
+                //LLVM has no pointer to void, so `*const void` is an i8 pointer as in C
+                let mut inkwell_tp = match Self::kestrel_to_inkwell_tp(context, pointee) {
+                    AnyTypeEnum::VoidType(_) => context.i8_type().ptr_type(AddressSpace::from(0u16)),
+                    AnyTypeEnum::ArrayType(tp) => tp.ptr_type(AddressSpace::from(0u16)),
+                    AnyTypeEnum::FloatType(tp) => tp.ptr_type(AddressSpace::from(0u16)),
+                    AnyTypeEnum::FunctionType(tp) => tp.ptr_type(AddressSpace::from(0u16)),
+                    AnyTypeEnum::IntType(tp) => tp.ptr_type(AddressSpace::from(0u16)),
+                    AnyTypeEnum::PointerType(tp) => tp.ptr_type(AddressSpace::from(0u16)),
+                    AnyTypeEnum::StructType(tp) => tp.ptr_type(AddressSpace::from(0u16)),
+                    AnyTypeEnum::VectorType(tp) => tp.ptr_type(AddressSpace::from(0u16)),
+                };
+                for _ in 0..tp.ref_n {
+                    inkwell_tp = inkwell_tp.ptr_type(AddressSpace::from(0u16));
+                }
+                inkwell_tp.into()
+            }
             BasicType::Enum => {
-                unimplemented!() //TODO
+                let inkwell_tp = match &tp.usertype {
+                    Some(CustomTypeInternals::Enum { variants: _, tp }) => *tp,
+                    _ => unreachable!(),
+                };
+                if tp.ref_n > 0 {
+                    let mut inkwell_tp = inkwell_tp.ptr_type(AddressSpace::from(0u16));
+                    for _ in 1..tp.ref_n {
+                        inkwell_tp = inkwell_tp.ptr_type(AddressSpace::from(0u16));
+                    }
+                    inkwell_tp.into()
+                } else {
+                    inkwell_tp.into()
+                }
             }
         }
     }
 
+    //Aggregates are passed and returned as first-class LLVM values. The only aggregate is `str`, which is two
+    //eightbytes and so is lowered to registers just as the C ABI would. Larger aggregates will need sret/byval.
     fn create_fn_tp(
         context: &'a Context,
         args: &[Type<'a>],
@@ -406,630 +693,287 @@ impl<'a> CodeGen<'a> {
             }
         }
     }
+}
 
-    fn resolve_type(builtins: &BuiltinTypes<'a>, types: &CustomTypes<'a>, info: &FileInfo<'a>, name: &Node) -> Type<'a> {
-        assert!(name.tp == NodeType::Identifier);
-        let data = name.data.get_data();
-        let name_str = data.raw.get("value").unwrap();
+impl<'a> CodeGen<'a> {
+    /// A reference to a literal points to a slot holding the literal's value.
+    fn build_literal_ref(&self, value: BasicValueEnum<'a>, basictype: BasicType) -> Data<'a> {
+        let ptr = self.build_entry_alloca(value.get_type());
+        self.builder.build_store(ptr, value);
 
-        for basictype in BasicType::iter() {
-            if name_str == &basictype.to_string() {
-                return builtins.get(&basictype).unwrap().clone();
-            }
+        let mut tp = self.types.builtins.get(&basictype).unwrap().clone();
+        tp.ref_n += 1;
+        Data {
+            data: Some(ptr.into()),
+            tp,
         }
+    }
 
-        if types.contains_key(name_str) {
-            return types.get(name_str).unwrap().clone();
+    fn compile_int(&self, node: &Node, flags: ExprFlags, basictype: BasicType) -> Data<'a> {
+        let value = node.data.get_data().raw.get("value").unwrap().clone();
+        if let Err(msg) = check_int_literal(&basictype, &value) {
+            raise_error(&msg, ErrorType::InvalidLiteralForRadix, &node.pos, &self.session.info);
         }
 
-        let fmt: String = format!("Type '{}' not found.", name_str);
-        raise_error(&fmt, ErrorType::TypeNotFound, &name.pos, info);
+        self.compile_int_value(flags, basictype, &value)
     }
-}
 
-impl<'a> CodeGen<'a> {
-    fn compile_i8(&self, node: &Node, flags: ExprFlags) -> Data<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<i8>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "i8 literal in radix 10 out of bounds ({} to {}).",
-                i8::MAX,
-                i8::MIN
-            );
-            raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                self.info,
-            );
-        }
+    fn compile_int_value(&self, flags: ExprFlags, basictype: BasicType, value: &str) -> Data<'a> {
 
-        let res = self.context.i8_type().const_int_from_string(
-            node.data.get_data().raw.get("value").unwrap(),
-            inkwell::types::StringRadix::Decimal,
-        );
+        let inttp = match basictype {
+            BasicType::I8 | BasicType::U8 => self.context.i8_type(),
+            BasicType::I16 | BasicType::U16 => self.context.i16_type(),
+            BasicType::I32 | BasicType::U32 => self.context.i32_type(),
+            BasicType::I64 | BasicType::U64 => self.context.i64_type(),
+            BasicType::I128 | BasicType::U128 => self.context.i128_type(),
+            _ => unreachable!(),
+        };
+        let int = inttp
+            .const_int_from_string(value, inkwell::types::StringRadix::Decimal)
+            .unwrap();
 
-        if let Some(int) = res {
-            if matches!(flags.ref_opt, RefOptions::Ref) {
-                let ptr = self.builder.build_alloca(int.get_type(), "");
-                let mut tp = self.builtins.get(&BasicType::I8).unwrap().clone();
-                tp.ref_n += 1;
-                Data {
-                    data: Some(ptr.into()),
-                    tp,
-                }
-            } else {
-                Data {
-                    data: Some(int.into()),
-                    tp: self.builtins.get(&BasicType::I8).unwrap().clone(),
-                }
-            }
+        if matches!(flags.ref_opt, RefOptions::Ref) {
+            self.build_literal_ref(int.into(), basictype)
         } else {
-            unimplemented!();
+            Data {
+                data: Some(int.into()),
+                tp: self.types.builtins.get(&basictype).unwrap().clone(),
+            }
         }
     }
 
-    fn compile_i16(&self, node: &Node, flags: ExprFlags) -> Data<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<i16>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "i16 literal in radix 10 out of bounds ({} to {}).",
-                i16::MAX,
-                i16::MIN
-            );
-            raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                self.info,
-            );
+    fn compile_float(&self, node: &Node, flags: ExprFlags, basictype: BasicType) -> Data<'a> {
+        let value = node.data.get_data().raw.get("value").unwrap().clone();
+        if let Err(msg) = check_float_literal(&basictype, &value) {
+            raise_error(&msg, ErrorType::InvalidLiteralForRadix, &node.pos, &self.session.info);
         }
 
-        let res = self.context.i16_type().const_int_from_string(
-            node.data.get_data().raw.get("value").unwrap(),
-            inkwell::types::StringRadix::Decimal,
-        );
+        //An f32 literal is rounded once, from the decimal, rather than through an f64
+        let float = match basictype {
+            BasicType::F32 => self
+                .context
+                .f32_type()
+                .const_float(value.parse::<f32>().unwrap() as f64),
+            _ => self
+                .context
+                .f64_type()
+                .const_float(value.parse::<f64>().unwrap()),
+        };
 
-        if let Some(int) = res {
-            if matches!(flags.ref_opt, RefOptions::Ref) {
-                let ptr = self.builder.build_alloca(int.get_type(), "");
-                let mut tp = self.builtins.get(&BasicType::I16).unwrap().clone();
-                tp.ref_n += 1;
-                Data {
-                    data: Some(ptr.into()),
-                    tp,
-                }
-            } else {
-                Data {
-                    data: Some(int.into()),
-                    tp: self.builtins.get(&BasicType::I16).unwrap().clone(),
-                }
-            }
+        if matches!(flags.ref_opt, RefOptions::Ref) {
+            self.build_literal_ref(float.into(), basictype)
         } else {
-            unimplemented!();
+            Data {
+                data: Some(float.into()),
+                tp: self.types.builtins.get(&basictype).unwrap().clone(),
+            }
         }
     }
 
-    fn compile_i32(&self, node: &Node, flags: ExprFlags) -> Data<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<i32>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "i32 literal in radix 10 out of bounds ({} to {}).",
-                i32::MAX,
-                i32::MIN
-            );
-            raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                self.info,
-            );
-        }
-
-        let res = self.context.i32_type().const_int_from_string(
-            node.data.get_data().raw.get("value").unwrap(),
-            inkwell::types::StringRadix::Decimal,
-        );
-
-        if let Some(int) = res {
-            if matches!(flags.ref_opt, RefOptions::Ref) {
-                let ptr = self.builder.build_alloca(int.get_type(), "");
-                let mut tp = self.builtins.get(&BasicType::I32).unwrap().clone();
-                tp.ref_n += 1;
+    fn compile_bool(&self, node: &Node, _flags: ExprFlags) -> Data<'a> {
+        match node.data.get_data().booleans.get("value").unwrap() {
+            true => {
+                let res = self.context.bool_type().const_int(1, false);
                 Data {
-                    data: Some(ptr.into()),
-                    tp,
+                    data: Some(res.into()),
+                    tp: self.types.builtins.get(&BasicType::Bool).unwrap().clone(),
                 }
-            } else {
+            }
+            false => {
+                let res = self.context.bool_type().const_int(0, false);
                 Data {
-                    data: Some(int.into()),
-                    tp: self.builtins.get(&BasicType::I32).unwrap().clone(),
+                    data: Some(res.into()),
+                    tp: self.types.builtins.get(&BasicType::Bool).unwrap().clone(),
                 }
             }
-        } else {
-            unimplemented!();
         }
     }
 
-    fn compile_i64(&self, node: &Node, flags: ExprFlags) -> Data<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<i64>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "i64 literal in radix 10 out of bounds ({} to {}).",
-                i64::MAX,
-                i64::MIN
-            );
-            raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                self.info,
-            );
-        }
-
-        let res = self.context.i64_type().const_int_from_string(
-            node.data.get_data().raw.get("value").unwrap(),
-            inkwell::types::StringRadix::Decimal,
-        );
+    fn compile_str(&mut self, node: &Node, _flags: ExprFlags) -> Data<'a> {
+        let value = node.data.get_data().raw.get("value").unwrap().clone();
+        let ptr = global_string(self, &value);
+        let len = self.context.i64_type().const_int(value.len() as u64, false);
 
-        if let Some(int) = res {
-            if matches!(flags.ref_opt, RefOptions::Ref) {
-                let ptr = self.builder.build_alloca(int.get_type(), "");
-                let mut tp = self.builtins.get(&BasicType::I64).unwrap().clone();
-                tp.ref_n += 1;
-                Data {
-                    data: Some(ptr.into()),
-                    tp,
-                }
-            } else {
-                Data {
-                    data: Some(int.into()),
-                    tp: self.builtins.get(&BasicType::I64).unwrap().clone(),
-                }
-            }
-        } else {
-            unimplemented!();
-        }
+        build_str(self, ptr, len)
     }
 
-    fn compile_i128(&self, node: &Node, flags: ExprFlags) -> Data<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<i128>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "i128 literal in radix 10 out of bounds ({} to {}).",
-                i128::MAX,
-                i128::MIN
-            );
-            raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                self.info,
-            );
+    fn compile_unit(&self, _node: &Node, _flags: ExprFlags) -> Data<'a> {
+        Data {
+            data: None,
+            tp: self.types.builtins.get(&BasicType::Void).unwrap().clone(),
         }
+    }
 
-        let res = self.context.i128_type().const_int_from_string(
-            node.data.get_data().raw.get("value").unwrap(),
-            inkwell::types::StringRadix::Decimal,
+    fn compile_binary(&mut self, node: &Node, _flags: ExprFlags) -> Data<'a> {
+        let binary = node.data.get_data();
+        let left = self.compile_expr(
+            binary.nodes.get("left").unwrap(),
+            ExprFlags {
+                ref_opt: RefOptions::Normal,
+            },
+        );
+        let right = self.compile_expr(
+            binary.nodes.get("right").unwrap(),
+            ExprFlags {
+                ref_opt: RefOptions::Normal,
+            },
         );
 
-        if let Some(int) = res {
-            if matches!(flags.ref_opt, RefOptions::Ref) {
-                let ptr = self.builder.build_alloca(int.get_type(), "");
-                let mut tp = self.builtins.get(&BasicType::I128).unwrap().clone();
-                tp.ref_n += 1;
-                Data {
-                    data: Some(ptr.into()),
-                    tp,
-                }
-            } else {
-                Data {
-                    data: Some(int.into()),
-                    tp: self.builtins.get(&BasicType::I128).unwrap().clone(),
-                }
-            }
-        } else {
-            unimplemented!();
-        }
-    }
+        let (traittp, name) = operator_trait(&binary.op.unwrap());
 
-    fn compile_u8(&self, node: &Node, flags: ExprFlags) -> Data<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<u8>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "u8 literal in radix 10 out of bounds ({} to {}).",
-                u8::MAX,
-                u8::MIN
-            );
+        if let Some((code, _)) = left.tp.operator(&traittp) {
+            code(self, &node.pos, left, right)
+        } else {
             raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
+                &format!("Type '{}' does not implement '{name}'.", left.tp.qualname()),
+                ErrorType::TraitNotImplemented,
                 &node.pos,
-                self.info,
+                &self.session.info,
             );
         }
+    }
 
-        let res = self.context.i8_type().const_int_from_string(
-            node.data.get_data().raw.get("value").unwrap(),
-            inkwell::types::StringRadix::Decimal,
-        );
+    fn compile_method_call(&mut self, node: &Node, tp: Type<'a>, traittp: TraitType) -> Data<'a> {
+        //The MIR checked that the type implements the trait
+        if traittp == TraitType::Default {
+            let Some(Trait {
+                code: TraitCode::Default(code),
+                ref_n: _,
+            }) = tp.traits.get(&TraitType::Default)
+            else {
+                unreachable!();
+            };
+            return code(self, &node.pos, tp.clone());
+        }
 
-        if let Some(int) = res {
-            if matches!(flags.ref_opt, RefOptions::Ref) {
-                let ptr = self.builder.build_alloca(int.get_type(), "");
-                let mut tp = self.builtins.get(&BasicType::U8).unwrap().clone();
-                tp.ref_n += 1;
-                Data {
-                    data: Some(ptr.into()),
-                    tp,
-                }
-            } else {
-                Data {
-                    data: Some(int.into()),
-                    tp: self.builtins.get(&BasicType::U8).unwrap().clone(),
-                }
-            }
-        } else {
-            unimplemented!();
-        }
-    }
-
-    fn compile_u16(&self, node: &Node, flags: ExprFlags) -> Data<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<u16>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "u16 literal in radix 10 out of bounds ({} to {}).",
-                u16::MAX,
-                u16::MIN
-            );
-            raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                self.info,
-            );
-        }
-
-        let res = self.context.i16_type().const_int_from_string(
-            node.data.get_data().raw.get("value").unwrap(),
-            inkwell::types::StringRadix::Decimal,
-        );
-
-        if let Some(int) = res {
-            if matches!(flags.ref_opt, RefOptions::Ref) {
-                let ptr = self.builder.build_alloca(int.get_type(), "");
-                let mut tp = self.builtins.get(&BasicType::U16).unwrap().clone();
-                tp.ref_n += 1;
-                Data {
-                    data: Some(ptr.into()),
-                    tp,
-                }
-            } else {
-                Data {
-                    data: Some(int.into()),
-                    tp: self.builtins.get(&BasicType::U16).unwrap().clone(),
-                }
-            }
-        } else {
-            unimplemented!();
-        }
-    }
-
-    fn compile_u32(&self, node: &Node, flags: ExprFlags) -> Data<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<u32>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "u32 literal in radix 10 out of bounds ({} to {}).",
-                u32::MAX,
-                u32::MIN
-            );
-            raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                self.info,
-            );
-        }
-
-        let res = self.context.i32_type().const_int_from_string(
-            node.data.get_data().raw.get("value").unwrap(),
-            inkwell::types::StringRadix::Decimal,
-        );
-
-        if let Some(int) = res {
-            if matches!(flags.ref_opt, RefOptions::Ref) {
-                let ptr = self.builder.build_alloca(int.get_type(), "");
-                let mut tp = self.builtins.get(&BasicType::U32).unwrap().clone();
-                tp.ref_n += 1;
-                Data {
-                    data: Some(ptr.into()),
-                    tp,
-                }
-            } else {
-                Data {
-                    data: Some(int.into()),
-                    tp: self.builtins.get(&BasicType::U32).unwrap().clone(),
-                }
-            }
-        } else {
-            unimplemented!();
-        }
-    }
-
-    fn compile_u64(&self, node: &Node, flags: ExprFlags) -> Data<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<u64>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "u64 literal in radix 10 out of bounds ({} to {}).",
-                u64::MAX,
-                u64::MIN
-            );
-            raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                self.info,
-            );
-        }
-
-        let res = self.context.i64_type().const_int_from_string(
-            node.data.get_data().raw.get("value").unwrap(),
-            inkwell::types::StringRadix::Decimal,
-        );
-
-        if let Some(int) = res {
-            if matches!(flags.ref_opt, RefOptions::Ref) {
-                let ptr = self.builder.build_alloca(int.get_type(), "");
-                let mut tp = self.builtins.get(&BasicType::U64).unwrap().clone();
-                tp.ref_n += 1;
-                Data {
-                    data: Some(ptr.into()),
-                    tp,
-                }
-            } else {
-                Data {
-                    data: Some(int.into()),
-                    tp: self.builtins.get(&BasicType::U64).unwrap().clone(),
-                }
-            }
-        } else {
-            unimplemented!();
-        }
-    }
-
-    fn compile_u128(&self, node: &Node, flags: ExprFlags) -> Data<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<u128>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "u128 literal in radix 10 out of bounds ({} to {}).",
-                u128::MAX,
-                u128::MIN
-            );
-            raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                self.info,
-            );
-        }
-
-        let res = self.context.i128_type().const_int_from_string(
-            node.data.get_data().raw.get("value").unwrap(),
-            inkwell::types::StringRadix::Decimal,
-        );
-
-        if let Some(int) = res {
-            if matches!(flags.ref_opt, RefOptions::Ref) {
-                let ptr = self.builder.build_alloca(int.get_type(), "");
-                let mut tp = self.builtins.get(&BasicType::U128).unwrap().clone();
-                tp.ref_n += 1;
-                Data {
-                    data: Some(ptr.into()),
-                    tp,
-                }
-            } else {
-                Data {
-                    data: Some(int.into()),
-                    tp: self.builtins.get(&BasicType::U128).unwrap().clone(),
-                }
-            }
-        } else {
-            unimplemented!();
-        }
-    }
-
-    fn compile_bool(&self, node: &Node, _flags: ExprFlags) -> Data<'a> {
-        match node.data.get_data().booleans.get("value").unwrap() {
-            true => {
-                let res = self.context.bool_type().const_int(1, false);
-                Data {
-                    data: Some(res.into()),
-                    tp: self.builtins.get(&BasicType::Bool).unwrap().clone(),
-                }
-            }
-            false => {
-                let res = self.context.bool_type().const_int(0, false);
-                Data {
-                    data: Some(res.into()),
-                    tp: self.builtins.get(&BasicType::Bool).unwrap().clone(),
-                }
-            }
-        }
-    }
-
-    fn compile_binary(&mut self, node: &Node, _flags: ExprFlags) -> Data<'a> {
-        let binary = node.data.get_data();
+        let args = node.data.get_data().nodearr.unwrap();
         let left = self.compile_expr(
-            binary.nodes.get("left").unwrap(),
+            &args[0],
             ExprFlags {
                 ref_opt: RefOptions::Normal,
             },
         );
         let right = self.compile_expr(
-            binary.nodes.get("right").unwrap(),
+            &args[1],
             ExprFlags {
                 ref_opt: RefOptions::Normal,
             },
         );
 
-        let traittp = match binary.op.unwrap() {
-            OpType::Add => TraitType::Add,
-            OpType::Eq => TraitType::Eq,
-            OpType::Ne => TraitType::Ne,
-        };
-
-        let t = left.tp.traits.get(&traittp);
-
-        if let Some(Trait::Add {
-            code,
-            skeleton: _,
-            ref_n: _,
-        }) = t
-        {
-            code(self, &node.pos, left, right)
-        } else if let Some(Trait::Eq {
-            code,
-            skeleton: _,
-            ref_n: _,
-        }) = t
-        {
-            code(self, &node.pos, left, right)
-        } else if let Some(Trait::Ne {
-            code,
-            skeleton: _,
-            ref_n: _,
-        }) = t
-        {
-            code(self, &node.pos, left, right)
-        } else {
-            unreachable!()
-        }
+        let (code, _) = tp.operator(&traittp).unwrap();
+        code(self, &node.pos, left, right)
     }
 
     fn compile_let(&mut self, node: &Node, _flags: ExprFlags) -> Data<'a> {
         let letnode = node.data.get_data();
         let name = letnode.raw.get("name").unwrap();
+        let is_mut = letnode.booleans.get("is_mut").unwrap();
+
+        //The MIR guarantees the binding is stored to before it is loaded
+        let Some(expr) = letnode.nodes.get("expr") else {
+            let tp = self
+                .types
+                .resolve(&self.session.info, letnode.nodes.get("tp").unwrap());
+            let alloc = BasicTypeEnum::try_from(Self::kestrel_to_inkwell_tp(self.context, &tp))
+                .ok()
+                .map(|inkwell_tp| self.build_entry_alloca(inkwell_tp));
+            self.insert_binding(name.clone(), (alloc, tp, BindingTags { is_mut: *is_mut }));
+
+            return Data {
+                data: None,
+                tp: self.types.builtins.get(&BasicType::Void).unwrap().clone(),
+            };
+        };
+
         let right = self.compile_expr(
-            letnode.nodes.get("expr").unwrap(),
+            expr,
             ExprFlags {
                 ref_opt: RefOptions::Normal,
             },
         );
-        let is_mut = letnode.booleans.get("is_mut").unwrap();
 
         if right.data.is_some() {
-            let alloc = self
-                .builder
-                .build_alloca(right.data.unwrap().get_type(), "");
+            let alloc = self.build_entry_alloca(right.data.unwrap().get_type());
 
             self.builder.build_store(alloc, right.data.unwrap());
-            self.namespaces
-                .get_mut(&self.cur_fn.unwrap())
-                .unwrap()
-                .bindings
-                .insert(
-                    name.clone(),
-                    (Some(alloc), right.tp, BindingTags { is_mut: *is_mut }),
-                );
+            self.insert_binding(
+                name.clone(),
+                (Some(alloc), right.tp, BindingTags { is_mut: *is_mut }),
+            );
         } else {
-            self.namespaces
-                .get_mut(&self.cur_fn.unwrap())
-                .unwrap()
-                .bindings
-                .insert(
-                    name.clone(),
-                    (None, right.tp, BindingTags { is_mut: *is_mut }),
-                );
+            self.insert_binding(
+                name.clone(),
+                (None, right.tp, BindingTags { is_mut: *is_mut }),
+            );
         }
 
         Data {
             data: None,
-            tp: self.builtins.get(&BasicType::Void).unwrap().clone(),
+            tp: self.types.builtins.get(&BasicType::Void).unwrap().clone(),
         }
     }
 
+    /// Allocas are placed at the start of the entry block, where mem2reg can promote them and
+    /// where they run once even if the binding is inside a branch.
+    pub fn build_entry_alloca<T: InkwellBasicType<'a>>(&self, tp: T) -> PointerValue<'a> {
+        let builder = self.context.create_builder();
+        let entry = self.cur_fn.unwrap().get_first_basic_block().unwrap();
+        match entry.get_first_instruction() {
+            Some(instr) => builder.position_before(&instr),
+            None => builder.position_at_end(entry),
+        }
+        builder.build_alloca(tp, "")
+    }
+
+    fn push_scope(&mut self) {
+        self.namespaces
+            .get_mut(&self.cur_fn.unwrap())
+            .unwrap()
+            .scopes
+            .push(HashMap::new());
+    }
+
+    fn pop_scope(&mut self) {
+        self.namespaces
+            .get_mut(&self.cur_fn.unwrap())
+            .unwrap()
+            .scopes
+            .pop();
+    }
+
+    fn insert_binding(&mut self, name: String, binding: Binding<'a>) {
+        self.namespaces
+            .get_mut(&self.cur_fn.unwrap())
+            .unwrap()
+            .scopes
+            .last_mut()
+            .unwrap()
+            .insert(name, binding);
+    }
+
+    fn get_binding(&self, name: &String) -> &Binding<'a> {
+        self.namespaces
+            .get(&self.cur_fn.unwrap())
+            .unwrap()
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .unwrap()
+    }
+
     fn compile_load(&mut self, node: &Node, flags: ExprFlags) -> Data<'a> {
         let identifiernode = node.data.get_data();
         let name = identifiernode.raw.get("value").unwrap();
 
-        let binding = self
-            .namespaces
-            .get_mut(&self.cur_fn.unwrap())
-            .unwrap()
-            .bindings
-            .get(name);
+        if let Some((basictype, value)) =
+            self.types
+                .assoc_const(&self.session.info, name, &node.pos)
+        {
+            return self.compile_int_value(flags, basictype, &value);
+        }
 
-        let binding = binding.unwrap();
+        let binding = self.get_binding(name);
 
         if matches!(flags.ref_opt, RefOptions::Ref) {
             let mut tp = binding.1.clone();
@@ -1042,24 +986,6 @@ impl<'a> CodeGen<'a> {
                 },
                 tp,
             }
-        } else if matches!(flags.ref_opt, RefOptions::Deref) {
-            let mut tp = binding.1.clone();
-            tp.ref_n -= 1;
-            Data {
-                data: if binding.0.is_some() {
-                    Some(
-                        self.builder.build_load(
-                            self.builder
-                                .build_load(binding.0.unwrap(), "")
-                                .into_pointer_value(),
-                            "",
-                        ),
-                    )
-                } else {
-                    None
-                },
-                tp: binding.1.clone(),
-            }
         } else {
             Data {
                 data: if binding.0.is_some() {
@@ -1083,14 +1009,7 @@ impl<'a> CodeGen<'a> {
             },
         );
 
-        let binding = self
-            .namespaces
-            .get_mut(&self.cur_fn.unwrap())
-            .unwrap()
-            .bindings
-            .get(name);
-
-        let binding = binding.unwrap();
+        let binding = self.get_binding(name);
 
         if right.data.is_some() {
             debug_assert!(binding.0.is_some());
@@ -1100,7 +1019,7 @@ impl<'a> CodeGen<'a> {
 
         Data {
             data: None,
-            tp: self.builtins.get(&BasicType::Void).unwrap().clone(),
+            tp: self.types.builtins.get(&BasicType::Void).unwrap().clone(),
         }
     }
 
@@ -1118,23 +1037,28 @@ impl<'a> CodeGen<'a> {
 
     fn compile_return(&mut self, node: &Node, _flags: ExprFlags) -> Data<'a> {
         let returnnode = node.data.get_data();
+        let exprnode = returnnode.nodes.get("expr").unwrap();
         let expr = self.compile_expr(
-            returnnode.nodes.get("expr").unwrap(),
+            exprnode,
             ExprFlags {
                 ref_opt: RefOptions::Normal,
             },
         );
 
-        if self.cur_fnstate.as_ref().unwrap().rettp != expr.tp {
-            raise_error(
-                &format!(
-                    "Expected '{}', got '{}'",
-                    self.cur_fnstate.as_ref().unwrap().rettp.qualname(),
-                    expr.tp.qualname()
-                ),
+        let fnstate = self.cur_fnstate.as_ref().unwrap();
+        if fnstate.rettp != expr.tp {
+            raise_error_multi(
+                vec![
+                    format!(
+                        "Expected '{}', got '{}'",
+                        fnstate.rettp.qualname(),
+                        expr.tp.qualname()
+                    ),
+                    "Expected because of this:".into(),
+                ],
                 ErrorType::TypeMismatch,
-                &node.pos,
-                self.info,
+                vec![Some(&exprnode.pos), Some(&fnstate.rettp_pos)],
+                &self.session.info,
             );
         }
 
@@ -1148,7 +1072,7 @@ impl<'a> CodeGen<'a> {
 
         Data {
             data: None,
-            tp: self.builtins.get(&BasicType::Void).unwrap().clone(),
+            tp: self.types.builtins.get(&BasicType::Void).unwrap().clone(),
         }
     }
 
@@ -1156,108 +1080,251 @@ impl<'a> CodeGen<'a> {
         let callnode = node.data.get_data();
         let name = callnode.raw.get("name").unwrap().clone();
 
-        let mut func = self.functions.get(&name).unwrap().clone();
+        //`Type::method(args)`, resolved in the same order as the MIR
+        if !self.functions.contains_key(&name) && !self.std_fns.contains_key(&name) {
+            if let Some((tpname, method)) = name.rsplit_once("::") {
+                if let (Some(tp), Some((traittp, _))) = (self.types.get(tpname), method_trait(method)) {
+                    return self.compile_method_call(node, tp, traittp);
+                }
+            }
+        }
+
+        if let Some(stdfn) = self.std_fns.get(&name).cloned() {
+            let args = callnode
+                .nodearr
+                .unwrap()
+                .iter()
+                .map(|arg| {
+                    self.compile_expr(
+                        arg,
+                        ExprFlags {
+                            ref_opt: RefOptions::Normal,
+                        },
+                    )
+                })
+                .collect::<Vec<_>>();
+            return (stdfn.code)(self, &node.pos, args);
+        }
+
+        let func = self.functions.get(&name).unwrap().clone();
 
         let func_rettp = func.1 .1.clone();
-        let args = func.1 .0.clone();
 
-        if func.2.is_none() {
-            let fnnode = func.0.data.get_data();
+        //The body is compiled later, once the caller is done with the builder
+        if self.queued.insert(name.clone()) {
+            self.fn_queue.push_back(name.clone());
+        }
 
-            let fn_tp = Self::create_fn_tp(self.context, &args, &func_rettp);
+        let args = callnode
+            .nodearr
+            .unwrap()
+            .iter()
+            .filter_map(|arg| {
+                self.compile_expr(
+                    arg,
+                    ExprFlags {
+                        ref_opt: RefOptions::Normal,
+                    },
+                )
+                .data
+            })
+            .map(|arg| arg.into())
+            .collect::<Vec<_>>();
 
-            let fn_real = self.module.add_function(&name, fn_tp, None);
+        Data {
+            data: self
+                .builder
+                .build_call(func.2.unwrap(), &args, "")
+                .try_as_basic_value()
+                .left(),
+            tp: func_rettp,
+        }
+    }
 
-            func.2 = Some(fn_real);
-            self.functions.insert(name.clone(), func.clone());
+    fn compile_deref(&mut self, node: &Node, _flags: ExprFlags) -> Data<'a> {
+        let derefnode = node.data.get_data();
+        let expr = self.compile_expr(
+            derefnode.nodes.get("expr").unwrap(),
+            ExprFlags {
+                ref_opt: RefOptions::Normal,
+            },
+        );
 
-            let basic_block = self.context.append_basic_block(fn_real, "");
+        //The reference is a pointer to the value, so each deref is one load
+        let tp = match expr.tp.pointee() {
+            Some((pointee, _)) => pointee.clone(),
+            None => {
+                let mut tp = expr.tp;
+                tp.ref_n -= 1;
+                tp
+            }
+        };
+        Data {
+            data: expr
+                .data
+                .map(|ptr| self.builder.build_load(ptr.into_pointer_value(), "")),
+            tp,
+        }
+    }
 
-            // Mir check
-            let mut mir = mir::new(
-                self.info.clone(),
-                self.builtins.clone(),
-                self.types.clone(),
-                self.functions.clone(),
-                name.clone(),
-                node.pos.clone(),
-                self.debug_mir,
-            );
-            let mut instructions = mir.generate(fnnode.nodearr.unwrap());
-            mir::check(&mut mir, &mut instructions, None, 0);
-            //
+    //MIR has checked the cast, and references and raw pointers share a representation
+    fn compile_cast(&mut self, node: &Node, _flags: ExprFlags) -> Data<'a> {
+        let castnode = node.data.get_data();
+        let expr = self.compile_expr(
+            castnode.nodes.get("expr").unwrap(),
+            ExprFlags {
+                ref_opt: RefOptions::Normal,
+            },
+        );
 
-            self.namespaces.insert(
-                fn_real,
-                Namespace {
-                    bindings: HashMap::new(),
-                },
+        let tp = self
+            .types
+            .resolve(&self.session.info, castnode.nodes.get("tp").unwrap());
+
+        //Integers are truncated, or extended by the signedness of the source type
+        if let (Some(signedness), Some(_)) = (expr.tp.signedness(), tp.signedness()) {
+            let int = self.builder.build_int_cast_sign_flag(
+                expr.data.unwrap().into_int_value(),
+                Self::kestrel_to_inkwell_tp(self.context, &tp).into_int_type(),
+                signedness == Signedness::Signed,
+                "",
             );
+            return Data {
+                data: Some(int.into()),
+                tp,
+            };
+        }
 
-            let old_block = self.block;
+        Data {
+            data: expr.data,
+            tp,
+        }
+    }
 
-            self.builder.position_at_end(basic_block);
-            self.block = Some(basic_block);
+    fn compile_unsafe(&mut self, node: &Node, _flags: ExprFlags) -> Data<'a> {
+        let unsafenode = node.data.get_data();
 
-            let old_state = self.cur_fnstate.clone();
-            self.cur_fnstate = Some(CurFunctionState {
-                cur_block: Some(basic_block),
-                returned: false,
-                rettp: func_rettp.clone(),
-            });
+        self.push_scope();
+        let res = self.compile_statements(unsafenode.nodearr.unwrap());
+        self.pop_scope();
 
-            let old_fn = self.cur_fn;
-            self.cur_fn = Some(fn_real);
+        res
+    }
 
-            //
+    /// A `while`, or a `loop` if there is no condition.
+    fn compile_loop(&mut self, node: &Node, _flags: ExprFlags) -> Data<'a> {
+        let loopnode = node.data.get_data();
 
-            //Compile code
-            self.compile_statements(fnnode.nodearr.unwrap());
+        //The condition has its own block, which the end of the body branches back to
+        let start_block = self.context.append_basic_block(self.cur_fn.unwrap(), "");
+        let body_block = self.context.append_basic_block(self.cur_fn.unwrap(), "");
+        let exit_block = self.context.append_basic_block(self.cur_fn.unwrap(), "");
 
-            if !self.cur_fnstate.as_ref().unwrap().returned
-                && func_rettp.basictype == BasicType::Void
-            {
-                self.builder.build_return(None);
-            } else if !self.cur_fnstate.as_ref().unwrap().returned
-                && func_rettp.basictype != BasicType::Void
-            {
+        self.builder.build_unconditional_branch(start_block);
+        self.builder.position_at_end(start_block);
+
+        let exprnode = loopnode.nodes.get("expr");
+        if let Some(exprnode) = exprnode {
+            let expr = self.compile_expr(
+                exprnode,
+                ExprFlags {
+                    ref_opt: RefOptions::Normal,
+                },
+            );
+
+            //MIR rejects these, but the branch below requires an i1
+            if expr.tp.basictype != BasicType::Bool || expr.tp.ref_n != 0 {
                 raise_error(
-                    &format!("Expected 'void', got '{}'", func_rettp.qualname()),
+                    &format!("Expected 'std::bool', got '{}'", expr.tp.qualname()),
                     ErrorType::TypeMismatch,
-                    &node.pos,
-                    self.info,
+                    &exprnode.pos,
+                    &self.session.info,
                 );
             }
-            //
 
-            self.cur_fn = old_fn;
-            self.cur_fnstate = old_state;
-            self.block = old_block;
+            self.builder.build_conditional_branch(
+                expr.data.unwrap().into_int_value(),
+                body_block,
+                exit_block,
+            );
+        } else {
+            self.builder.build_unconditional_branch(body_block);
+        }
+
+        self.builder.position_at_end(body_block);
+
+        self.loops.push(LoopBlocks {
+            start: start_block,
+            exit: exit_block,
+            broken: false,
+        });
+        self.cur_fnstate.as_mut().unwrap().returned = false;
+        self.push_scope();
+        self.compile_statements(loopnode.nodearr.unwrap());
+        self.pop_scope();
+        if !self.cur_fnstate.as_ref().unwrap().returned {
+            self.builder.build_unconditional_branch(start_block);
+        }
+        let broken = self.loops.pop().unwrap().broken;
 
-            self.builder.position_at_end(self.block.unwrap());
+        //The condition may be false the first time, so only a loop without a break never ends
+        self.builder.position_at_end(exit_block);
+        if exprnode.is_none() && !broken {
+            self.builder.build_unreachable();
+            self.cur_fnstate.as_mut().unwrap().returned = true;
+        } else {
+            self.cur_fnstate.as_mut().unwrap().returned = false;
         }
 
         Data {
-            data: Some(
-                self.builder
-                    .build_call(func.2.unwrap(), &[], "")
-                    .try_as_basic_value()
-                    .unwrap_left(),
-            ),
-            tp: func_rettp,
+            data: None,
+            tp: self.types.builtins.get(&BasicType::Void).unwrap().clone(),
         }
     }
 
-    fn compile_deref(&mut self, node: &Node, _flags: ExprFlags) -> Data<'a> {
-        let derefnode = node.data.get_data();
-        let expr = self.compile_expr(
-            derefnode.nodes.get("expr").unwrap(),
-            ExprFlags {
-                ref_opt: RefOptions::Deref,
-            },
-        );
+    fn compile_jump(&mut self, node: &Node, _flags: ExprFlags) -> Data<'a> {
+        let innermost = self.loops.last_mut().unwrap();
+        let target = if node.tp == NodeType::Break {
+            innermost.broken = true;
+            innermost.exit
+        } else {
+            innermost.start
+        };
+        self.builder.build_unconditional_branch(target);
+        self.cur_fnstate.as_mut().unwrap().returned = true;
 
-        expr
+        Data {
+            data: None,
+            tp: self.types.builtins.get(&BasicType::Void).unwrap().clone(),
+        }
+    }
+
+    fn compile_macro(&mut self, node: &Node, _flags: ExprFlags) -> Data<'a> {
+        let macronode = node.data.get_data();
+        let args = macronode.nodearr.unwrap();
+
+        match macronode.raw.get("name").unwrap().as_str() {
+            "asm" => {
+                let assembly = args.first().unwrap().data.get_data().raw.get("value").unwrap().clone();
+                //No operands or clobbers, but the side effects keep it from being optimized out
+                let asm = self.context.create_inline_asm(
+                    self.context.void_type().fn_type(&[], false),
+                    assembly,
+                    String::new(),
+                    true,
+                    false,
+                    None,
+                    false,
+                );
+                self.builder.build_call(CallableValue::try_from(asm).unwrap(), &[], "");
+            }
+            _ => unreachable!(),
+        }
+
+        Data {
+            data: None,
+            tp: self.types.builtins.get(&BasicType::Void).unwrap().clone(),
+        }
     }
 
     fn compile_if(&mut self, node: &Node, _flags: ExprFlags) -> Data<'a> {
@@ -1265,81 +1332,124 @@ impl<'a> CodeGen<'a> {
         let codes = ifnode.nodearr_codes.unwrap().clone();
         let exprs = ifnode.nodearr.unwrap();
 
+        let switch = self.switch_cases(exprs);
+
+        //Each condition after the first is checked in its own block, reached when the previous one fails
         let mut if_blocks = vec![];
         let mut check_blocks = vec![];
-        for _ in 0..codes.len() {
-            let if_block = self.context.append_basic_block(self.cur_fn.unwrap(), "");
-            if_blocks.push(if_block);
-        }
-        check_blocks.push(None);
-        for _ in 1..codes.len() {
-            let check_block = self.context.append_basic_block(self.cur_fn.unwrap(), "");
-            check_blocks.push(Some(check_block));
+        for i in 0..codes.len() {
+            if i > 0 && switch.is_none() {
+                check_blocks.push(self.context.append_basic_block(self.cur_fn.unwrap(), ""));
+            }
+            if_blocks.push(self.context.append_basic_block(self.cur_fn.unwrap(), ""));
         }
 
-        let done_block = self.context.append_basic_block(self.cur_fn.unwrap(), "");
         let else_block = self.context.append_basic_block(self.cur_fn.unwrap(), "");
+        let done_block = self.context.append_basic_block(self.cur_fn.unwrap(), "");
+
+        if let Some((scrutinee, cases)) = &switch {
+            let value = self
+                .compile_expr(
+                    scrutinee,
+                    ExprFlags {
+                        ref_opt: RefOptions::Normal,
+                    },
+                )
+                .data
+                .unwrap()
+                .into_int_value();
+            let cases = std::iter::zip(cases.iter().copied(), if_blocks.iter().copied())
+                .collect::<Vec<_>>();
+            self.builder.build_switch(value, else_block, &cases);
+        }
 
         let mut results = vec![];
         let mut tp = None;
 
         for (i, (code, expr)) in std::iter::zip(codes, exprs).enumerate() {
-            let if_block = if_blocks.get(i);
-            let if_block = if_block.as_ref().unwrap();
-
-            if i > 0 {
-                let check_block = check_blocks.get(i);
-                let check_block = check_block.as_ref().unwrap();
-                if_block.move_after(check_block.unwrap()).unwrap();
-                self.builder.position_at_end(check_block.unwrap());
-            }
+            let if_block = if_blocks.get(i).unwrap();
 
-            if_block
-                .move_after(self.cur_fnstate.as_ref().unwrap().cur_block.unwrap())
-                .unwrap();
+            if switch.is_none() {
+                if i > 0 {
+                    self.builder.position_at_end(*check_blocks.get(i - 1).unwrap());
+                }
 
-            let expr = self.compile_expr(
-                expr,
-                ExprFlags {
-                    ref_opt: RefOptions::Normal,
-                },
-            );
+                let pos = &expr.pos;
+                let expr = self.compile_expr(
+                    expr,
+                    ExprFlags {
+                        ref_opt: RefOptions::Normal,
+                    },
+                );
 
-            self.builder.build_conditional_branch(
-                expr.data.unwrap().into_int_value(),
-                **if_block,
-                if i < if_blocks.len() - 1 {
-                    *if_blocks.get(i + 1).unwrap()
-                } else {
-                    else_block
-                },
-            );
+                //MIR rejects these, but the branch below requires an i1
+                if expr.tp.basictype != BasicType::Bool || expr.tp.ref_n != 0 {
+                    raise_error(
+                        &format!("Expected 'std::bool', got '{}'", expr.tp.qualname()),
+                        ErrorType::TypeMismatch,
+                        pos,
+                        &self.session.info,
+                    );
+                }
+
+                self.builder.build_conditional_branch(
+                    expr.data.unwrap().into_int_value(),
+                    *if_block,
+                    if i < if_blocks.len() - 1 {
+                        *check_blocks.get(i).unwrap()
+                    } else {
+                        else_block
+                    },
+                );
+            }
 
-            self.builder.position_at_end(**if_block);
+            self.builder.position_at_end(*if_block);
 
+            self.cur_fnstate.as_mut().unwrap().returned = false;
+            self.push_scope();
             let res = self.compile_statements(&code);
-            self.builder.build_unconditional_branch(done_block);
+            self.pop_scope();
             tp = Some(res.tp.clone());
-            results.push((res, *if_block));
+            if !self.cur_fnstate.as_ref().unwrap().returned {
+                self.builder.build_unconditional_branch(done_block);
+                results.push((res, self.builder.get_insert_block().unwrap()));
+            }
         }
 
+        self.cur_fnstate.as_mut().unwrap().returned = false;
         self.builder.position_at_end(else_block);
         if ifnode.nodearr_else.is_some() {
             let elsecode = ifnode.nodearr_else.as_ref().unwrap();
+            self.push_scope();
             let res = self.compile_statements(elsecode);
-            results.push((res, &else_block));
+            self.pop_scope();
+            if !self.cur_fnstate.as_ref().unwrap().returned {
+                results.push((res, self.builder.get_insert_block().unwrap()));
+            }
+        }
+        if !self.cur_fnstate.as_ref().unwrap().returned {
+            self.builder.build_unconditional_branch(done_block);
         }
-        self.builder.build_unconditional_branch(done_block);
 
         self.builder.position_at_end(done_block);
 
-        if results.last().unwrap().0.data.is_some() {
+        //Every branch returned, so nothing can reach the done block
+        if results.is_empty() && self.cur_fnstate.as_ref().unwrap().returned {
+            self.builder.build_unreachable();
+            return Data {
+                data: None,
+                tp: tp.unwrap(),
+            };
+        }
+        self.cur_fnstate.as_mut().unwrap().returned = false;
+
+        if results.last().is_some_and(|result| result.0.data.is_some()) {
             let phi = self
                 .builder
                 .build_phi(results.last().unwrap().0.data.unwrap().get_type(), "");
 
             for result in results {
-                phi.add_incoming(&[(&result.0.data.unwrap(), *result.1)]);
+                phi.add_incoming(&[(&result.0.data.unwrap(), result.1)]);
             }
 
             Data {
@@ -1354,12 +1464,80 @@ impl<'a> CodeGen<'a> {
         }
     }
 
-    fn compile_enum(&mut self, node: &Node, _flags: ExprFlags) -> Data<'a> {
-        self.create_enum(node.clone());
+    /// If every condition in a chain compares the same binding against a distinct integer literal,
+    /// returns the binding and the literals so the chain can be lowered to a `switch`.
+    fn switch_cases<'b>(&mut self, exprs: &'b [Node]) -> Option<(&'b Node, Vec<IntValue<'a>>)> {
+        if exprs.len() < 2 {
+            return None;
+        }
+
+        let mut scrutinee: Option<&Node> = None;
+        let mut cases: Vec<IntValue<'a>> = vec![];
+        for expr in exprs {
+            if expr.tp != NodeType::Binary {
+                return None;
+            }
+            let binary = expr.data.get_data();
+            if !matches!(binary.op, Some(OpType::Eq)) {
+                return None;
+            }
+            let left = *binary.nodes.get("left").unwrap();
+            let right = *binary.nodes.get("right").unwrap();
+            let (binding, literal) = match (&left.tp, &right.tp) {
+                (NodeType::Identifier, tp) if Self::is_integer_literal(tp) => (left, right),
+                (tp, NodeType::Identifier) if Self::is_integer_literal(tp) => (right, left),
+                _ => return None,
+            };
+
+            let name = binding.data.get_data().raw.get("value").unwrap().clone();
+            match scrutinee {
+                Some(node) if node.data.get_data().raw.get("value").unwrap() != &name => {
+                    return None;
+                }
+                _ => scrutinee = Some(binding),
+            }
+
+            let case = self
+                .compile_expr(
+                    literal,
+                    ExprFlags {
+                        ref_opt: RefOptions::Normal,
+                    },
+                )
+                .data
+                .unwrap()
+                .into_int_value();
+            //A repeated case is unreachable, and would make the switch invalid
+            if cases.contains(&case) {
+                return None;
+            }
+            cases.push(case);
+        }
+
+        Some((scrutinee.unwrap(), cases))
+    }
+
+    fn is_integer_literal(tp: &NodeType) -> bool {
+        matches!(
+            tp,
+            NodeType::I8
+                | NodeType::I16
+                | NodeType::I32
+                | NodeType::I64
+                | NodeType::I128
+                | NodeType::U8
+                | NodeType::U16
+                | NodeType::U32
+                | NodeType::U64
+                | NodeType::U128
+        )
+    }
 
+    /// Type definitions in a function body were registered when the function was hoisted.
+    fn compile_type_def(&mut self, _node: &Node, _flags: ExprFlags) -> Data<'a> {
         Data {
             data: None,
-            tp: self.builtins.get(&BasicType::Void).unwrap().clone(),
+            tp: self.types.builtins.get(&BasicType::Void).unwrap().clone(),
         }
     }
 }
@@ -1372,9 +1550,9 @@ impl<'a> CodeGen<'a> {
 
         let mut maxtp = None;
 
-        let mut types = HashMap::new();
+        let mut types = IndexMap::new();
         for (name, typename) in variants{
-            let tp = Self::resolve_type(&self.builtins, &self.types, self.info, &typename);
+            let tp = self.types.resolve(&self.session.info, &typename);
             let inkwell_tp = Self::kestrel_to_inkwell_tp(self.context, &tp);
             if let Some((size, _)) = maxtp {
                 if Self::get_size_of(inkwell_tp)>size {
@@ -1431,7 +1609,7 @@ impl<'a> CodeGen<'a> {
             usertype: Some(CustomTypeInternals::Enum { variants: types, tp: structtp })
         };
 
-        self.types.insert(name, tp);
+        self.types.register(name, tp, &node.pos, &self.session.info);
     }
 }
 
@@ -1440,29 +1618,50 @@ impl<'a> CodeGen<'a> {
         let fnnode = node.data.get_data();
         let name = fnnode.raw.get("name").unwrap();
 
-        if self.functions.get(name).is_some() {
+        let rettp = if let Some(ref v) = fnnode.tp {
+            self.types.resolve(&self.session.info, v)
+        } else {
+            self.types.builtins.get(&BasicType::Void).unwrap().clone()
+        };
+
+        let args = fnnode
+            .mapping
+            .unwrap()
+            .iter()
+            .map(|(_, tp)| self.types.resolve(&self.session.info, tp))
+            .collect::<Vec<_>>();
+
+        if let Some(first) = self.functions.get(name) {
+            let signature = |params: &Vec<Type>| {
+                format!(
+                    "{}({})",
+                    name,
+                    params
+                        .iter()
+                        .map(|tp| tp.qualname())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            };
+            let msg = if first.1 .0 == args {
+                format!("Function {} is defined multiple times.", signature(&args))
+            } else {
+                format!(
+                    "Function {} conflicts with {}, overloading is not supported.",
+                    signature(&args),
+                    signature(&first.1 .0)
+                )
+            };
             raise_error_multi(
-                vec![
-                    format!("Function {} is defined multiple times.", name),
-                    "First definition here:".into(),
-                ],
+                vec![msg, "First definition here:".into()],
                 ErrorType::MultipleFunctionDefinitions,
-                vec![
-                    Some(&node.pos),
-                    Some(&self.functions.get(name).as_ref().unwrap().0.pos),
-                ],
-                self.info,
+                vec![Some(&node.pos), Some(&first.0.pos)],
+                &self.session.info,
             );
         }
 
-        let rettp = if let Some(ref v) = fnnode.tp {
-            Self::resolve_type(&self.builtins, &self.types, self.info, v)
-        } else {
-            self.builtins.get(&BasicType::Void).unwrap().clone()
-        };
-
         self.functions
-            .insert(name.clone(), (node, (vec![], rettp), None));
+            .insert(name.clone(), (node, (args, rettp), None));
     }
 
     fn create_fn(&mut self, node: &Node) {
@@ -1471,92 +1670,104 @@ impl<'a> CodeGen<'a> {
 
 
         if name == "main" {
-            let main_tp: inkwell::types::FunctionType = self.context.i32_type().fn_type(
-                &[
-                    inkwell::types::BasicMetadataTypeEnum::IntType(self.context.i32_type()),
-                    inkwell::types::BasicMetadataTypeEnum::PointerType(
-                        self.context
-                            .i32_type()
-                            .ptr_type(inkwell::AddressSpace::from(0u16))
-                            .ptr_type(inkwell::AddressSpace::from(0u16)),
+            let rettp = self.functions.get(name).unwrap().1 .1.clone();
+            if rettp.ref_n != 0 || !matches!(rettp.basictype, BasicType::Void | BasicType::I32) {
+                raise_error(
+                    &format!(
+                        "Expected 'std::void' or 'std::i32' as the return type of main, got '{}'.",
+                        rettp.qualname()
                     ),
-                ],
-                false,
-            );
-            let realmain = self.module.add_function("main", main_tp, None);
+                    ErrorType::InvalidMainReturnType,
+                    &fnnode.tp.as_ref().unwrap().pos,
+                    &self.session.info,
+                );
+            }
+
+            let main_tp = Self::create_fn_tp(self.context, &[], &rettp);
+            let realmain =
+                self.module
+                    .add_function(KESTREL_MAIN, main_tp, Some(Linkage::Private));
+            self.functions.get_mut(name).unwrap().2 = Some(realmain);
+            self.queued.insert(name.clone());
             let basic_block = self.context.append_basic_block(realmain, "");
 
-            self.hoist_defs_in_fn(fnnode.nodearr.unwrap().clone());
+            self.hoist_type_defs(fnnode.nodearr.unwrap().clone());
 
             // Mir check
             let mut mir = mir::new(
-                self.info.clone(),
-                self.builtins.clone(),
+                self.session,
                 self.types.clone(),
                 self.functions.clone(),
+                self.std_fns.clone(),
                 name.into(),
                 node.pos.clone(),
-                self.debug_mir,
             );
             let mut instructions = mir.generate(fnnode.nodearr.unwrap());
             mir::check(&mut mir, &mut instructions, None, 0);
+            self.append_mir(&mir.output);
             //
 
             self.namespaces.insert(
                 realmain,
                 Namespace {
-                    bindings: HashMap::new(),
+                    scopes: vec![HashMap::new()],
                 },
             );
 
-            self.add_attrs(realmain);
-
             self.builder.position_at_end(basic_block);
             self.block = Some(basic_block);
 
             self.cur_fnstate = Some(CurFunctionState {
                 cur_block: Some(basic_block),
                 returned: false,
-                rettp: self.builtins.get(&BasicType::I32).unwrap().clone(),
+                rettp: rettp.clone(),
+                rettp_pos: fnnode.tp.as_ref().map_or(node.pos.clone(), |tp| tp.pos.clone()),
             });
             self.cur_fn = Some(realmain);
 
             //
 
             //Compile code
+            let rt_checks = self.rt_locations.len();
             self.compile_statements(fnnode.nodearr.unwrap());
 
+            self.add_attrs(
+                realmain,
+                FnFacts {
+                    leaf: mir::is_leaf(&instructions),
+                    loops: mir::has_loop(&instructions),
+                    rt_checks: self.rt_locations.len() > rt_checks,
+                },
+            );
+
             if !self.cur_fnstate.as_ref().unwrap().returned {
-                self.builder
-                    .build_return(Some(&self.context.i32_type().const_int(0, false)));
+                if rettp.basictype == BasicType::Void {
+                    self.builder.build_return(None);
+                } else {
+                    self.builder
+                        .build_return(Some(&self.context.i32_type().const_int(0, false)));
+                }
             }
 
             //
+
+            self.add_main_wrapper(realmain);
         }
     }
 
     fn add_main_skeleton(&mut self) {
-        let main_tp: inkwell::types::FunctionType = self.context.i32_type().fn_type(
-            &[
-                inkwell::types::BasicMetadataTypeEnum::IntType(self.context.i32_type()),
-                inkwell::types::BasicMetadataTypeEnum::PointerType(
-                    self.context
-                        .i32_type()
-                        .ptr_type(inkwell::AddressSpace::from(0u16))
-                        .ptr_type(inkwell::AddressSpace::from(0u16)),
-                ),
-            ],
-            false,
-        );
-        let realmain = self.module.add_function("main", main_tp, None);
+        let main_tp: inkwell::types::FunctionType = self.context.i32_type().fn_type(&[], false);
+        let realmain = self
+            .module
+            .add_function(KESTREL_MAIN, main_tp, Some(Linkage::Private));
         let basic_block = self.context.append_basic_block(realmain, "");
 
         // Mir check
         let mut mir = mir::new(
-            self.info.clone(),
-            self.builtins.clone(),
+            self.session,
             self.types.clone(),
             self.functions.clone(),
+            self.std_fns.clone(),
             "main".into(),
             Position {
                 line: 0,
@@ -1564,21 +1775,28 @@ impl<'a> CodeGen<'a> {
                 endcol: 0,
                 opcol: None,
             },
-            self.debug_mir,
         );
 
         let mut instructions = mir.generate(&vec![]);
         mir::check(&mut mir, &mut instructions, None, 0);
+        self.append_mir(&mir.output);
         //
 
         self.namespaces.insert(
             realmain,
             Namespace {
-                bindings: HashMap::new(),
+                scopes: vec![HashMap::new()],
             },
         );
 
-        self.add_attrs(realmain);
+        self.add_attrs(
+            realmain,
+            FnFacts {
+                leaf: true,
+                loops: false,
+                rt_checks: false,
+            },
+        );
 
         self.builder.position_at_end(basic_block);
         self.block = Some(basic_block);
@@ -1586,7 +1804,13 @@ impl<'a> CodeGen<'a> {
         self.cur_fnstate = Some(CurFunctionState {
             cur_block: Some(basic_block),
             returned: false,
-            rettp: self.builtins.get(&BasicType::I32).unwrap().clone(),
+            rettp: self.types.builtins.get(&BasicType::I32).unwrap().clone(),
+            rettp_pos: Position {
+                line: 0,
+                startcol: 0,
+                endcol: 0,
+                opcol: None,
+            },
         });
         self.cur_fn = Some(realmain);
 
@@ -1596,20 +1820,53 @@ impl<'a> CodeGen<'a> {
             self.builder
                 .build_return(Some(&self.context.i32_type().const_int(0, false)));
         }
+
+        self.add_main_wrapper(realmain);
+    }
+
+    /// The C entry point: preserves argc/argv for `std` and runs the user's main.
+    fn add_main_wrapper(&mut self, kestrel_main: FunctionValue<'a>) {
+        let main_tp: inkwell::types::FunctionType = self.context.i32_type().fn_type(
+            &[
+                inkwell::types::BasicMetadataTypeEnum::IntType(self.context.i32_type()),
+                inkwell::types::BasicMetadataTypeEnum::PointerType(
+                    self.context
+                        .i8_type()
+                        .ptr_type(inkwell::AddressSpace::from(0u16))
+                        .ptr_type(inkwell::AddressSpace::from(0u16)),
+                ),
+            ],
+            false,
+        );
+        let realmain = self.module.add_function("main", main_tp, None);
+        let basic_block = self.context.append_basic_block(realmain, "");
+
+        self.add_attrs(realmain, FnFacts::default());
+
+        self.builder.position_at_end(basic_block);
+
+        self.builder.build_store(
+            self.module.get_global(ARGC_GLOBAL).unwrap().as_pointer_value(),
+            realmain.get_nth_param(0).unwrap(),
+        );
+        self.builder.build_store(
+            self.module.get_global(ARGV_GLOBAL).unwrap().as_pointer_value(),
+            realmain.get_nth_param(1).unwrap(),
+        );
+
+        let res = self
+            .builder
+            .build_call(kestrel_main, &[], "")
+            .try_as_basic_value()
+            .left()
+            .unwrap_or(self.context.i32_type().const_zero().into());
+        self.builder.build_return(Some(&res));
     }
 }
 
-pub fn generate_code(
-    module_name: &str,
-    source_name: &str,
-    ast: Vec<Node>,
-    info: &FileInfo,
-    flags: Vec<Flags>,
-    optimize: bool,
-    debug_mir: bool,
-) -> Result<(), Box<dyn Error>> {
+pub fn generate_code(session: &Session, ast: Vec<Node>) -> Result<Artifacts, Box<dyn Error>> {
     let context: inkwell::context::Context = Context::create();
-    let module: inkwell::module::Module = context.create_module(module_name);
+    let module: inkwell::module::Module = context.create_module(&session.info.name);
 
     let mut triple: String = String::from("");
     guess_host_triple::guess_host_triple()
@@ -1617,7 +1874,7 @@ pub fn generate_code(
         .unwrap_or_else(|| triple = String::from("unknown-unknown-unknown"));
 
     module.set_triple(&inkwell::targets::TargetTriple::create(triple.as_str()));
-    module.set_source_file_name(source_name);
+    module.set_source_file_name(&session.info.name);
 
     //Setup debug info
     module.add_basic_value_flag(
@@ -1625,17 +1882,29 @@ pub fn generate_code(
         FlagBehavior::Error,
         context.i32_type().const_int(3, false),
     );
+    if is_darwin(&triple) {
+        //Apple's dsymutil and lldb expect DWARF 4, while LLVM defaults to 5 elsewhere
+        module.add_basic_value_flag(
+            "Dwarf Version",
+            FlagBehavior::Warning,
+            context.i32_type().const_int(4, false),
+        );
+    }
     let (dibuilder, _) = module.create_debug_info_builder(
         true,
         DWARFSourceLanguage::C,
-        &info.name,
-        &info.dir,
+        &session.info.name,
+        &session.info.dir,
         "kestrel",
-        optimize,
+        session.profile.optimize,
         "",
         0,
         "",
-        DWARFEmissionKind::Full,
+        if session.profile.debug_info {
+            DWARFEmissionKind::Full
+        } else {
+            DWARFEmissionKind::None
+        },
         0,
         false,
         false,
@@ -1648,91 +1917,438 @@ pub fn generate_code(
         module,
         builder: context.create_builder(),
         block: None,
-        info,
+        session,
         dibuilder,
         cur_fnstate: None,
         cur_fn: None,
-        builtins: HashMap::new(),
-        types: HashMap::new(),
+        types: TypeRegistry::default(),
         extern_fns: HashMap::new(),
-        functions: HashMap::new(),
+        std_fns: HashMap::new(),
+        functions: IndexMap::new(),
+        fn_queue: VecDeque::new(),
+        queued: HashSet::new(),
         namespaces: HashMap::new(),
-        flags: flags.clone(),
-        optimized: optimize,
-        debug_mir,
+        strings: HashMap::new(),
+        mir: String::new(),
+        rt_locations: Vec::new(),
+        loops: Vec::new(),
     };
 
-    let f = OpenOptions::new()
-        .write(true)
-        .append(true)
-        .open("a.mir")
-        .expect("Unable to create MIR output file.");
-    f.set_len(0).expect("Unable to truncate MIR output file.");
-
     init_builtins(&mut codegen);
     init_extern_fns(&mut codegen);
 
-    //Pass manager (optimizer)
-    let pass_manager_builder: inkwell::passes::PassManagerBuilder =
-        inkwell::passes::PassManagerBuilder::create();
-    pass_manager_builder.set_optimization_level(inkwell::OptimizationLevel::Aggressive);
-    let manager: inkwell::passes::PassManager<Module> = inkwell::passes::PassManager::create(());
-    pass_manager_builder.populate_module_pass_manager(&manager);
-
-    //
-
     codegen.compile(ast);
+    define_rt_error(&mut codegen);
 
     //Generate debug info
     codegen.dibuilder.finalize();
 
-    //Optimize
-    unsafe { codegen.module.run_in_pass_manager(&manager) };
+    //Optimize with the new pass manager, running any additional passes after the default pipeline
+    let mut pipeline = vec![String::from("default<O3>")];
+    pipeline.extend(session.passes.iter().cloned());
+    if session.pgo == Some(Pgo::Generate) {
+        pipeline.push(String::from("pgo-instr-gen,instrprof"));
+    }
+    let machine = target_machine(&triple, RelocMode::Default)?;
+    if let Err(e) = codegen.module.run_passes(
+        &pipeline.join(","),
+        &machine,
+        PassBuilderOptions::create(),
+    ) {
+        raise_error_no_pos(
+            &format!("Invalid pass pipeline: {}", e.to_string().trim()),
+            ErrorType::InvalidFlag,
+        );
+    }
 
-    codegen.module.print_to_file(std::path::Path::new("a.ll"))?;
+    let globals = session
+        .emit
+        .contains(&Emit::Globals)
+        .then(|| dump_globals(&codegen.module, &machine.get_target_data()));
+
+    let mut llvm_ir = codegen.module.print_to_string().to_string();
+    let object = PathBuf::from("a.o");
+    let executable = PathBuf::from("a.out");
+
+    //The C API cannot give the pass builder a profile to read, so it is used by running opt on the
+    //optimized IR. Instrumentation ran on the same IR, so the profile's function hashes match.
+    let mut link_args = Vec::new();
+    match &session.pgo {
+        Some(Pgo::Generate) => {
+            link_args.push(profile_runtime(&triple).into_os_string());
+        }
+        Some(Pgo::Use(profdata)) => {
+            llvm_ir = String::from_utf8(run_tool(
+                std::process::Command::new("opt")
+                    .args(["-S", "-passes=pgo-instr-use,default<O3>"])
+                    .arg(format!("-pgo-test-profile-file={profdata}")),
+                llvm_ir.as_bytes(),
+                "opt",
+            ))?;
+        }
+        None => {}
+    }
+    link_args.extend(hardening_link_args(&session.hardening, &triple));
 
-    let mut res: std::process::Output = std::process::Command::new("llc")
-        .arg("a.ll")
-        .output()
-        .expect("Failed to execute llc");
-    if !res.status.success() {
-        eprintln!(
-            "Stderr:\n{}\n\nStdout:{}",
-            std::str::from_utf8(&res.stderr[..]).expect("Unable to convert for stderr (llc)"),
-            std::str::from_utf8(&res.stdout[..]).expect("Unable to convert for stdout (llc)")
-        );
-        panic!("Failed to run llc (exit code {})", res.status);
+    //An object file is linked by the user, so it is emitted as is
+    if session.crate_type == CrateType::Obj {
+        let object = if is_msvc(&triple) {
+            PathBuf::from("a.obj")
+        } else {
+            object
+        };
+        emit_object(&context, &triple, &llvm_ir, RelocMode::PIC, &object)?;
+        return Ok(Artifacts {
+            llvm_ir,
+            mir: codegen.mir,
+            object,
+            executable: None,
+            library: None,
+            globals,
+        });
     }
 
-    res = std::process::Command::new("gcc")
-        .arg("a.s")
-        .arg("-oa.o")
-        .arg("-c")
-        .output()
-        .expect("Failed to execute gcc");
-    if !res.status.success() {
-        eprintln!(
-            "Stderr:\n{}\n\nStdout:{}",
-            std::str::from_utf8(&res.stderr[..]).expect("Unable to convert for stderr (gcc)"),
-            std::str::from_utf8(&res.stdout[..]).expect("Unable to convert for stdout (gcc)")
+    if is_msvc(&triple) {
+        let (object, output) =
+            link_msvc(&context, &triple, &llvm_ir, &link_args, session.link_mode, session.crate_type)?;
+        let (executable, library) = match session.crate_type {
+            CrateType::Bin => (Some(output), None),
+            CrateType::Cdylib => (None, Some(output)),
+            CrateType::Obj => unreachable!(),
+        };
+        return Ok(Artifacts {
+            llvm_ir,
+            mir: codegen.mir,
+            object,
+            executable,
+            library,
+            globals,
+        });
+    }
+
+    if session.crate_type == CrateType::Cdylib {
+        let library = link_cdylib(&context, &triple, &llvm_ir, &object, &link_args)?;
+        return Ok(Artifacts {
+            llvm_ir,
+            mir: codegen.mir,
+            object,
+            executable: None,
+            library: Some(library),
+            globals,
+        });
+    }
+
+    let assembly = run_tool(
+        std::process::Command::new("llc").args(["-o", "-", "-"]),
+        llvm_ir.as_bytes(),
+        "llc",
+    );
+
+    if is_darwin(&triple) {
+        if session.link_mode != LinkMode::Dynamic {
+            raise_error_no_pos(
+                "Static linking is not supported on macOS, where libSystem is only available as a shared library.",
+                ErrorType::InvalidFlag,
+            );
+        }
+        let executable = link_darwin(&assembly, &object, &link_args, session.profile.debug_info);
+        return Ok(Artifacts {
+            llvm_ir,
+            mir: codegen.mir,
+            object,
+            executable: Some(executable),
+            library: None,
+            globals,
+        });
+    }
+
+    run_tool(
+        std::process::Command::new("gcc")
+            .args(["-xassembler", "-", "-c", "-o"])
+            .arg(&object),
+        &assembly,
+        "gcc",
+    );
+
+    run_tool(
+        std::process::Command::new("gcc")
+            .args(["-xassembler", "-", "-no-pie", "-o"])
+            .arg(&executable)
+            .args(&link_args)
+            //glibc cannot link only its runtime statically, so both modes link everything statically
+            .args(if session.link_mode == LinkMode::Dynamic {
+                &[][..]
+            } else {
+                &["-static"][..]
+            }),
+        &assembly,
+        "gcc",
+    );
+
+    Ok(Artifacts {
+        llvm_ir,
+        mir: codegen.mir,
+        object,
+        executable: Some(executable),
+        library: None,
+        globals,
+    })
+}
+
+/// Linker flags for hardening. Passing the stack protector flag to the driver links the runtime
+/// that provides `__stack_chk_fail` where the C library does not, as FORTIFY_SOURCE builds need.
+fn hardening_link_args(hardening: &[Hardening], triple: &str) -> Vec<OsString> {
+    let mut args = Vec::new();
+    for option in hardening {
+        let arg = match option {
+            //The MSVC CRT always provides the stack protector's cookie check
+            Hardening::StackProtector | Hardening::StackProtectorStrong if is_msvc(triple) => {
+                continue
+            }
+            Hardening::StackProtector => "-fstack-protector",
+            Hardening::StackProtectorStrong => "-fstack-protector-strong",
+            Hardening::Relro | Hardening::Now if is_darwin(triple) || triple.contains("windows") => {
+                raise_error_no_pos(
+                    "'relro' and 'now' are only supported when linking ELF executables",
+                    ErrorType::InvalidFlag,
+                )
+            }
+            Hardening::Relro => "-Wl,-z,relro",
+            Hardening::Now => "-Wl,-z,now",
+        };
+        args.push(arg.into());
+    }
+    args
+}
+
+pub fn is_msvc(triple: &str) -> bool {
+    triple.ends_with("windows-msvc")
+}
+
+pub fn is_darwin(triple: &str) -> bool {
+    triple.contains("apple-darwin")
+}
+
+/// On macOS, `cc` is clang and executables are always position independent, so there is no
+/// `-no-pie`. The linker leaves debug info in the object and only records where it is, so the
+/// executable is linked from the kept object, and dsymutil collects it into `a.out.dSYM`.
+fn link_darwin(
+    assembly: &[u8],
+    object: &Path,
+    link_args: &[OsString],
+    debug_info: bool,
+) -> PathBuf {
+    let executable = PathBuf::from("a.out");
+
+    run_tool(
+        std::process::Command::new("cc")
+            .args(["-xassembler", "-", "-c", "-o"])
+            .arg(object),
+        assembly,
+        "cc",
+    );
+
+    run_tool(
+        std::process::Command::new("cc")
+            .arg("-o")
+            .arg(&executable)
+            .arg(object)
+            .args(link_args),
+        &[],
+        "cc",
+    );
+
+    if debug_info {
+        run_tool(
+            std::process::Command::new("dsymutil").arg(&executable),
+            &[],
+            "dsymutil",
         );
-        panic!("Failed to run gcc (exit code {})", res.status);
     }
 
-    res = std::process::Command::new("gcc")
-        .arg("a.s")
-        .arg("-oa.out")
-        .arg("-no-pie")
+    executable
+}
+
+/// There is no gcc to assemble with on MSVC targets, so the object is emitted as COFF by LLVM
+/// and linked with lld-link, or with MSVC's link.exe if lld is not installed.
+fn link_msvc(
+    context: &Context,
+    triple: &str,
+    llvm_ir: &str,
+    link_args: &[OsString],
+    link_mode: LinkMode,
+    crate_type: CrateType,
+) -> Result<(PathBuf, PathBuf), Box<dyn Error>> {
+    let object = PathBuf::from("a.obj");
+    let output = PathBuf::from(match crate_type {
+        CrateType::Bin => "a.exe",
+        CrateType::Cdylib => "a.dll",
+        CrateType::Obj => unreachable!(),
+    });
+
+    emit_object(context, triple, llvm_ir, RelocMode::Default, &object)?;
+
+    let linker = if std::process::Command::new("lld-link")
+        .arg("--version")
+        .output()
+        .is_ok()
+    {
+        "lld-link"
+    } else {
+        "link.exe"
+    };
+    //System DLLs such as kernel32 are always linked dynamically, so --static only makes the CRT static
+    let crt = if link_mode == LinkMode::Dynamic {
+        ["/defaultlib:msvcrt", "/defaultlib:ucrt", "/defaultlib:vcruntime"]
+    } else {
+        ["/defaultlib:libcmt", "/defaultlib:libucrt", "/defaultlib:libvcruntime"]
+    };
+    run_tool(
+        std::process::Command::new(linker)
+            .args(crt)
+            .args([
+                "/nologo",
+                "/subsystem:console",
+                "/defaultlib:oldnames",
+                //printf and friends are inline in the UCRT headers, so C code never needs this
+                "/defaultlib:legacy_stdio_definitions",
+            ])
+            .args(if crate_type == CrateType::Cdylib {
+                &["/dll"][..]
+            } else {
+                &[][..]
+            })
+            .arg(format!("/out:{}", output.display()))
+            .arg(&object)
+            .args(link_args),
+        &[],
+        linker,
+    );
+
+    Ok((object, output))
+}
+
+/// Shared libraries are linked from an object that LLVM emits as position-independent code, so
+/// that the library can be loaded at any address.
+fn link_cdylib(
+    context: &Context,
+    triple: &str,
+    llvm_ir: &str,
+    object: &Path,
+    link_args: &[OsString],
+) -> Result<PathBuf, Box<dyn Error>> {
+    emit_object(context, triple, llvm_ir, RelocMode::PIC, object)?;
+
+    let (library, shared) = if is_darwin(triple) {
+        (PathBuf::from("liba.dylib"), "-dynamiclib")
+    } else {
+        (PathBuf::from("liba.so"), "-shared")
+    };
+    run_tool(
+        std::process::Command::new("cc")
+            .arg(shared)
+            .arg("-o")
+            .arg(&library)
+            .arg(object)
+            .args(link_args),
+        &[],
+        "cc",
+    );
+
+    Ok(library)
+}
+
+/// Emits the IR as an object file for `triple` with LLVM's TargetMachine.
+fn emit_object(
+    context: &Context,
+    triple: &str,
+    llvm_ir: &str,
+    reloc: RelocMode,
+    object: &Path,
+) -> Result<(), Box<dyn Error>> {
+    //Parse the IR back, since opt may have rewritten it for PGO
+    let buffer = MemoryBuffer::create_from_memory_range_copy(llvm_ir.as_bytes(), "a.ll");
+    let module = context.create_module_from_ir(buffer)?;
+
+    target_machine(triple, reloc)?.write_to_file(&module, FileType::Object, object)?;
+
+    Ok(())
+}
+
+fn target_machine(triple: &str, reloc: RelocMode) -> Result<TargetMachine, Box<dyn Error>> {
+    Target::initialize_native(&InitializationConfig::default())?;
+    let target_triple = TargetTriple::create(triple);
+    let machine = Target::from_triple(&target_triple)?
+        .create_target_machine(
+            &target_triple,
+            "generic",
+            "",
+            OptimizationLevel::Default,
+            reloc,
+            CodeModel::Default,
+        )
+        .ok_or_else(|| format!("Unable to create a target machine for '{triple}'."))?;
+
+    Ok(machine)
+}
+
+/// The LLVM profile runtime that instrumented executables are linked against, from LLVM's clang
+/// resource directory.
+fn profile_runtime(triple: &str) -> PathBuf {
+    let name = if is_msvc(triple) {
+        "lib/windows/clang_rt.profile-x86_64.lib"
+    } else if is_darwin(triple) {
+        "lib/darwin/libclang_rt.profile_osx.a"
+    } else {
+        "lib/linux/libclang_rt.profile-x86_64.a"
+    };
+    let libdir = std::process::Command::new("llvm-config")
+        .arg("--libdir")
         .output()
-        .expect("Failed to execute gcc");
+        .ok()
+        .and_then(|res| String::from_utf8(res.stdout).ok());
+    let runtime = libdir.and_then(|libdir| {
+        std::fs::read_dir(PathBuf::from(libdir.trim()).join("clang"))
+            .ok()?
+            .filter_map(|version| version.ok())
+            .map(|version| version.path().join(name))
+            .find(|runtime| runtime.is_file())
+    });
+    match runtime {
+        Some(runtime) => runtime,
+        None => raise_error_no_pos(
+            "Instrumenting for PGO requires LLVM's profile runtime (libclang_rt.profile), which was not found.",
+            ErrorType::ProfileRuntimeNotFound,
+        ),
+    }
+}
+
+/// Runs an external tool with `input` on its stdin, returning its stdout.
+fn run_tool(command: &mut std::process::Command, input: &[u8], name: &str) -> Vec<u8> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|_| panic!("Failed to execute {name}"));
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input)
+        .unwrap_or_else(|_| panic!("Unable to write to stdin ({name})"));
+    let res = child
+        .wait_with_output()
+        .unwrap_or_else(|_| panic!("Failed to execute {name}"));
     if !res.status.success() {
         eprintln!(
             "Stderr:\n{}\n\nStdout:{}",
-            std::str::from_utf8(&res.stderr[..]).expect("Unable to convert for stderr (gcc)"),
-            std::str::from_utf8(&res.stdout[..]).expect("Unable to convert for stdout (gcc)")
+            std::str::from_utf8(&res.stderr[..])
+                .unwrap_or_else(|_| panic!("Unable to convert for stderr ({name})")),
+            std::str::from_utf8(&res.stdout[..])
+                .unwrap_or_else(|_| panic!("Unable to convert for stdout ({name})"))
         );
-        panic!("Failed to run gcc (exit code {})", res.status);
+        panic!("Failed to run {name} (exit code {})", res.status);
     }
-
-    Ok(())
+    res.stdout
 }
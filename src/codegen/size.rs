@@ -0,0 +1,68 @@
+use std::path::Path;
+
+use super::{run_tool, Artifacts};
+
+/// Prints the section sizes of the linked output and the size of each function in the object
+/// file, largest first, so the cost of runtime checks can be seen.
+pub fn print_size(artifacts: &Artifacts) {
+    let output = artifacts.output();
+
+    //Berkeley format: a header, then text, data, bss, dec, hex and the file name
+    let sections = String::from_utf8_lossy(&run_tool(
+        std::process::Command::new("size").arg(output),
+        &[],
+        "size",
+    ))
+    .into_owned();
+    let sizes = sections
+        .lines()
+        .nth(1)
+        .unwrap_or_default()
+        .split_whitespace()
+        .take(3)
+        .collect::<Vec<_>>();
+    if let [text, data, bss] = sizes[..] {
+        println!(
+            "{}: text {text}, data {data}, bss {bss} bytes",
+            output.display()
+        );
+    }
+
+    let functions = function_sizes(&artifacts.object);
+    if !functions.is_empty() {
+        println!(
+            "Functions in {}, largest first:",
+            artifacts.object.display()
+        );
+    }
+    for (size, name) in functions {
+        println!("{size:>10}  {name}");
+    }
+}
+
+/// The size of each function defined in `object`, largest first.
+fn function_sizes(object: &Path) -> Vec<(u64, String)> {
+    if !object.is_file() {
+        return Vec::new();
+    }
+
+    let symbols = String::from_utf8_lossy(&run_tool(
+        std::process::Command::new("nm")
+            .args(["--print-size", "--radix=d"])
+            .arg(object),
+        &[],
+        "nm",
+    ))
+    .into_owned();
+
+    //Only defined symbols have a size, listed as: address, size, type and name
+    let mut functions = symbols
+        .lines()
+        .filter_map(|line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+            [_, size, "T" | "t" | "W", name] => Some((size.parse::<u64>().ok()?, name.to_string())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    functions.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    functions
+}
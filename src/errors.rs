@@ -1,6 +1,12 @@
 use colored::Colorize;
+use unicode_width::UnicodeWidthChar;
 
-use crate::utils::{FileInfo, Position};
+use strum::{EnumIter, IntoEnumIterator};
+
+use crate::{
+    session::Session,
+    utils::{FileInfo, Position},
+};
 
 #[derive(Clone)]
 pub enum ErrorType {
@@ -28,6 +34,32 @@ pub enum ErrorType {
     FloatingElif,
     ValueNotLiveEnough,
     MissingElseClause,
+    InvalidManifest,
+    ProfileNotFound,
+    InvalidMainReturnType,
+    UnterminatedString,
+    MalformedNumericLiteral,
+    InvalidEscape,
+    InvalidCharacter,
+    WrongArgumentCount,
+    InvalidCast,
+    UnsafeRequired,
+    MacroNotFound,
+    FormatMismatch,
+    UninitializedBinding,
+    MissingReturn,
+    NestingTooDeep,
+    MultipleParameterDefinitions,
+    MultipleVariantDefinitions,
+    ReservedName,
+    ProfileDataNotFound,
+    ProfileRuntimeNotFound,
+    MultipleTypeDefinitions,
+    MixedSignedness,
+    NonBoolCondition,
+    InvalidAttribute,
+    JumpOutsideLoop,
+    JumpWithValue,
 }
 
 impl std::fmt::Display for ErrorType {
@@ -62,52 +94,129 @@ pub fn repr_err(tp: ErrorType) -> &'static str {
         ErrorType::FloatingElif => "floating elif is not allowed here",
         ErrorType::ValueNotLiveEnough => "value does not live long enough",
         ErrorType::MissingElseClause => "missing else clause",
+        ErrorType::InvalidManifest => "invalid manifest",
+        ErrorType::ProfileNotFound => "profile not found",
+        ErrorType::InvalidMainReturnType => "invalid return type for main",
+        ErrorType::UnterminatedString => "unterminated string literal",
+        ErrorType::MalformedNumericLiteral => "malformed numeric literal",
+        ErrorType::InvalidEscape => "invalid escape sequence",
+        ErrorType::InvalidCharacter => "invalid character",
+        ErrorType::WrongArgumentCount => "wrong number of arguments",
+        ErrorType::InvalidCast => "invalid cast",
+        ErrorType::UnsafeRequired => "unsafe block required",
+        ErrorType::MacroNotFound => "macro not found",
+        ErrorType::FormatMismatch => "format string mismatch",
+        ErrorType::UninitializedBinding => "binding is not initialized",
+        ErrorType::MissingReturn => "missing return",
+        ErrorType::NestingTooDeep => "nesting too deep",
+        ErrorType::MultipleParameterDefinitions => "multiple parameter definitions are disallowed",
+        ErrorType::MultipleVariantDefinitions => "multiple variant definitions are disallowed",
+        ErrorType::ReservedName => "reserved name",
+        ErrorType::ProfileDataNotFound => "profile data not found",
+        ErrorType::ProfileRuntimeNotFound => "profile runtime not found",
+        ErrorType::MultipleTypeDefinitions => "multiple type definitions are disallowed",
+        ErrorType::MixedSignedness => "mixed signed and unsigned integers",
+        ErrorType::NonBoolCondition => "condition is not a bool",
+        ErrorType::InvalidAttribute => "invalid attribute",
+        ErrorType::JumpOutsideLoop => "break or continue outside of a loop",
+        ErrorType::JumpWithValue => "break or continue with a value",
     }
 }
 
-#[derive(Clone)]
-pub enum WarningType {}
+#[derive(Clone, Copy, PartialEq, Eq, Debug, EnumIter)]
+pub enum WarningType {
+    ConstantCondition,
+    UnusedMut,
+    Shadowing,
+}
+
+impl WarningType {
+    /// The name used to enable or disable the warning with `--warn` and `--allow`.
+    pub fn lint_name(&self) -> &'static str {
+        match self {
+            WarningType::ConstantCondition => "constant-condition",
+            WarningType::UnusedMut => "unused-mut",
+            WarningType::Shadowing => "shadowing",
+        }
+    }
+
+    pub fn from_lint_name(name: &str) -> Option<Self> {
+        WarningType::iter().find(|tp| tp.lint_name() == name)
+    }
+
+    pub fn enabled_by_default(&self) -> bool {
+        !matches!(self, WarningType::Shadowing)
+    }
+}
 
 impl std::fmt::Display for WarningType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", repr_warn(self.clone()))
+        write!(f, "{}", repr_warn(*self))
     }
 }
 
 pub fn repr_warn(tp: WarningType) -> &'static str {
-    match tp {}
+    match tp {
+        WarningType::ConstantCondition => "constant condition",
+        WarningType::UnusedMut => "binding does not need to be mutable",
+        WarningType::Shadowing => "binding shadows another binding",
+    }
 }
 
-pub fn raise_error(
-    error: &str,
-    errtp: ErrorType,
-    pos: &crate::utils::Position,
-    info: &crate::utils::FileInfo,
-) -> ! {
-    let header: String = format!("error[E{:0>3}]: {}", errtp as u8 + 1, error);
-    let location: String = format!("{}:{}:{}", info.name, pos.line + 1, pos.startcol + 1);
-    eprintln!("{}", header.red().bold());
-    eprintln!("{}", location.red());
-
+fn print_snippet(pos: &Position, info: &FileInfo) {
     let collected = info.data.clone().collect::<Vec<_>>();
     let lines = Vec::from_iter(collected.split(|num| *num == '\n'));
 
-    let snippet: String = format!(
-        "{}",
-        String::from_iter(lines.get(pos.line).unwrap().to_vec()).blue()
-    );
+    //Tabs are one column in a Position, so shift the carets by the extra width of each tab
+    let mut snippet = String::new();
+    let mut startcol = pos.startcol;
+    let mut endcol = pos.endcol;
+    let mut col = 0;
+    for cur in lines.get(pos.line).unwrap().iter() {
+        match cur {
+            '\r' => {}
+            '\t' => {
+                snippet.push_str(&" ".repeat(info.tab_width));
+                if col < pos.startcol {
+                    startcol += info.tab_width - 1;
+                }
+                if col < pos.endcol {
+                    endcol += info.tab_width - 1;
+                }
+                col += 1;
+            }
+            _ => {
+                snippet.push(*cur);
+                col += UnicodeWidthChar::width(*cur).unwrap_or(0);
+            }
+        }
+    }
 
     let mut arrows: String = String::new();
-    for idx in 0..snippet.len() {
-        if idx >= pos.startcol && idx < pos.endcol {
+    for idx in 0..snippet.len().max(endcol) {
+        if idx >= startcol && idx < endcol {
             arrows += "^";
         } else {
             arrows += " ";
         }
     }
     let linestr = (pos.line + 1).to_string().blue().bold();
-    eprintln!("{} | {}", linestr, snippet);
+    eprintln!("{} | {}", linestr, snippet.blue());
     eprintln!("{} | {}", " ".repeat(linestr.len()), arrows.green());
+}
+
+pub fn raise_error(
+    error: &str,
+    errtp: ErrorType,
+    pos: &crate::utils::Position,
+    info: &crate::utils::FileInfo,
+) -> ! {
+    let header: String = format!("error[E{:0>3}]: {}", errtp as u8 + 1, error);
+    let location: String = format!("{}:{}:{}", info.name, pos.line + 1, pos.startcol + 1);
+    eprintln!("{}", header.red().bold());
+    eprintln!("{}", location.red());
+
+    print_snippet(pos, info);
     std::process::exit(1);
 }
 
@@ -145,25 +254,36 @@ pub fn raise_error_multi(
         }
         eprintln!("{}", location.red());
 
-        let collected = info.data.clone().collect::<Vec<_>>();
-        let lines = Vec::from_iter(collected.split(|num| *num == '\n'));
+        print_snippet(pos, info);
+    }
+    std::process::exit(1);
+}
 
-        let snippet: String = format!(
-            "{}",
-            String::from_iter(lines.get(pos.line).unwrap().to_vec()).blue()
-        );
+/// Like `raise_error_multi`, but reports a warning and lets compilation continue. Nothing is
+/// printed if the warning's lint is not enabled for the session.
+pub fn raise_warning_multi(
+    warn: Vec<String>,
+    warntp: WarningType,
+    pos: Vec<Option<&Position>>,
+    session: &Session,
+) {
+    if !session.lints.contains(&warntp) {
+        return;
+    }
+    let info = &session.info;
+    for (i, (warning, pos)) in std::iter::zip(&warn, pos).enumerate() {
+        if i == 0 {
+            let header: String = format!("warning[W{:0>3}]: {}", warntp as u8 + 1, warning);
+            eprintln!("{}", header.yellow().bold());
+        } else {
+            eprintln!("{}", warning.yellow());
+        }
+        if let Some(pos) = pos {
+            let location: String =
+                format!("{}:{}:{}", info.name, pos.line + 1, pos.startcol + 1);
+            eprintln!("{}", location.yellow());
 
-        let mut arrows: String = String::new();
-        for idx in 0..snippet.len() {
-            if idx >= pos.startcol && idx < pos.endcol {
-                arrows += "^";
-            } else {
-                arrows += " ";
-            }
+            print_snippet(pos, info);
         }
-        let linestr = (pos.line + 1).to_string().blue().bold();
-        eprintln!("{} | {}", linestr, snippet);
-        eprintln!("{} | {}", " ".repeat(linestr.len()), arrows.green());
     }
-    std::process::exit(1);
 }
@@ -4,7 +4,8 @@ use std::str::Chars;
 
 use crate::{
     errors::{raise_error, ErrorType},
-    utils::{FileInfo, Position},
+    session::Session,
+    utils::Position,
 };
 
 #[derive(Clone, PartialEq, Debug)]
@@ -36,6 +37,28 @@ pub enum TokenType {
     NotEqual,
     Colon,
     Asterisk,
+    DoubleColon,
+    Str,
+    F32,
+    F64,
+    Semicolon,
+    HashBracket,
+    LBracket,
+    RBracket,
+    DoublePipe,
+    Hyphen,
+    Slash,
+    Percent,
+}
+
+//The single authoritative keyword table, shared with the parser
+pub const KEYWORDS: &[&str] = &[
+    "let", "mut", "true", "false", "fn", "return", "if", "else", "elif", "enum", "const", "as",
+    "unsafe", "export", "type", "while", "loop", "break", "continue",
+];
+
+pub fn is_keyword(name: &str) -> bool {
+    KEYWORDS.contains(&name)
 }
 
 pub struct Lexer<'a> {
@@ -43,7 +66,7 @@ pub struct Lexer<'a> {
     pub line: usize,
     pub col: usize,
     pub chars: Chars<'a>,
-    pub info: FileInfo<'a>,
+    pub session: &'a Session<'a>,
 }
 
 #[derive(Clone, Debug)]
@@ -90,28 +113,47 @@ impl std::fmt::Display for TokenType {
             TokenType::NotEqual => write!(f, "notequal"),
             TokenType::Colon => write!(f, "colon"),
             TokenType::Asterisk => write!(f, "asterisk"),
+            TokenType::DoubleColon => write!(f, "doublecolon"),
+            TokenType::Str => write!(f, "str"),
+            TokenType::F32 => write!(f, "f32"),
+            TokenType::F64 => write!(f, "f64"),
+            TokenType::Semicolon => write!(f, "semicolon"),
+            TokenType::HashBracket => write!(f, "hashbracket"),
+            TokenType::LBracket => write!(f, "lbracket"),
+            TokenType::RBracket => write!(f, "rbracket"),
+            TokenType::DoublePipe => write!(f, "doublepipe"),
+            TokenType::Hyphen => write!(f, "hyphen"),
+            TokenType::Slash => write!(f, "slash"),
+            TokenType::Percent => write!(f, "percent"),
         }
     }
 }
 
-pub fn new<'a>(info: &mut crate::utils::FileInfo<'a>) -> Lexer<'a> {
-    let mut chars = info.data.clone();
+pub fn new<'a>(session: &'a Session<'a>) -> Lexer<'a> {
+    let mut chars = session.info.data.clone();
     let current = chars.next().unwrap_or('\0');
     Lexer {
         current,
         line: 0,
         col: 0,
         chars,
-        info: info.clone(),
+        session,
+    }
+}
+
+//Columns count each tab as one column, tab expansion is done when rendering errors
+fn char_width(cur: char) -> usize {
+    match cur {
+        '\t' => 1,
+        '\n' | '\r' => 0,
+        _ => unicode_width::UnicodeWidthChar::width(cur).unwrap_or(0),
     }
 }
 
 fn advance(lexer: &mut Lexer) {
     let next = lexer.chars.next();
 
-    if lexer.current != '\n' && lexer.current != '\r' {
-        lexer.col += unicode_width::UnicodeWidthChar::width(lexer.current).unwrap();
-    }
+    lexer.col += char_width(lexer.current);
 
     if next.is_none() {
         lexer.current = '\0';
@@ -120,7 +162,8 @@ fn advance(lexer: &mut Lexer) {
 
     let next = next.unwrap();
 
-    if lexer.current == '\n' || lexer.current == '\r' {
+    //Only '\n' ends a line, so a CRLF pair counts as one newline
+    if lexer.current == '\n' {
         lexer.line += 1;
         lexer.col = 0;
     }
@@ -141,28 +184,35 @@ pub fn print_tokens(len: usize, tokens: &Vec<Token>) {
     println!("========================");
 }
 
+//Identifiers follow the XID rules, but non-ASCII symbols such as emoji are also allowed
+fn is_identi_symbol(cur: char) -> bool {
+    !cur.is_ascii() && !cur.is_whitespace() && !cur.is_control()
+}
+
+pub fn is_identi_start(cur: char) -> bool {
+    cur == '_' || unicode_ident::is_xid_start(cur) || is_identi_symbol(cur)
+}
+
 pub fn is_identi(cur: char) -> bool {
-    !(cur == '+'
-        || cur == '\n'
-        || cur == '='
-        || cur.is_whitespace()
-        || cur == '&'
-        || cur == '!'
-        || cur == '('
-        || cur == ')'
-        || cur == '{'
-        || cur == '}'
-        || cur == ':'
-        || cur == ',')
+    unicode_ident::is_xid_continue(cur) || is_identi_symbol(cur)
+}
+
+fn invalid_character_hint(cur: char) -> Option<&'static str> {
+    match cur {
+        '\'' => Some("use '\"' for string literals"),
+        _ => None,
+    }
 }
 
-pub fn generate_tokens(lexer: &mut Lexer, kwds: &[String]) -> (usize, Vec<Token>) {
+pub fn generate_tokens(lexer: &mut Lexer) -> (usize, Vec<Token>) {
     let mut tokens: Vec<Token> = Vec::new();
+    //Newlines directly inside parentheses do not end a statement
+    let mut brackets: Vec<char> = Vec::new();
 
     while lexer.current != '\0' {
         let cur = lexer.current;
 
-        if cur.is_ascii_digit() {
+        if cur.is_ascii_digit() || is_negative_number(lexer, &tokens) {
             tokens.push(make_number(lexer));
         } else if cur == '+' {
             tokens.push(Token {
@@ -182,6 +232,62 @@ pub fn generate_tokens(lexer: &mut Lexer, kwds: &[String]) -> (usize, Vec<Token>
                 },
             });
             advance(lexer);
+        } else if cur == '-' {
+            tokens.push(Token {
+                data: String::from("-"),
+                tp: TokenType::Hyphen,
+                start: Position {
+                    line: lexer.line,
+                    startcol: lexer.col,
+                    endcol: lexer.col + 1,
+                    opcol: None,
+                },
+                end: Position {
+                    line: lexer.line,
+                    startcol: lexer.col,
+                    endcol: lexer.col + 1,
+                    opcol: None,
+                },
+            });
+            advance(lexer);
+        } else if cur == '/' {
+            tokens.push(Token {
+                data: String::from("/"),
+                tp: TokenType::Slash,
+                start: Position {
+                    line: lexer.line,
+                    startcol: lexer.col,
+                    endcol: lexer.col + 1,
+                    opcol: None,
+                },
+                end: Position {
+                    line: lexer.line,
+                    startcol: lexer.col,
+                    endcol: lexer.col + 1,
+                    opcol: None,
+                },
+            });
+            advance(lexer);
+        } else if cur == '%' {
+            tokens.push(Token {
+                data: String::from("%"),
+                tp: TokenType::Percent,
+                start: Position {
+                    line: lexer.line,
+                    startcol: lexer.col,
+                    endcol: lexer.col + 1,
+                    opcol: None,
+                },
+                end: Position {
+                    line: lexer.line,
+                    startcol: lexer.col,
+                    endcol: lexer.col + 1,
+                    opcol: None,
+                },
+            });
+            advance(lexer);
+        } else if cur == '\n' && brackets.last() == Some(&'(') {
+            advance(lexer);
         } else if cur == '\n' {
             tokens.push(Token {
                 data: String::from("\\n"),
@@ -284,7 +390,27 @@ pub fn generate_tokens(lexer: &mut Lexer, kwds: &[String]) -> (usize, Vec<Token>
                 },
             });
             advance(lexer);
+        } else if cur == '|' && lexer.chars.clone().next() == Some('|') {
+            tokens.push(Token {
+                data: String::from("||"),
+                tp: TokenType::DoublePipe,
+                start: Position {
+                    line: lexer.line,
+                    startcol: lexer.col,
+                    endcol: lexer.col + 2,
+                    opcol: None,
+                },
+                end: Position {
+                    line: lexer.line,
+                    startcol: lexer.col,
+                    endcol: lexer.col + 2,
+                    opcol: None,
+                },
+            });
+            advance(lexer);
+            advance(lexer);
         } else if cur == '(' {
+            brackets.push(cur);
             tokens.push(Token {
                 data: String::from("("),
                 tp: TokenType::LParen,
@@ -303,6 +429,9 @@ pub fn generate_tokens(lexer: &mut Lexer, kwds: &[String]) -> (usize, Vec<Token>
             });
             advance(lexer);
         } else if cur == ')' {
+            if brackets.last() == Some(&'(') {
+                brackets.pop();
+            }
             tokens.push(Token {
                 data: String::from(")"),
                 tp: TokenType::RParen,
@@ -321,6 +450,7 @@ pub fn generate_tokens(lexer: &mut Lexer, kwds: &[String]) -> (usize, Vec<Token>
             });
             advance(lexer);
         } else if cur == '{' {
+            brackets.push(cur);
             tokens.push(Token {
                 data: String::from("{"),
                 tp: TokenType::LCurly,
@@ -339,6 +469,9 @@ pub fn generate_tokens(lexer: &mut Lexer, kwds: &[String]) -> (usize, Vec<Token>
             });
             advance(lexer);
         } else if cur == '}' {
+            if brackets.last() == Some(&'{') {
+                brackets.pop();
+            }
             tokens.push(Token {
                 data: String::from("}"),
                 tp: TokenType::RCurly,
@@ -375,9 +508,42 @@ pub fn generate_tokens(lexer: &mut Lexer, kwds: &[String]) -> (usize, Vec<Token>
             });
             advance(lexer);
         } else if cur == ':' {
+            let startcol = lexer.col;
+            let line = lexer.line;
+            let mut endcol = lexer.col + 1;
+            let mut data = String::from(":");
+            let mut tp = TokenType::Colon;
+
+            advance(lexer);
+
+            if lexer.current == ':' {
+                endcol = lexer.col + 1;
+                data.push(':');
+                tp = TokenType::DoubleColon;
+
+                advance(lexer);
+            }
+
+            tokens.push(Token {
+                data,
+                tp,
+                start: Position {
+                    line,
+                    startcol,
+                    endcol,
+                    opcol: None,
+                },
+                end: Position {
+                    line,
+                    startcol,
+                    endcol,
+                    opcol: None,
+                },
+            });
+        } else if cur == ';' {
             tokens.push(Token {
-                data: String::from(":"),
-                tp: TokenType::Colon,
+                data: String::from(";"),
+                tp: TokenType::Semicolon,
                 start: Position {
                     line: lexer.line,
                     startcol: lexer.col,
@@ -410,14 +576,94 @@ pub fn generate_tokens(lexer: &mut Lexer, kwds: &[String]) -> (usize, Vec<Token>
                 },
             });
             advance(lexer);
+        } else if cur == '"' {
+            tokens.push(make_string(lexer));
         } else if cur == '#' {
-            while lexer.current != '\n' {
+            let startcol = lexer.col;
+            let line = lexer.line;
+
+            advance(lexer);
+
+            //'#[' opens an attribute, any other '#' starts a comment
+            if lexer.current == '[' {
+                tokens.push(Token {
+                    data: String::from("#["),
+                    tp: TokenType::HashBracket,
+                    start: Position {
+                        line,
+                        startcol,
+                        endcol: startcol + 2,
+                        opcol: None,
+                    },
+                    end: Position {
+                        line,
+                        startcol,
+                        endcol: startcol + 2,
+                        opcol: None,
+                    },
+                });
                 advance(lexer);
+            } else {
+                while lexer.current != '\n' && lexer.current != '\0' {
+                    advance(lexer);
+                }
             }
-        } else if !cur.is_whitespace() {
-            tokens.push(make_identifier(lexer, kwds));
-        } else {
+        } else if cur == '[' {
+            tokens.push(Token {
+                data: String::from("["),
+                tp: TokenType::LBracket,
+                start: Position {
+                    line: lexer.line,
+                    startcol: lexer.col,
+                    endcol: lexer.col + 1,
+                    opcol: None,
+                },
+                end: Position {
+                    line: lexer.line,
+                    startcol: lexer.col,
+                    endcol: lexer.col + 1,
+                    opcol: None,
+                },
+            });
+            advance(lexer);
+        } else if cur == ']' {
+            tokens.push(Token {
+                data: String::from("]"),
+                tp: TokenType::RBracket,
+                start: Position {
+                    line: lexer.line,
+                    startcol: lexer.col,
+                    endcol: lexer.col + 1,
+                    opcol: None,
+                },
+                end: Position {
+                    line: lexer.line,
+                    startcol: lexer.col,
+                    endcol: lexer.col + 1,
+                    opcol: None,
+                },
+            });
+            advance(lexer);
+        } else if is_identi_start(cur) {
+            tokens.push(make_identifier(lexer));
+        } else if cur.is_whitespace() {
             advance(lexer);
+        } else {
+            let mut msg = format!("Invalid character '{}' (U+{:04X}).", cur, cur as u32);
+            if let Some(hint) = invalid_character_hint(cur) {
+                msg = format!("{msg} Hint: {hint}.");
+            }
+            raise_error(
+                &msg,
+                ErrorType::InvalidCharacter,
+                &Position {
+                    line: lexer.line,
+                    startcol: lexer.col,
+                    endcol: lexer.col + 1,
+                    opcol: None,
+                },
+                &lexer.session.info,
+            );
         }
     }
 
@@ -441,12 +687,77 @@ pub fn generate_tokens(lexer: &mut Lexer, kwds: &[String]) -> (usize, Vec<Token>
     (tokens.len(), tokens)
 }
 
+//A '-' directly followed by a digit is part of the literal, unless it follows an operand
+fn is_negative_number(lexer: &Lexer, tokens: &[Token]) -> bool {
+    let follows_operand = matches!(
+        tokens.last().map(|x| &x.tp),
+        Some(
+            TokenType::I8
+                | TokenType::I16
+                | TokenType::I32
+                | TokenType::I64
+                | TokenType::I128
+                | TokenType::U8
+                | TokenType::U16
+                | TokenType::U32
+                | TokenType::U64
+                | TokenType::U128
+                | TokenType::F32
+                | TokenType::F64
+                | TokenType::Identifier
+                | TokenType::RParen
+                | TokenType::Str
+        )
+    );
+
+    lexer.current == '-'
+        && lexer.chars.clone().next().is_some_and(|x| x.is_ascii_digit())
+        && !follows_operand
+}
+
+//Push a run of digits, which may be separated by single underscores
+fn make_digits(lexer: &mut Lexer, data: &mut String) {
+    let mut last_underscore: Option<usize> = None;
+    while lexer.current.is_ascii_digit() || lexer.current == '_' {
+        if lexer.current == '_' {
+            if last_underscore == Some(lexer.col - 1) {
+                raise_error(
+                    "Consecutive underscores in numeric literal.",
+                    ErrorType::MalformedNumericLiteral,
+                    &Position {
+                        line: lexer.line,
+                        startcol: lexer.col - 1,
+                        endcol: lexer.col + 1,
+                        opcol: None,
+                    },
+                    &lexer.session.info,
+                );
+            }
+            last_underscore = Some(lexer.col);
+        } else {
+            data.push(lexer.current);
+        }
+        advance(lexer);
+    }
+
+    if let Some(col) = last_underscore.filter(|col| *col == lexer.col - 1) {
+        raise_error(
+            "Trailing underscore in numeric literal.",
+            ErrorType::MalformedNumericLiteral,
+            &Position {
+                line: lexer.line,
+                startcol: col,
+                endcol: col + 1,
+                opcol: None,
+            },
+            &lexer.session.info,
+        );
+    }
+}
+
 fn make_number(lexer: &mut Lexer) -> Token {
-    let start_col = lexer.col;
     let mut data: String = String::from("");
 
-    let mut tp: TokenType = TokenType::I32;
-
     let start = Position {
         line: lexer.line,
         startcol: lexer.col,
@@ -454,89 +765,266 @@ fn make_number(lexer: &mut Lexer) -> Token {
         opcol: None,
     };
 
-    while lexer.current.is_numeric() || lexer.current == '_' {
+    if lexer.current == '-' {
         data.push(lexer.current);
         advance(lexer);
-        if lexer.current == 'i' {
+    }
+
+    make_digits(lexer, &mut data);
+
+    let mut is_float = false;
+
+    //Only a '.' followed by a digit is a decimal point, so that `.` stays free for field access
+    if lexer.current == '.' && lexer.chars.clone().next().is_some_and(|x| x.is_ascii_digit()) {
+        is_float = true;
+        data.push(lexer.current);
+        advance(lexer);
+        make_digits(lexer, &mut data);
+    }
+
+    if lexer.current == 'e' || lexer.current == 'E' {
+        let mut chars = lexer.chars.clone();
+        let is_exponent = match chars.next() {
+            Some('+') | Some('-') => chars.next().is_some_and(|x| x.is_ascii_digit()),
+            Some(x) => x.is_ascii_digit(),
+            None => false,
+        };
+
+        if is_exponent {
+            is_float = true;
+            data.push('e');
             advance(lexer);
-            let mut specified_type = String::new();
-            while lexer.current.is_numeric() {
-                specified_type.push(lexer.current);
+            if lexer.current == '+' || lexer.current == '-' {
+                data.push(lexer.current);
                 advance(lexer);
             }
-            match specified_type.as_str() {
-                "8" => {
-                    tp = TokenType::I8;
-                }
-                "16" => {
-                    tp = TokenType::I16;
-                }
-                "32" => {
-                    tp = TokenType::I32;
-                }
-                "64" => {
-                    tp = TokenType::I64;
-                }
-                "128" => {
-                    tp = TokenType::I128;
-                }
-                _ => {
-                    raise_error(
-                        &format!("Invalid specified type i{}.", specified_type),
-                        ErrorType::InvalidSpecifiedNumericType,
-                        &Position {
-                            line: lexer.line,
-                            startcol: start_col,
-                            endcol: lexer.col,
-                            opcol: None,
-                        },
-                        &lexer.info,
-                    );
+            make_digits(lexer, &mut data);
+        }
+    }
+
+    let mut tp: TokenType = if is_float {
+        TokenType::F64
+    } else {
+        TokenType::I32
+    };
+
+    let mut suffix_start = None;
+    if lexer.current.is_alphanumeric() || lexer.current == '_' {
+        suffix_start = Some(lexer.col);
+        let mut suffix = String::new();
+        while lexer.current.is_alphanumeric() || lexer.current == '_' {
+            suffix.push(lexer.current);
+            advance(lexer);
+        }
+
+        tp = match suffix.as_str() {
+            "i8" => TokenType::I8,
+            "i16" => TokenType::I16,
+            "i32" => TokenType::I32,
+            "i64" => TokenType::I64,
+            "i128" => TokenType::I128,
+            "u8" => TokenType::U8,
+            "u16" => TokenType::U16,
+            "u32" => TokenType::U32,
+            "u64" => TokenType::U64,
+            "u128" => TokenType::U128,
+            "f32" => TokenType::F32,
+            "f64" => TokenType::F64,
+            _ => {
+                raise_error(
+                    &format!("Invalid specified type {}.", suffix),
+                    ErrorType::InvalidSpecifiedNumericType,
+                    &Position {
+                        line: lexer.line,
+                        startcol: suffix_start.unwrap(),
+                        endcol: lexer.col,
+                        opcol: None,
+                    },
+                    &lexer.session.info,
+                );
+            }
+        };
+
+        if is_float && !matches!(tp, TokenType::F32 | TokenType::F64) {
+            raise_error(
+                &format!("Invalid specified type {} for a float literal.", suffix),
+                ErrorType::InvalidSpecifiedNumericType,
+                &Position {
+                    line: lexer.line,
+                    startcol: suffix_start.unwrap(),
+                    endcol: lexer.col,
+                    opcol: None,
+                },
+                &lexer.session.info,
+            );
+        }
+    }
+
+    //Without a suffix the literal defaults to i32, so point at the suffix that would make it fit
+    if tp == TokenType::I32 && suffix_start.is_none() && data.parse::<i32>().is_err() {
+        let fits = if data.parse::<i64>().is_ok() {
+            Some("i64")
+        } else if data.parse::<i128>().is_ok() {
+            Some("i128")
+        } else if data.parse::<u128>().is_ok() {
+            Some("u128")
+        } else {
+            None
+        };
+
+        let msg = match fits {
+            Some(suffix) => format!(
+                "Integer literal does not fit in the default type i32, add a suffix such as '{data}{suffix}'."
+            ),
+            None => "Integer literal does not fit in any integer type.".into(),
+        };
+        raise_error(
+            &msg,
+            ErrorType::InvalidLiteralForRadix,
+            &Position {
+                line: lexer.line,
+                startcol: start.startcol,
+                endcol: lexer.col,
+                opcol: None,
+            },
+            &lexer.session.info,
+        );
+    }
+
+    Token {
+        data,
+        tp,
+        start,
+        end: Position {
+            line: lexer.line,
+            startcol: lexer.col,
+            endcol: lexer.col,
+            opcol: None,
+        },
+    }
+}
+
+//Shared by all literals that allow escapes; the lexer must be on the '\\'
+fn make_escape(lexer: &mut Lexer) -> char {
+    let startcol = lexer.col;
+    let line = lexer.line;
+
+    advance(lexer);
+
+    let mut include_current = lexer.current != '\n' && lexer.current != '\0';
+
+    let res = match lexer.current {
+        'n' => Some('\n'),
+        'r' => Some('\r'),
+        't' => Some('\t'),
+        '"' => Some('"'),
+        '\\' => Some('\\'),
+        '0' => Some('\0'),
+        //Two hex digits, at most 7F so the byte is a whole UTF-8 character
+        'x' => {
+            let mut chars = lexer.chars.clone();
+            match (chars.next(), chars.next()) {
+                (Some(high), Some(low)) if high.is_ascii_hexdigit() && low.is_ascii_hexdigit() => {
+                    advance(lexer);
+                    advance(lexer);
+                    u8::from_str_radix(&format!("{high}{low}"), 16)
+                        .ok()
+                        .filter(|byte| byte.is_ascii())
+                        .map(char::from)
                 }
+                _ => None,
             }
-        } else if lexer.current == 'u' {
+        }
+        'u' => {
             advance(lexer);
-            let mut specified_type = String::new();
-            while lexer.current.is_numeric() {
-                specified_type.push(lexer.current);
+            let mut digits = String::new();
+            if lexer.current == '{' {
                 advance(lexer);
-            }
-            match specified_type.as_str() {
-                "8" => {
-                    tp = TokenType::U8;
-                }
-                "16" => {
-                    tp = TokenType::U16;
-                }
-                "32" => {
-                    tp = TokenType::U32;
-                }
-                "64" => {
-                    tp = TokenType::U64;
-                }
-                "128" => {
-                    tp = TokenType::U128;
-                }
-                _ => {
-                    raise_error(
-                        &format!("Invalid specified type u{}.", specified_type),
-                        ErrorType::InvalidSpecifiedNumericType,
-                        &Position {
-                            line: lexer.line,
-                            startcol: start_col,
-                            endcol: lexer.col,
-                            opcol: None,
-                        },
-                        &lexer.info,
-                    );
+                while lexer.current.is_ascii_hexdigit() {
+                    digits.push(lexer.current);
+                    advance(lexer);
                 }
             }
+
+            include_current = lexer.current == '}';
+
+            if lexer.current == '}' && !digits.is_empty() && digits.len() <= 6 {
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+
+    let endcol = if include_current {
+        lexer.col + 1
+    } else {
+        lexer.col
+    };
+
+    match res {
+        Some(res) => {
+            advance(lexer);
+            res
+        }
+        None => {
+            raise_error(
+                "Invalid escape sequence.",
+                ErrorType::InvalidEscape,
+                &Position {
+                    line,
+                    startcol,
+                    endcol,
+                    opcol: None,
+                },
+                &lexer.session.info,
+            );
+        }
+    }
+}
+
+fn make_string(lexer: &mut Lexer) -> Token {
+    let mut data: String = String::from("");
+
+    let start = Position {
+        line: lexer.line,
+        startcol: lexer.col,
+        endcol: lexer.col + 1,
+        opcol: None,
+    };
+
+    advance(lexer);
+
+    while lexer.current != '"' {
+        if lexer.current == '\0' || lexer.current == '\n' {
+            raise_error(
+                "Unterminated string literal.",
+                ErrorType::UnterminatedString,
+                &Position {
+                    line: start.line,
+                    startcol: start.startcol,
+                    endcol: lexer.col,
+                    opcol: None,
+                },
+                &lexer.session.info,
+            );
+        }
+        if lexer.current == '\\' {
+            data.push(make_escape(lexer));
+        } else {
+            data.push(lexer.current);
+            advance(lexer);
         }
     }
 
+    advance(lexer);
+
     Token {
         data,
-        tp,
+        tp: TokenType::Str,
         start,
         end: Position {
             line: lexer.line,
@@ -547,7 +1035,7 @@ fn make_number(lexer: &mut Lexer) -> Token {
     }
 }
 
-fn make_identifier(lexer: &mut Lexer, kwds: &[String]) -> Token {
+fn make_identifier(lexer: &mut Lexer) -> Token {
     let mut data: String = String::from("");
 
     let start = Position {
@@ -562,12 +1050,9 @@ fn make_identifier(lexer: &mut Lexer, kwds: &[String]) -> Token {
         advance(lexer);
     }
 
-    let mut endcol = lexer.col;
-    if lexer.current == '(' || lexer.current == ')' {
-        endcol -= unicode_width::UnicodeWidthChar::width(lexer.current).unwrap();
-    }
+    let endcol = lexer.col;
 
-    let tp = if kwds.contains(&data) {
+    let tp = if is_keyword(&data) {
         TokenType::Keyword
     } else {
         TokenType::Identifier
@@ -1,7 +1,9 @@
 use clap::{ArgAction, Parser};
-use codegen::generate_code;
-use errors::{raise_error_no_pos, ErrorType};
-use utils::FileInfo;
+use codegen::{generate_code, print_size};
+use errors::{raise_error_no_pos, ErrorType, WarningType};
+use session::{CrateType, Emit, Flags, Hardening, LinkMode, Pgo, Session};
+use strum::IntoEnumIterator;
+use utils::{write_depinfo, FileInfo};
 
 mod errors;
 mod types;
@@ -15,6 +17,10 @@ mod codegen;
 
 mod mir;
 
+mod profile;
+
+mod session;
+
 //Version: major.minor
 #[derive(Parser, Debug)]
 #[command(author, version = "0.1.0", about, long_about = None)]
@@ -30,19 +36,81 @@ struct Args {
     #[clap(long, short, action)]
     optimize: bool,
 
+    /// Build profile, debug, release, or a profile defined in Kestrel.toml
+    #[clap(long, default_value = "debug")]
+    profile: String,
+
     #[clap(long = "dm", action)]
     debug_mir: bool,
-}
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
-pub enum Flags {
-    NoOUChecks,
-    Sanitize,
+    /// Color the MIR written to a.mir with ANSI escapes
+    #[clap(long, action)]
+    mir_color: bool,
+
+    /// Print instruction, block and reference counts and the longest lifetime of each function's MIR
+    #[clap(long, action)]
+    mir_stats: bool,
+
+    /// After linking, print the text, data and bss sizes of the output and the size of each
+    /// function, largest first
+    #[clap(long, action)]
+    print_size: bool,
+
+    /// Additional outputs to emit, depinfo (Makefile-style dependency file) or globals (listing of
+    /// the global constants and their bytes)
+    #[clap(use_value_delimiter=true, value_delimiter=',', action=ArgAction::Append, long)]
+    emit: Option<Vec<String>>,
+
+    /// Number of columns a tab is rendered as in error messages
+    #[clap(long, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..))]
+    tab_width: u16,
+
+    /// Instrument the executable to write a profile (default.profraw) when it runs
+    #[clap(long, action, conflicts_with = "pgo_use")]
+    pgo_generate: bool,
+
+    /// Optimize using profile data merged with llvm-profdata from an instrumented run
+    #[clap(long, value_name = "PROFDATA")]
+    pgo_use: Option<String>,
+
+    /// Link statically, so the executable does not depend on any shared library
+    #[clap(long = "static", action)]
+    static_link: bool,
+
+    /// Link the C runtime statically
+    #[clap(long, action)]
+    crt_static: bool,
+
+    /// Kind of output, bin (executable), cdylib (shared library of the `export fn`s) or obj (object
+    /// file, without a generated main)
+    #[clap(long, default_value = "bin")]
+    crate_type: String,
+
+    /// Additional LLVM module passes, in opt's -passes syntax, to run after optimization
+    #[clap(action=ArgAction::Append, long)]
+    passes: Vec<String>,
+
+    /// Hardening, ssp or ssp-strong (stack protector), relro and now (read-only relocations, ELF only), or full (all of ssp-strong, relro and now)
+    #[clap(use_value_delimiter=true, value_delimiter=',', action=ArgAction::Append, long)]
+    hardening: Vec<String>,
+
+    /// Lints to report, constant-condition, unused-mut or shadowing (off by default)
+    #[clap(use_value_delimiter=true, value_delimiter=',', action=ArgAction::Append, long, short = 'W')]
+    warn: Vec<String>,
+
+    /// Lints to silence, constant-condition, unused-mut or shadowing
+    #[clap(use_value_delimiter=true, value_delimiter=',', action=ArgAction::Append, long, short = 'A')]
+    allow: Vec<String>,
 }
 
 fn main() {
     let args = Args::parse();
 
+    let mut profile = profile::resolve_profile(&args.profile);
+    if args.optimize {
+        profile.optimize = true;
+    }
+
     let mut flags = Vec::new();
 
     if args.flags.is_some() {
@@ -72,6 +140,144 @@ fn main() {
         }
     }
 
+    if !profile.overflow_checks && !flags.contains(&Flags::NoOUChecks) {
+        flags.push(Flags::NoOUChecks);
+    }
+
+    let mut emit = Vec::new();
+
+    if let Some(emit_args) = args.emit {
+        for tp in emit_args {
+            let kind = match tp.as_str() {
+                "depinfo" => Emit::DepInfo,
+                "globals" => Emit::Globals,
+                _ => raise_error_no_pos(
+                    &format!("'{tp}' was not recognized as a valid emit type"),
+                    ErrorType::InvalidFlag,
+                ),
+            };
+            if emit.contains(&kind) {
+                raise_error_no_pos(
+                    &format!("'{tp}' was specified multiple times"),
+                    ErrorType::DuplicateFlag,
+                );
+            }
+            emit.push(kind);
+        }
+    }
+
+    let mut hardening = Vec::new();
+
+    for tp in &args.hardening {
+        let options = match tp.as_str() {
+            "ssp" => &[Hardening::StackProtector][..],
+            "ssp-strong" => &[Hardening::StackProtectorStrong][..],
+            "relro" => &[Hardening::Relro][..],
+            "now" => &[Hardening::Now][..],
+            "full" => &[
+                Hardening::StackProtectorStrong,
+                Hardening::Relro,
+                Hardening::Now,
+            ][..],
+            _ => raise_error_no_pos(
+                &format!("'{tp}' was not recognized as a valid hardening option"),
+                ErrorType::InvalidFlag,
+            ),
+        };
+        for option in options {
+            if hardening.contains(option) {
+                raise_error_no_pos(
+                    &format!("'{tp}' was specified multiple times"),
+                    ErrorType::DuplicateFlag,
+                );
+            }
+            hardening.push(*option);
+        }
+    }
+    if hardening.contains(&Hardening::StackProtector)
+        && hardening.contains(&Hardening::StackProtectorStrong)
+    {
+        raise_error_no_pos(
+            "'ssp' and 'ssp-strong' cannot be used together",
+            ErrorType::InvalidFlag,
+        );
+    }
+
+    let mut lints = WarningType::iter()
+        .filter(WarningType::enabled_by_default)
+        .collect::<Vec<_>>();
+
+    for name in &args.warn {
+        if args.allow.contains(name) {
+            raise_error_no_pos(
+                &format!("'{name}' was both warned and allowed"),
+                ErrorType::DuplicateFlag,
+            );
+        }
+    }
+    for (name, enable) in args
+        .warn
+        .iter()
+        .map(|name| (name, true))
+        .chain(args.allow.iter().map(|name| (name, false)))
+    {
+        let lint = match WarningType::from_lint_name(name) {
+            Some(lint) => lint,
+            None => raise_error_no_pos(
+                &format!("'{name}' was not recognized as a valid lint"),
+                ErrorType::InvalidFlag,
+            ),
+        };
+        lints.retain(|tp| *tp != lint);
+        if enable {
+            lints.push(lint);
+        }
+    }
+
+    let pgo = if args.pgo_generate {
+        Some(Pgo::Generate)
+    } else if let Some(profdata) = args.pgo_use {
+        if !std::path::Path::new(&profdata).is_file() {
+            raise_error_no_pos(
+                &format!("Profile data '{profdata}' is unable to be opened."),
+                ErrorType::ProfileDataNotFound,
+            );
+        }
+        Some(Pgo::Use(profdata))
+    } else {
+        None
+    };
+
+    let link_mode = if args.static_link {
+        LinkMode::Static
+    } else if args.crt_static {
+        LinkMode::CrtStatic
+    } else {
+        LinkMode::Dynamic
+    };
+
+    let crate_type = match args.crate_type.as_str() {
+        "bin" => CrateType::Bin,
+        "cdylib" => CrateType::Cdylib,
+        "obj" => CrateType::Obj,
+        tp => raise_error_no_pos(
+            &format!("'{tp}' was not recognized as a valid crate type"),
+            ErrorType::InvalidFlag,
+        ),
+    };
+    if crate_type == CrateType::Cdylib && link_mode == LinkMode::Static {
+        raise_error_no_pos(
+            "'--static' cannot be used with a cdylib, which is always loaded dynamically",
+            ErrorType::InvalidFlag,
+        );
+    }
+    if crate_type == CrateType::Obj && link_mode != LinkMode::Dynamic {
+        raise_error_no_pos(
+            "'--static' and '--crt-static' cannot be used with an obj, which is not linked",
+            ErrorType::InvalidFlag,
+        );
+    }
+
     let res = std::fs::read_to_string(&args.name);
     let file_data = match res {
         Ok(_) => res.unwrap(),
@@ -83,38 +289,51 @@ fn main() {
 
     let data = file_data.chars();
 
-    let mut file_info = FileInfo {
-        data: data.clone(),
-        name: args.name.clone(),
-        dir: String::from("."),
+    let session = Session {
+        info: FileInfo {
+            data: data.clone(),
+            name: args.name.clone(),
+            dir: String::from("."),
+            tab_width: args.tab_width.into(),
+        },
+        profile,
+        flags,
+        emit,
+        pgo,
+        link_mode,
+        crate_type,
+        hardening,
+        lints,
+        passes: args.passes,
+        debug_mir: args.debug_mir,
+        mir_color: args.mir_color,
+        mir_stats: args.mir_stats,
     };
 
-    let keywords = vec![
-        "let".into(),
-        "mut".into(),
-        "true".into(),
-        "false".into(),
-        "fn".into(),
-        "return".into(),
-        "if".into(),
-        "else".into(),
-        "elif".into(),
-        "enum".into(),
-    ];
-    let mut lexer = lexer::new(&mut file_info);
-    let (_, tokens) = lexer::generate_tokens(&mut lexer, &keywords);
-
-    let mut parser = parser::Parser::new(tokens, &file_info);
+    let mut lexer = lexer::new(&session);
+    let (_, tokens) = lexer::generate_tokens(&mut lexer);
+
+    let mut parser = parser::Parser::new(tokens, &session);
     let ast = parser.generate_ast();
 
-    generate_code(
-        &args.name,
-        &args.name,
-        ast,
-        &file_info,
-        flags,
-        args.optimize,
-        args.debug_mir,
-    )
-    .expect("Code generation error.");
+    let artifacts = generate_code(&session, ast).expect("Code generation error.");
+
+    std::fs::write("a.ll", &artifacts.llvm_ir).expect("Unable to write LLVM IR output file.");
+    std::fs::write("a.mir", &artifacts.mir).expect("Unable to write MIR output file.");
+    if let Some(globals) = &artifacts.globals {
+        std::fs::write("a.globals", globals).expect("Unable to write globals output file.");
+    }
+
+    if session.emit.contains(&Emit::DepInfo) {
+        write_depinfo(
+            "a.d",
+            &artifacts.output().to_string_lossy(),
+            std::slice::from_ref(&session.info.name),
+        )
+        .expect("Unable to write dependency file.");
+    }
+
+    if args.print_size {
+        print_size(&artifacts);
+    }
 }
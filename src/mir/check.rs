@@ -1,99 +1,18 @@
 use indexmap::IndexMap;
 
+use std::collections::HashMap;
+
 use crate::{
-    errors::{raise_error, raise_error_multi, ErrorType},
-    types::{implements_trait, Lifetime, Trait, TraitType},
+    errors::{raise_error, raise_error_multi, raise_warning_multi, ErrorType, WarningType},
+    types::{implements_trait, BasicType, Lifetime, TraitType, Type},
+    utils::Position,
 };
 
 use super::{
-    check, Mir, MirInstruction, MirReference, MirTag, RawMirInstruction, ReferenceBase,
+    check, liveness, Mir, MirInstruction, MirReference, MirTag, RawMirInstruction, ReferenceBase,
     ReferenceType,
 };
 
-pub fn calculate_last_use(i: &usize, instructions: &mut Vec<MirInstruction>) -> usize {
-    let mut uses = Vec::new();
-    for j in (*i)..instructions.len() {
-        match &instructions.get(j).as_ref().unwrap().instruction {
-            RawMirInstruction::Add { left, right } => {
-                if i == left || i == right {
-                    uses.push(j);
-                }
-            }
-            RawMirInstruction::Declare { name: _, is_mut: _ } => {}
-            RawMirInstruction::I8(_) => {}
-            RawMirInstruction::I16(_) => {}
-            RawMirInstruction::I32(_) => {}
-            RawMirInstruction::I64(_) => {}
-            RawMirInstruction::I128(_) => {}
-            RawMirInstruction::U8(_) => {}
-            RawMirInstruction::U16(_) => {}
-            RawMirInstruction::U32(_) => {}
-            RawMirInstruction::U64(_) => {}
-            RawMirInstruction::U128(_) => {}
-            RawMirInstruction::Bool(_) => {}
-            RawMirInstruction::Load(_) => {}
-            RawMirInstruction::Own(result) => {
-                if i == result {
-                    uses.push(j);
-                }
-            }
-            RawMirInstruction::Store { name: _, right } => {
-                if i == right {
-                    uses.push(j);
-                }
-            }
-            RawMirInstruction::Reference(right) => {
-                if i == right {
-                    uses.push(j);
-                }
-            }
-            RawMirInstruction::Copy(right) => {
-                if i == right {
-                    uses.push(j);
-                }
-            }
-            RawMirInstruction::Return(right) => {
-                if i == right {
-                    uses.push(j);
-                }
-            }
-            RawMirInstruction::CallFunction(_) => {}
-            RawMirInstruction::Eq { left, right } => {
-                if i == left || i == right {
-                    uses.push(j);
-                }
-            }
-            RawMirInstruction::Ne { left, right } => {
-                if i == left || i == right {
-                    uses.push(j);
-                }
-            }
-            RawMirInstruction::Deref(right) => {
-                if i == right {
-                    uses.push(j);
-                }
-            }
-            RawMirInstruction::IfCondition {
-                code: _,
-                check_n: _,
-                right,
-                offset: _,
-                id: _,
-            } => {
-                if right.is_some() && right.unwrap() == *i {
-                    uses.push(j);
-                }
-            }
-            RawMirInstruction::InstructionWrapper(_) | RawMirInstruction::NoOp => {}
-        }
-    }
-
-    match uses.len() {
-        0 => *i,
-        _ => *uses.last().unwrap(),
-    }
-}
-
 pub fn generate_lifetimes<'a>(
     this: &mut Mir<'a>,
     instructions: &mut Vec<MirInstruction<'a>>,
@@ -102,10 +21,26 @@ pub fn generate_lifetimes<'a>(
 ) -> IndexMap<usize, MirReference> {
     let mut lifetime_num = 0;
     let mut references = IndexMap::new();
+    let start = this.block_positions.get(&id).unwrap().0;
+
+    let reachable = reachable(instructions, &this.block_positions, start);
+    let back_edges = back_edges(this, instructions);
+    let values = liveness::values(instructions, &reachable, &back_edges);
+    let bindings = liveness::bindings(instructions, &reachable, &back_edges);
+
+    //The state of the bindings before each arm of the current if chain, and merged after its arms
+    let mut chain: Option<(Ownership, Ownership)> = None;
 
     for i in 0..instructions.len() {
         let mut instruction = instructions.get(i).unwrap().clone();
         match &instruction.instruction {
+            //The caller already tracked the instructions before this block, including the moves in
+            //the blocks nested in them, which replaying these would forget
+            RawMirInstruction::Declare { .. }
+            | RawMirInstruction::Load(_)
+            | RawMirInstruction::Own(_)
+            | RawMirInstruction::Store { .. }
+                if i < start => {}
             RawMirInstruction::I8(_) => {}
             RawMirInstruction::I16(_) => {}
             RawMirInstruction::I32(_) => {}
@@ -116,57 +51,19 @@ pub fn generate_lifetimes<'a>(
             RawMirInstruction::U32(_) => {}
             RawMirInstruction::U64(_) => {}
             RawMirInstruction::U128(_) => {}
+            RawMirInstruction::F32(_) => {}
+            RawMirInstruction::F64(_) => {}
             RawMirInstruction::Bool(_) => {}
-            RawMirInstruction::Add { left, right } => {
-                let left_tp = instructions.get(*left).unwrap().tp.as_ref().unwrap();
-                let right_tp = instructions.get(*right).unwrap().tp.as_ref().unwrap();
-                //TODO: _res will be used in the future with custom lifetimes
-                let _res = if let Some(Trait::Add {
-                    code: _,
-                    skeleton,
-                    ref_n: _,
-                }) = left_tp.traits.get(&TraitType::Add)
-                {
-                    skeleton(
-                        this,
-                        &instructions.get(*left).unwrap().pos,
-                        left_tp.clone(),
-                        right_tp.clone(),
-                    )
-                } else {
-                    unreachable!()
-                };
-            }
+            RawMirInstruction::Str(_) => {}
+            RawMirInstruction::Unit | RawMirInstruction::Asm(_) => {}
+            RawMirInstruction::Param(_) => {}
+            RawMirInstruction::Break(_) | RawMirInstruction::Continue(_) => {}
             RawMirInstruction::Declare { ref name, is_mut } => {
                 let block = this.blocks.get_mut(name.blockid).unwrap();
 
                 lifetime_num += 1;
 
-                let mut uses = Vec::new();
-                for j in i..instructions.len() {
-                    if let RawMirInstruction::Load(load_name) =
-                        &instructions.get(j).as_ref().unwrap().instruction
-                    {
-                        if name == load_name {
-                            uses.push(j);
-                        }
-                    }
-
-                    if let RawMirInstruction::Store {
-                        name: load_name,
-                        right: _,
-                    } = &instructions.get(j).as_ref().unwrap().instruction
-                    {
-                        if name == load_name {
-                            uses.push(j);
-                        }
-                    }
-                }
-                let end_mir = if uses.is_empty() {
-                    i
-                } else {
-                    *uses.last().unwrap()
-                };
+                let end_mir = bindings.last_use(name, i);
 
                 instructions.get_mut(end_mir).unwrap().last_use = Some(name.name.clone());
 
@@ -188,6 +85,24 @@ pub fn generate_lifetimes<'a>(
                     ),
                 );
             }
+            //Only assigned in nested blocks, which this check of an enclosing block skips, so this
+            //load is where moves out of the binding are first tracked
+            RawMirInstruction::Load(ref name)
+                if this
+                    .blocks
+                    .get(name.blockid)
+                    .unwrap()
+                    .namespace_check
+                    .get(&name.name)
+                    .unwrap()
+                    .2
+                    .owner
+                    .is_none() =>
+            {
+                let block = this.blocks.get_mut(name.blockid).unwrap();
+                block.namespace_check.get_mut(&name.name).unwrap().2.owner =
+                    Some((i, name.blockid));
+            }
             RawMirInstruction::Load(ref name) => {
                 let block = this.blocks.get(name.blockid).unwrap();
 
@@ -222,14 +137,20 @@ pub fn generate_lifetimes<'a>(
                     || old_instruction.tp.is_some()
                         && implements_trait(old_instruction.tp.as_ref().unwrap(), TraitType::Copy))
                 {
+                    let tp = old_instruction.tp.as_ref().unwrap();
                     raise_error_multi(
                         vec![
-                            format!("Use of binding '{}' after move.", name.name),
+                            format!(
+                                "Use of binding '{}' of type '{}' after move.",
+                                name.name,
+                                tp.qualname()
+                            ),
                             "It was moved here:".into(),
+                            copy_note(tp),
                         ],
                         ErrorType::MovedBinding,
-                        vec![Some(&instruction.pos), Some(&old_instruction.pos)],
-                        &this.info,
+                        vec![Some(&instruction.pos), Some(&old_instruction.pos), None],
+                        &this.session.info,
                     );
                 } else {
                     let block = this.blocks.get_mut(name.blockid).unwrap();
@@ -302,7 +223,7 @@ pub fn generate_lifetimes<'a>(
                                 } => Lifetime::ImplicitLifetime {
                                     name,
                                     start_mir,
-                                    end_mir: calculate_last_use(&rt, instructions),
+                                    end_mir: values.last_use(&rt, rt),
                                 },
                                 Lifetime::Static => life,
                             };
@@ -330,7 +251,7 @@ pub fn generate_lifetimes<'a>(
                                 } => Lifetime::ImplicitLifetime {
                                     name,
                                     start_mir,
-                                    end_mir: calculate_last_use(&rt, instructions),
+                                    end_mir: values.last_use(&rt, rt),
                                 },
                                 Lifetime::Static => life,
                             };
@@ -353,7 +274,10 @@ pub fn generate_lifetimes<'a>(
                         | RawMirInstruction::U16(_)
                         | RawMirInstruction::U32(_)
                         | RawMirInstruction::U64(_)
-                        | RawMirInstruction::U128(_) => {
+                        | RawMirInstruction::U128(_)
+                        | RawMirInstruction::F32(_)
+                        | RawMirInstruction::F64(_)
+                        | RawMirInstruction::Param(_) => {
                             let life = instructions
                                 .get(rt)
                                 .as_ref()
@@ -371,7 +295,7 @@ pub fn generate_lifetimes<'a>(
                                 } => Lifetime::ImplicitLifetime {
                                     name,
                                     start_mir,
-                                    end_mir: calculate_last_use(&rt, instructions),
+                                    end_mir: values.last_use(&rt, rt),
                                 },
                                 Lifetime::Static => life,
                             };
@@ -383,7 +307,7 @@ pub fn generate_lifetimes<'a>(
                     }
                 }
 
-                let mut last = calculate_last_use(&i, instructions);
+                let mut last = values.last_use(&i, i);
                 for j in (i..instructions.len()).rev() {
                     //Find a store
                     if let RawMirInstruction::Store { name, right } =
@@ -431,7 +355,7 @@ pub fn generate_lifetimes<'a>(
                 }
 
                 if let Some(res) = block_res {
-                    last = last.max(calculate_last_use(&(res + 1), instructions));
+                    last = last.max(values.last_use(&(res + 1), res + 1));
                 }
 
                 lifetime_num += 1;
@@ -451,64 +375,50 @@ pub fn generate_lifetimes<'a>(
                 references.insert(i, res);
             }
 
-            RawMirInstruction::Copy(_) => {}
+            RawMirInstruction::Copy(_) | RawMirInstruction::Cast(_) => {}
             RawMirInstruction::Return(right) => {
-                assert_eq!(
-                    instructions.get(*right).unwrap().tp.as_ref().unwrap().ref_n,
-                    0
-                )
+                if instructions.get(*right).unwrap().tp.as_ref().unwrap().ref_n != 0 {
+                    check_returned_reference(this, instructions, &references, *right, i);
+                }
             }
             RawMirInstruction::CallFunction(_) => {}
-            RawMirInstruction::Eq { left, right } => {
-                let left_tp = instructions.get(*left).unwrap().tp.as_ref().unwrap();
-                let right_tp = instructions.get(*right).unwrap().tp.as_ref().unwrap();
-                //TODO: _res will be used in the future with custom lifetimes
-                let _res = if let Some(Trait::Eq {
-                    code: _,
-                    skeleton,
-                    ref_n: _,
-                }) = left_tp.traits.get(&TraitType::Eq)
-                {
-                    skeleton(
-                        this,
-                        &instructions.get(*left).unwrap().pos,
-                        left_tp.clone(),
-                        right_tp.clone(),
-                    )
-                } else {
-                    unreachable!()
+            RawMirInstruction::Add { left, right }
+            | RawMirInstruction::Sub { left, right }
+            | RawMirInstruction::Mul { left, right }
+            | RawMirInstruction::Div { left, right }
+            | RawMirInstruction::Rem { left, right }
+            | RawMirInstruction::Eq { left, right }
+            | RawMirInstruction::Ne { left, right } => {
+                let traittp = match &instruction.instruction {
+                    RawMirInstruction::Add { .. } => TraitType::Add,
+                    RawMirInstruction::Sub { .. } => TraitType::Sub,
+                    RawMirInstruction::Mul { .. } => TraitType::Mul,
+                    RawMirInstruction::Div { .. } => TraitType::Div,
+                    RawMirInstruction::Rem { .. } => TraitType::Rem,
+                    RawMirInstruction::Eq { .. } => TraitType::Eq,
+                    _ => TraitType::Ne,
                 };
-            }
-            RawMirInstruction::Ne { left, right } => {
-                let left_tp = instructions.get(*left).unwrap().clone().tp.unwrap().clone();
-                let right_tp = instructions
-                    .get(*right)
-                    .unwrap()
-                    .clone()
-                    .tp
-                    .unwrap()
-                    .clone();
+                let left_tp = instructions.get(*left).unwrap().tp.clone().unwrap();
+                let right_tp = instructions.get(*right).unwrap().tp.clone().unwrap();
+                let (_, skeleton) = left_tp.operator(&traittp).unwrap();
                 //TODO: _res will be used in the future with custom lifetimes
-                let _res = if let Some(Trait::Ne {
-                    code: _,
-                    skeleton,
-                    ref_n: _,
-                }) = left_tp.traits.get(&TraitType::Ne)
-                {
-                    skeleton(
-                        this,
-                        &instructions.get(*left).unwrap().pos,
-                        left_tp.clone(),
-                        right_tp.clone(),
-                    )
-                } else {
-                    unreachable!()
-                };
+                let _res = skeleton(
+                    this,
+                    &instructions.get(*left).unwrap().pos,
+                    left_tp.clone(),
+                    right_tp,
+                );
             }
             RawMirInstruction::Deref(right) => {
                 let rt_instruction = instructions.get(*right).unwrap();
-                let mut tp = rt_instruction.tp.as_ref().unwrap().clone();
-                tp.ref_n -= 1;
+                let tp = match rt_instruction.tp.as_ref().unwrap().pointee() {
+                    Some((pointee, _)) => pointee.clone(),
+                    None => {
+                        let mut tp = rt_instruction.tp.as_ref().unwrap().clone();
+                        tp.ref_n -= 1;
+                        tp
+                    }
+                };
                 if !implements_trait(&tp, TraitType::Copy) {
                     if let RawMirInstruction::Load(name) = &rt_instruction.instruction {
                         let fmt: String = format!(
@@ -520,7 +430,7 @@ pub fn generate_lifetimes<'a>(
                             &fmt,
                             ErrorType::CannotMoveOutOfBinding,
                             &rt_instruction.pos,
-                            &this.info,
+                            &this.session.info,
                         );
                     } else {
                         let fmt: String =
@@ -529,19 +439,44 @@ pub fn generate_lifetimes<'a>(
                             &fmt,
                             ErrorType::CannotMoveOutOfNonCopy,
                             &rt_instruction.pos,
-                            &this.info,
+                            &this.session.info,
                         );
                     }
                 }
             }
             RawMirInstruction::IfCondition {
                 code,
-                check_n: _,
+                check_n,
+                right: _,
+                offset: _,
+                id,
+            } => {
+                //Earlier arms and enclosing blocks were already checked by the caller, and
+                //rechecking them for every nested arm would take exponential time
+                if i >= start {
+                    //Only one arm runs, so each starts from the state before the chain, and a
+                    //binding is moved after the chain if any arm moved it
+                    let (before, merged) = match chain.take() {
+                        Some(chain) if *check_n > 0 => chain,
+                        _ => (ownership(this), ownership(this)),
+                    };
+                    set_ownership(this, &before);
+                    check(this, &mut code.to_vec(), Some(i), *id);
+                    let merged = merge_ownership(merged, ownership(this));
+                    set_ownership(this, &merged);
+                    chain = Some((before, merged));
+                }
+            }
+            RawMirInstruction::Loop {
+                code,
                 right: _,
                 offset: _,
                 id,
             } => {
-                check(this, &mut code.clone(), Some(i), *id);
+                if i >= start {
+                    check(this, &mut code.to_vec(), Some(i), *id);
+                    check_loop_moves(this, instructions, *id);
+                }
             }
             RawMirInstruction::InstructionWrapper(_) | RawMirInstruction::NoOp => {}
         }
@@ -549,7 +484,7 @@ pub fn generate_lifetimes<'a>(
         if let RawMirInstruction::Declare { name: _, is_mut: _ } = instruction.instruction {
         } else if instruction.tp.is_some() {
             lifetime_num += 1;
-            let end_mir = calculate_last_use(&i, instructions); //Do this before the removal!
+            let end_mir = values.last_use(&i, i); //Do this before the removal!
             instructions.remove(i);
 
             let mutable_type = instruction.tp.as_mut().unwrap();
@@ -567,6 +502,219 @@ pub fn generate_lifetimes<'a>(
     references
 }
 
+/// Whether each binding of each block is owned, and the instruction that last moved or assigned
+/// it.
+type Ownership = Vec<HashMap<String, (bool, Option<(usize, usize)>)>>;
+
+fn ownership(this: &Mir) -> Ownership {
+    this.blocks
+        .iter()
+        .map(|block| {
+            block
+                .namespace_check
+                .iter()
+                .map(|(name, (_, _, tag))| (name.clone(), (tag.is_owned, tag.owner)))
+                .collect()
+        })
+        .collect()
+}
+
+fn set_ownership(this: &mut Mir, ownership: &Ownership) {
+    for (block, bindings) in this.blocks.iter_mut().zip(ownership) {
+        for (name, (is_owned, owner)) in bindings {
+            if let Some((_, _, tag)) = block.namespace_check.get_mut(name) {
+                tag.is_owned = *is_owned;
+                tag.owner = *owner;
+            }
+        }
+    }
+}
+
+/// A binding moved in either state stays moved.
+fn merge_ownership(mut merged: Ownership, other: Ownership) -> Ownership {
+    for (bindings, other) in merged.iter_mut().zip(other) {
+        for (name, state) in other {
+            match bindings.get(&name) {
+                Some((false, _)) => {}
+                Some(_) if state.0 => {}
+                _ => {
+                    bindings.insert(name, state);
+                }
+            }
+        }
+    }
+    merged
+}
+
+/// The body of loop `id` runs again after a back edge, so a binding of an enclosing block that the
+/// body moves out of must not be used in the body before it is assigned again. The body was
+/// already checked, so the bindings are in their state at the end of it.
+fn check_loop_moves(this: &Mir, instructions: &[MirInstruction], id: usize) {
+    let (loop_start, loop_end) = *this.block_positions.get(&id).unwrap();
+    let loop_end = loop_end.min(instructions.len());
+    let pos = &instructions.get(loop_start).unwrap().pos;
+
+    let continues = instructions[loop_start..loop_end].iter().any(|instruction| {
+        matches!(raw_instruction(instruction), RawMirInstruction::Continue(target) if *target == id)
+    });
+    let falls = falls_through(instructions, &this.block_positions, loop_start, loop_end, pos);
+    if !continues && falls.is_none() {
+        return;
+    }
+
+    let mut assigned = Vec::new();
+    for instruction in &instructions[loop_start..loop_end] {
+        match raw_instruction(instruction) {
+            RawMirInstruction::Store { name, right: _ } => assigned.push(name),
+            RawMirInstruction::Load(name) if !assigned.contains(&name) => {
+                let Some((declared, _, tag)) = this
+                    .blocks
+                    .get(name.blockid)
+                    .unwrap()
+                    .namespace_check
+                    .get(&name.name)
+                else {
+                    continue;
+                };
+                if declared.is_none_or(|declared| declared >= loop_start) || tag.is_owned {
+                    continue;
+                }
+                let Some((owner, owner_block)) = tag.owner else {
+                    continue;
+                };
+                let Some(moved) = this
+                    .blocks
+                    .get(owner_block)
+                    .unwrap()
+                    .instructions
+                    .as_ref()
+                    .unwrap()
+                    .get(owner)
+                else {
+                    continue;
+                };
+                let Some(tp) = moved.tp.as_ref() else {
+                    continue;
+                };
+                if implements_trait(tp, TraitType::Copy) {
+                    continue;
+                }
+
+                raise_error_multi(
+                    vec![
+                        format!(
+                            "Use of binding '{}' of type '{}' after move.",
+                            name.name,
+                            tp.qualname()
+                        ),
+                        "It was moved here, in an earlier iteration of the loop:".into(),
+                        copy_note(tp),
+                    ],
+                    ErrorType::MovedBinding,
+                    vec![Some(&instruction.pos), Some(&moved.pos), None],
+                    &this.session.info,
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+/// References cannot outlive the function, so explain which value the returned reference `right`,
+/// returned by instruction `i`, points into.
+fn check_returned_reference(
+    this: &Mir,
+    instructions: &[MirInstruction],
+    references: &IndexMap<usize, MirReference>,
+    right: usize,
+    i: usize,
+) -> ! {
+    let pos = &instructions.get(i).unwrap().pos;
+
+    //Follow the returned value back to the reference that created it, through the bindings that
+    //it was stored in
+    let mut rt = right;
+    let base = loop {
+        match &instructions.get(rt).unwrap().instruction {
+            RawMirInstruction::Own(next) | RawMirInstruction::Copy(next) => rt = *next,
+            RawMirInstruction::Load(name) => {
+                let store = (0..rt).rev().find_map(|j| match &instructions.get(j).unwrap().instruction {
+                    RawMirInstruction::Store { name: stored, right } if stored == name => Some(*right),
+                    _ => None,
+                });
+                match store {
+                    Some(next) => rt = next,
+                    None => break None,
+                }
+            }
+            RawMirInstruction::Reference(_) => break references.get(&rt).map(|reference| &reference.3),
+            _ => break None,
+        }
+    };
+
+    match base {
+        Some(ReferenceBase::Load { name, bindinglife: _ }) => {
+            let tag = &this
+                .blocks
+                .get(name.blockid)
+                .unwrap()
+                .namespace_check
+                .get(&name.name)
+                .unwrap()
+                .2;
+            let Lifetime::ImplicitLifetime {
+                name: _,
+                start_mir,
+                end_mir,
+            } = tag.lifetime
+            else {
+                unreachable!()
+            };
+            raise_error_multi(
+                vec![
+                    format!("Cannot return a reference to local binding '{}'.", name.name),
+                    format!("'{}' is declared here, with lifetime {}:", name.name, tag.lifetime),
+                    "It is dropped after its last use here, before the caller could use the \
+                     reference:"
+                        .into(),
+                ],
+                ErrorType::ReturnReference,
+                vec![
+                    Some(pos),
+                    Some(&instructions.get(start_mir).unwrap().pos),
+                    Some(&instructions.get(end_mir).unwrap().pos),
+                ],
+                &this.session.info,
+            );
+        }
+        Some(ReferenceBase::Literal(_)) => raise_error(
+            "Cannot return a reference to a temporary value.",
+            ErrorType::ReturnReference,
+            pos,
+            &this.session.info,
+        ),
+        _ => raise_error(
+            "Cannot return reference.",
+            ErrorType::ReturnReference,
+            pos,
+            &this.session.info,
+        ),
+    }
+}
+
+/// Why a moved value of type `tp` was not copied instead.
+fn copy_note(tp: &Type) -> String {
+    if tp.ref_n > 0 {
+        format!(
+            "'{}' is a reference, which can never implement Copy because a value may only have \
+             one reference at a time.",
+            tp.qualname()
+        )
+    } else {
+        format!("'{}' does not implement Copy, so it is moved when used.", tp.qualname())
+    }
+}
+
 fn check_value_life(this: &mut Mir, life: &Lifetime, right: &usize, id: usize) {
     let blockend = this.block_positions.get(&id).unwrap().1;
     match life {
@@ -586,7 +734,7 @@ fn check_value_life(this: &mut Mir, life: &Lifetime, right: &usize, id: usize) {
                         Some(&this.instructions.get(*right).unwrap().pos),
                         Some(&this.instructions.get(blockend).unwrap().pos),
                     ],
-                    &this.info,
+                    &this.session.info,
                 );
             }
         }
@@ -602,6 +750,10 @@ pub fn check_references(
     id: usize,
 ) {
     for (_, (right, _reftype, _life, base, refblock)) in references {
+        //A binding of an enclosing block outlives this block
+        if matches!(base, ReferenceBase::Load { name, bindinglife: _ } if name.blockid != id) {
+            continue;
+        }
         if *refblock == id
             && (this.block_positions.get(&id).unwrap().0..this.block_positions.get(&id).unwrap().1)
                 .contains(right)
@@ -622,7 +774,7 @@ pub fn check_references(
         }
     }
 
-    for (i, (right, _reftype, life, base1, _)) in references {
+    for (i, (_, _reftype, life, base1, _)) in references {
         for (j, (_right, _reftype, other_life, base2, _)) in references {
             if i >= j {
                 continue;
@@ -650,58 +802,266 @@ pub fn check_references(
                 usize::MAX
             };
 
-            if base1 == base2 {
-                if let RawMirInstruction::Load(ref name) =
-                    instructions.get(*right).as_ref().unwrap().instruction
-                {
-                    if l1_end > l2_start {
-                        raise_error_multi(
-                            vec![
-                                format!(
-                                    "Binding '{}' has multiple immutable references.",
-                                    &name.name
-                                ),
-                                "First reference here.".into(),
-                            ],
-                            ErrorType::MultipleImmutableReferences,
-                            vec![
-                                Some(&instructions.get(*j).unwrap().pos),
-                                Some(&instructions.get(*i).unwrap().pos),
-                            ],
-                            &this.info,
-                        );
+            if base1 == base2 && l1_end > l2_start {
+                let (value, valuelife) = match base1 {
+                    ReferenceBase::Load { name, bindinglife } => {
+                        (format!("Binding '{}'", name.name), bindinglife)
                     }
-                } else if l1_end > l2_start {
-                    raise_error_multi(
-                        vec![
-                            "Value has multiple immutable references.".into(),
-                            "First reference here.".into(),
-                        ],
-                        ErrorType::MultipleImmutableReferences,
-                        vec![
-                            Some(&instructions.get(*j).unwrap().pos),
-                            Some(&instructions.get(*i).unwrap().pos),
-                        ],
-                        &this.info,
-                    );
-                }
+                    ReferenceBase::Literal(lifetime) | ReferenceBase::Reference(lifetime) => {
+                        ("Value".to_string(), lifetime)
+                    }
+                };
+                raise_error_multi(
+                    vec![
+                        format!("{value} has multiple immutable references."),
+                        format!("First reference here, with lifetime {life}."),
+                        format!(
+                            "The second reference has lifetime {other_life}, so it is created at \
+                             .{l2_start} while the first is still live until .{l1_end}. {value} \
+                             {valuelife} may only have one reference at a time."
+                        ),
+                    ],
+                    ErrorType::MultipleImmutableReferences,
+                    vec![
+                        Some(&instructions.get(*j).unwrap().pos),
+                        Some(&instructions.get(*i).unwrap().pos),
+                        None,
+                    ],
+                    &this.session.info,
+                );
             }
         }
     }
 }
 
-pub fn check_return(_this: &mut Mir, _instructions: &mut [MirInstruction]) {
-    /*
-    for instruction in instructions {
-        if let RawMirInstruction::Return(_) = instruction.instruction {
-            return;
-        }
+pub fn check_return(this: &mut Mir, instructions: &mut [MirInstruction]) {
+    let rettp = match this.functions.get(&this.fn_name) {
+        Some(func) => func.1 .1.clone(),
+        None => return,
+    };
+    if rettp.basictype == BasicType::Void {
+        return;
     }
-    raise_error(
-        &format!("Function '{}' does not return.", this.fn_name),
-        ErrorType::TraitNotImplemented,
+
+    if let Some((pos, msg)) = falls_through(
+        instructions,
+        &this.block_positions,
+        0,
+        instructions.len(),
         &this.fn_pos,
-        &this.info,
-    );
-    */
+    ) {
+        raise_error_multi(
+            vec![
+                format!(
+                    "Function '{}' does not return '{}' on every path.",
+                    this.fn_name,
+                    rettp.qualname()
+                ),
+                msg.into(),
+            ],
+            ErrorType::MissingReturn,
+            vec![Some(&this.fn_pos), Some(&pos)],
+            &this.session.info,
+        );
+    }
+}
+
+/// Warn about each `let mut` binding that is never assigned to after it is initialized.
+pub fn check_unused_mut(this: &Mir) {
+    for (name, pos, reassigned) in &this.mut_bindings {
+        if !reassigned {
+            raise_warning_multi(
+                vec![
+                    format!(
+                        "Binding '{}' is never reassigned, so it does not need to be mutable.",
+                        name.name
+                    ),
+                    "Remove the 'mut'.".into(),
+                ],
+                WarningType::UnusedMut,
+                vec![Some(pos), None],
+                this.session,
+            );
+        }
+    }
+}
+
+/// Whether the function makes no calls and runs no inline assembly, so control cannot leave it
+/// before it returns.
+pub fn is_leaf(instructions: &[MirInstruction]) -> bool {
+    !instructions.iter().any(|instruction| {
+        matches!(
+            raw_instruction(instruction),
+            RawMirInstruction::CallFunction(_) | RawMirInstruction::Asm(_)
+        )
+    })
+}
+
+/// Whether the function contains a loop, which may never exit.
+pub fn has_loop(instructions: &[MirInstruction]) -> bool {
+    instructions
+        .iter()
+        .any(|instruction| matches!(raw_instruction(instruction), RawMirInstruction::Loop { .. }))
+}
+
+pub fn raw_instruction<'a, 'b>(instruction: &'b MirInstruction<'a>) -> &'b RawMirInstruction<'a> {
+    match &instruction.instruction {
+        RawMirInstruction::InstructionWrapper(inner) => raw_instruction(inner),
+        raw => raw,
+    }
+}
+
+/// The edges from the end of each loop and from each `continue` back to the loop's condition.
+/// When the instructions cut a loop short, as they do for the blocks nested in it, the edge leaves
+/// from the last instruction instead, so values live at the start of the loop stay live through
+/// all of it.
+fn back_edges(this: &Mir, instructions: &[MirInstruction]) -> Vec<(usize, usize)> {
+    let len = instructions.len();
+    let ends = this
+        .blocks
+        .iter()
+        .filter(|block| block.is_loop)
+        .filter_map(|block| this.block_positions.get(&block.blockid))
+        .filter(|(start, end)| start < end && *start < len)
+        .map(|(start, end)| ((*end).min(len) - 1, *start));
+    let continues = instructions
+        .iter()
+        .enumerate()
+        .filter_map(|(j, instruction)| match raw_instruction(instruction) {
+            RawMirInstruction::Continue(id) => {
+                this.block_positions.get(id).map(|(start, _)| (j, *start))
+            }
+            _ => None,
+        });
+    ends.chain(continues).collect()
+}
+
+/// Whether control can reach each instruction of the block starting at `start`, which it cannot
+/// after a return or an `if` chain that returns on every path.
+fn reachable(
+    instructions: &[MirInstruction],
+    positions: &HashMap<usize, (usize, usize)>,
+    start: usize,
+) -> Vec<bool> {
+    let mut reachable = vec![true; instructions.len()];
+    for i in start..instructions.len() {
+        if !matches!(
+            instructions.get(i).unwrap().instruction,
+            RawMirInstruction::Return(_)
+                | RawMirInstruction::Break(_)
+                | RawMirInstruction::Continue(_)
+                | RawMirInstruction::IfCondition { .. }
+                | RawMirInstruction::Loop { .. }
+        ) {
+            continue;
+        }
+        let pos = &instructions.get(i).unwrap().pos;
+        if falls_through(instructions, positions, start, i + 1, pos).is_none() {
+            for later in &mut reachable[i + 1..] {
+                *later = false;
+            }
+            break;
+        }
+    }
+    reachable
+}
+
+/// Walks the block in `start..end` backwards over its control flow graph, stepping over each `if`
+/// chain as one node whose arms are the block ranges in `positions`. Returns None if every path
+/// through the block reaches a return, or else where control falls out of it.
+pub fn falls_through(
+    instructions: &[MirInstruction],
+    positions: &HashMap<usize, (usize, usize)>,
+    start: usize,
+    end: usize,
+    fallback: &Position,
+) -> Option<(Position, &'static str)> {
+    let mut reason = None;
+
+    let mut i = end;
+    while i > start {
+        i -= 1;
+        match raw_instruction(instructions.get(i).unwrap()) {
+            RawMirInstruction::Return(_)
+            | RawMirInstruction::Break(_)
+            | RawMirInstruction::Continue(_) => return None,
+            //A while may not run, so a return in it does not count, and only a break leaves a loop
+            RawMirInstruction::Loop { right, id, .. } => {
+                let (loop_start, loop_end) = *positions.get(id).unwrap();
+                let breaks = instructions[loop_start..loop_end].iter().any(|instruction| {
+                    matches!(
+                        raw_instruction(instruction),
+                        RawMirInstruction::Break(target) if target == id
+                    )
+                });
+                if right.is_none() && !breaks {
+                    return None;
+                }
+                reason = reason.or(Some((
+                    instructions.get(i).unwrap().pos.clone(),
+                    "Control reaches the end of the block here:",
+                )));
+                i = loop_start;
+            }
+            RawMirInstruction::IfCondition { .. } => {
+                let mut has_else = false;
+                let mut arm_reason = None;
+                loop {
+                    let instruction = instructions.get(i).unwrap();
+                    let RawMirInstruction::IfCondition {
+                        check_n, right, id, ..
+                    } = raw_instruction(instruction)
+                    else {
+                        unreachable!()
+                    };
+
+                    has_else |= right.is_none();
+
+                    //Arms are visited last to first, so the first arm that falls through wins
+                    let (arm_start, arm_end) = *positions.get(id).unwrap();
+                    if let Some(res) =
+                        falls_through(instructions, positions, arm_start, arm_end, &instruction.pos)
+                    {
+                        arm_reason = Some(res);
+                    }
+
+                    if *check_n == 0 {
+                        i = arm_start;
+                        if !has_else {
+                            arm_reason = Some((
+                                instruction.pos.clone(),
+                                "This 'if' has no 'else' clause, so it may be skipped:",
+                            ));
+                        }
+                        break;
+                    }
+
+                    //Step over this arm and its condition to the previous arm of the chain
+                    i = (start..arm_start)
+                        .rev()
+                        .find(|j| {
+                            matches!(
+                                raw_instruction(instructions.get(*j).unwrap()),
+                                RawMirInstruction::IfCondition { check_n: n, .. } if n + 1 == *check_n
+                            )
+                        })
+                        .unwrap();
+                }
+
+                arm_reason.as_ref()?;
+                reason = reason.or(arm_reason);
+            }
+            _ => {
+                reason = reason.or(Some((
+                    instructions.get(i).unwrap().pos.clone(),
+                    "Control reaches the end of the block here:",
+                )));
+            }
+        }
+    }
+
+    Some(reason.unwrap_or((
+        fallback.clone(),
+        "Control reaches the end of this empty block:",
+    )))
 }
@@ -0,0 +1,44 @@
+use super::{MirInstruction, RawMirInstruction};
+
+/// A value that is known at compile time.
+#[derive(PartialEq)]
+enum Const {
+    Bool(bool),
+    Signed(i128),
+    Unsigned(u128),
+}
+
+/// Fold the value produced by instruction `idx`, if every operand it depends on is a literal.
+fn fold(instructions: &[MirInstruction], idx: usize) -> Option<Const> {
+    match &instructions.get(idx)?.instruction {
+        RawMirInstruction::Bool(value) => Some(Const::Bool(*value)),
+        RawMirInstruction::I8(value)
+        | RawMirInstruction::I16(value)
+        | RawMirInstruction::I32(value)
+        | RawMirInstruction::I64(value)
+        | RawMirInstruction::I128(value) => value.parse().ok().map(Const::Signed),
+        RawMirInstruction::U8(value)
+        | RawMirInstruction::U16(value)
+        | RawMirInstruction::U32(value)
+        | RawMirInstruction::U64(value)
+        | RawMirInstruction::U128(value) => value.parse().ok().map(Const::Unsigned),
+        RawMirInstruction::Own(right) | RawMirInstruction::Copy(right) => {
+            fold(instructions, *right)
+        }
+        RawMirInstruction::Eq { left, right } => Some(Const::Bool(
+            fold(instructions, *left)? == fold(instructions, *right)?,
+        )),
+        RawMirInstruction::Ne { left, right } => Some(Const::Bool(
+            fold(instructions, *left)? != fold(instructions, *right)?,
+        )),
+        _ => None,
+    }
+}
+
+/// The value of a condition, if it is a compile-time constant.
+pub fn const_bool(instructions: &[MirInstruction], idx: usize) -> Option<bool> {
+    match fold(instructions, idx)? {
+        Const::Bool(value) => Some(value),
+        _ => None,
+    }
+}
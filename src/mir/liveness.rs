@@ -0,0 +1,171 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+use super::{BlockName, MirInstruction, RawMirInstruction};
+
+/// The result of a backward liveness dataflow over the instructions of one block.
+pub struct Liveness<K> {
+    last_live: HashMap<K, usize>,
+}
+
+impl<K: Clone + Eq + Hash> Liveness<K> {
+    /// `uses` gives the keys an instruction reads, and `def` the key it defines, which is dead before it.
+    /// Instructions that are not `reachable` use nothing, so lifetimes end at the return before them.
+    /// Each of `back_edges` goes from the last instruction of a loop to its first.
+    fn new(
+        instructions: &[MirInstruction],
+        reachable: &[bool],
+        back_edges: &[(usize, usize)],
+        uses: impl Fn(&RawMirInstruction) -> Vec<K>,
+        def: impl Fn(usize, &RawMirInstruction) -> Option<K>,
+    ) -> Self {
+        let mut live_in: Vec<HashSet<K>> = vec![HashSet::new(); instructions.len()];
+
+        //Iterate to a fixed point, so that values used across a back-edge stay live for the whole loop
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for j in (0..instructions.len()).rev() {
+                if !reachable.get(j).unwrap() {
+                    continue;
+                }
+
+                let mut live = HashSet::new();
+                for succ in successors(instructions, back_edges, j) {
+                    live.extend(live_in.get(succ).unwrap().iter().cloned());
+                }
+
+                let instruction = &instructions.get(j).unwrap().instruction;
+                if let Some(key) = def(j, instruction) {
+                    live.remove(&key);
+                }
+                live.extend(uses(instruction));
+
+                if &live != live_in.get(j).unwrap() {
+                    *live_in.get_mut(j).unwrap() = live;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut last_live = HashMap::new();
+        for (j, live) in live_in.into_iter().enumerate() {
+            for key in live {
+                last_live.insert(key, j);
+            }
+        }
+
+        Liveness { last_live }
+    }
+
+    /// The last instruction at which `key` is live, or `start` if it is never used.
+    pub fn last_use(&self, key: &K, start: usize) -> usize {
+        self.last_live.get(key).copied().unwrap_or(start)
+    }
+}
+
+/// Nested blocks are analyzed on their own, so control falls through to the next instruction, or
+/// at the end of a loop also goes back to its start.
+fn successors(
+    instructions: &[MirInstruction],
+    back_edges: &[(usize, usize)],
+    j: usize,
+) -> Vec<usize> {
+    let mut successors = if j + 1 < instructions.len() {
+        vec![j + 1]
+    } else {
+        vec![]
+    };
+    successors.extend(
+        back_edges
+            .iter()
+            .filter(|(from, _)| *from == j)
+            .map(|(_, to)| *to),
+    );
+    successors
+}
+
+/// Liveness of the result of each instruction, keyed by its index.
+pub fn values(
+    instructions: &[MirInstruction],
+    reachable: &[bool],
+    back_edges: &[(usize, usize)],
+) -> Liveness<usize> {
+    Liveness::new(instructions, reachable, back_edges, operands, |j, _| Some(j))
+}
+
+/// Liveness of each binding, from its declaration until its last load or store.
+pub fn bindings(
+    instructions: &[MirInstruction],
+    reachable: &[bool],
+    back_edges: &[(usize, usize)],
+) -> Liveness<BlockName> {
+    Liveness::new(
+        instructions,
+        reachable,
+        back_edges,
+        |instruction| match instruction {
+            RawMirInstruction::Load(name) | RawMirInstruction::Store { name, right: _ } => {
+                vec![name.clone()]
+            }
+            _ => vec![],
+        },
+        |_, instruction| match instruction {
+            RawMirInstruction::Declare { name, is_mut: _ } => Some(name.clone()),
+            _ => None,
+        },
+    )
+}
+
+fn operands(instruction: &RawMirInstruction) -> Vec<usize> {
+    match instruction {
+        RawMirInstruction::Add { left, right }
+        | RawMirInstruction::Sub { left, right }
+        | RawMirInstruction::Mul { left, right }
+        | RawMirInstruction::Div { left, right }
+        | RawMirInstruction::Rem { left, right }
+        | RawMirInstruction::Eq { left, right }
+        | RawMirInstruction::Ne { left, right } => vec![*left, *right],
+        RawMirInstruction::Own(right)
+        | RawMirInstruction::Store { name: _, right }
+        | RawMirInstruction::Reference(right)
+        | RawMirInstruction::Copy(right)
+        | RawMirInstruction::Return(right)
+        | RawMirInstruction::Deref(right)
+        | RawMirInstruction::Cast(right) => vec![*right],
+        RawMirInstruction::IfCondition {
+            code: _,
+            check_n: _,
+            right,
+            offset: _,
+            id: _,
+        } => right.iter().copied().collect(),
+        RawMirInstruction::I8(_)
+        | RawMirInstruction::I16(_)
+        | RawMirInstruction::I32(_)
+        | RawMirInstruction::I64(_)
+        | RawMirInstruction::I128(_)
+        | RawMirInstruction::U8(_)
+        | RawMirInstruction::U16(_)
+        | RawMirInstruction::U32(_)
+        | RawMirInstruction::U64(_)
+        | RawMirInstruction::U128(_)
+        | RawMirInstruction::F32(_)
+        | RawMirInstruction::F64(_)
+        | RawMirInstruction::Bool(_)
+        | RawMirInstruction::Str(_)
+        | RawMirInstruction::Asm(_)
+        | RawMirInstruction::Loop { .. }
+        | RawMirInstruction::Break(_)
+        | RawMirInstruction::Continue(_)
+        | RawMirInstruction::Unit
+        | RawMirInstruction::Param(_)
+        | RawMirInstruction::Declare { name: _, is_mut: _ }
+        | RawMirInstruction::Load(_)
+        | RawMirInstruction::CallFunction(_)
+        | RawMirInstruction::InstructionWrapper(_)
+        | RawMirInstruction::NoOp => vec![],
+    }
+}
@@ -2,7 +2,7 @@ use std::io::{self, Write};
 
 use indexmap::IndexMap;
 
-use crate::{mir::output_mir, types::Lifetime, utils::FileInfo};
+use crate::{mir::output_mir, types::Lifetime};
 
 use super::{Block, Mir, MirInstruction, MirReference};
 
@@ -12,7 +12,6 @@ pub fn explore(
     instructions: &[MirInstruction<'_>],
     block: Block,
     references: IndexMap<usize, MirReference>,
-    info: FileInfo,
 ) {
     let mut buf = String::from("");
     println!("Kestrel MIR Debugger");
@@ -53,8 +52,9 @@ pub fn explore(
                         &instructions[*start_mir..=*end_mir],
                         &mut out,
                         start_mir,
-                        &info,
-                        this.blocks.clone(),
+                        &this.session.info,
+                        &this.blocks,
+                        this.session.mir_color,
                     );
                     println!("{out}");
                 }
@@ -86,8 +86,9 @@ pub fn explore(
                         &instructions[*start_mir..=*end_mir],
                         &mut out,
                         start_mir,
-                        &info,
-                        this.blocks.clone(),
+                        &this.session.info,
+                        &this.blocks,
+                        this.session.mir_color,
                     );
                     println!("{out}");
                 }
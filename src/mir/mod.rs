@@ -1,33 +1,63 @@
-use std::{collections::HashMap, fs::OpenOptions, io::Write};
+use std::{collections::HashMap, rc::Rc};
 
+use colored::Color;
 use indexmap::IndexMap;
 
 use crate::{
-    codegen::{BindingTags, CodegenFunctions, CustomTypes},
-    errors::{raise_error, raise_error_multi, ErrorType},
-    parser::nodes::{Node, NodeType, OpType},
-    types::{implements_trait, BasicType, BuiltinTypes, Lifetime, Trait, TraitType, Type},
-    utils::{FileInfo, Position},
+    codegen::{BindingTags, CodegenFunctions},
+    errors::{raise_error, raise_error_multi, raise_warning_multi, ErrorType, WarningType},
+    parser::nodes::{Node, NodeType},
+    types::{
+        builtins::printf::check_format, check_float_literal, check_int_literal, implements_trait, method_trait, operator_trait,
+        registry::TypeRegistry, BasicType, Lifetime, StdFunctions, TraitType, Type,
+    },
+    session::Session,
+    utils::{edit_distance, FileInfo, Position},
 };
 
 use self::mirxplore::explore;
 
+pub use self::check::{has_loop, is_leaf};
+
 mod check;
+mod fold;
+mod liveness;
 mod mirxplore;
 
 #[allow(dead_code)]
 pub struct Mir<'a> {
-    pub info: FileInfo<'a>,
+    pub session: &'a Session<'a>,
     fn_name: String,
     fn_pos: Position,
     instructions: Vec<MirInstruction<'a>>,
-    pub builtins: BuiltinTypes<'a>,
-    types: CustomTypes<'a>,
+    pub types: TypeRegistry<'a>,
     functions: CodegenFunctions<'a>,
-    debug_mir: bool,
+    std_fns: StdFunctions<'a>,
     cur_block: usize,
     blocks: Vec<Block<'a>>,
     block_positions: HashMap<usize, (usize, usize)>, //(start(inclusive), end(exclusive))
+    init: HashMap<BlockName, InitState>,
+    /// Each `let mut` in the function, and whether it is assigned to after being initialized.
+    mut_bindings: Vec<(BlockName, Position, bool)>,
+    /// The loops enclosing the instruction being generated, innermost last.
+    loops: Vec<LoopJumps>,
+    /// The textual MIR of the function, filled in once it has been checked.
+    pub output: String,
+}
+
+/// Whether a binding has been assigned on every path that reaches the instruction being generated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum InitState {
+    Init,
+    Uninit,
+    MaybeInit,
+}
+
+/// The initialization states at each `break` and `continue` of a loop.
+struct LoopJumps {
+    id: usize,
+    breaks: Vec<HashMap<BlockName, InitState>>,
+    continues: Vec<HashMap<BlockName, InitState>>,
 }
 
 #[allow(dead_code)]
@@ -36,11 +66,14 @@ pub struct Block<'a> {
     namespace_check: MirNamespace,
     parents: Vec<usize>,
     blockid: usize,
-    namespace: HashMap<String, (Type<'a>, BindingTags)>,
+    namespace: HashMap<String, (Type<'a>, BindingTags, Position)>, //(tp, tags, where tp comes from)
     instructions: Option<Vec<MirInstruction<'a>>>,
+    is_unsafe: bool,
+    /// Whether control returns to the start of the block after its end.
+    is_loop: bool,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct BlockName {
     name: String,
     blockid: usize,
@@ -58,10 +91,28 @@ pub enum RawMirInstruction<'a> {
     U32(String),
     U64(String),
     U128(String),
+    F32(String),
+    F64(String),
     Add {
         left: usize,
         right: usize,
     },
+    Sub {
+        left: usize,
+        right: usize,
+    },
+    Mul {
+        left: usize,
+        right: usize,
+    },
+    Div {
+        left: usize,
+        right: usize,
+    },
+    Rem {
+        left: usize,
+        right: usize,
+    },
     Declare {
         name: BlockName,
         is_mut: bool,
@@ -86,15 +137,28 @@ pub enum RawMirInstruction<'a> {
         right: usize,
     },
     Deref(usize),
+    Cast(usize),
+    Asm(String),
     IfCondition {
-        code: Vec<MirInstruction<'a>>,
+        code: Rc<Vec<MirInstruction<'a>>>,
         check_n: usize,
         right: Option<usize>,
         offset: usize,
         id: usize,
     },
+    Break(usize),
+    Continue(usize),
+    Loop {
+        code: Rc<Vec<MirInstruction<'a>>>,
+        right: Option<usize>,
+        offset: usize,
+        id: usize,
+    },
     InstructionWrapper(Box<MirInstruction<'a>>),
     NoOp,
+    Str(String),
+    Unit,
+    Param(usize),
 }
 
 #[derive(Clone, Debug)]
@@ -120,7 +184,7 @@ pub struct MirTag {
     lifetime: Lifetime,
 }
 
-type MirNamespace = HashMap<String, (Option<usize>, Option<usize>, MirTag)>; //(declaration, right, tag)
+type MirNamespace = IndexMap<String, (Option<usize>, Option<usize>, MirTag)>; //(declaration, right, tag)
 type MirReference = (usize, ReferenceType, Lifetime, ReferenceBase, usize); //(right, type, lifetime, referred, blockid)
 
 #[derive(Debug, Eq, PartialOrd, Ord, Clone)]
@@ -154,11 +218,23 @@ impl PartialEq for ReferenceBase {
 }
 
 impl<'a> RawMirInstruction<'a> {
-    fn fmt(&self, f: &mut String, blocks: Vec<Block>, info: &FileInfo) {
+    fn fmt(&self, f: &mut String, blocks: &[Block], info: &FileInfo, color: bool) {
         f.push_str(&match self {
             RawMirInstruction::Add { left, right } => {
                 format!("add .{left} .{right}")
             }
+            RawMirInstruction::Sub { left, right } => {
+                format!("sub .{left} .{right}")
+            }
+            RawMirInstruction::Mul { left, right } => {
+                format!("mul .{left} .{right}")
+            }
+            RawMirInstruction::Div { left, right } => {
+                format!("div .{left} .{right}")
+            }
+            RawMirInstruction::Rem { left, right } => {
+                format!("rem .{left} .{right}")
+            }
             RawMirInstruction::Declare { name, is_mut } => {
                 format!("declare {}{}", if *is_mut { "mut " } else { "" }, name.name)
             }
@@ -195,6 +271,13 @@ impl<'a> RawMirInstruction<'a> {
             RawMirInstruction::Bool(value) => {
                 format!("bool {value}")
             }
+            RawMirInstruction::Str(value) => {
+                format!("str {value:?}")
+            }
+            RawMirInstruction::Unit => "unit".into(),
+            RawMirInstruction::Param(index) => {
+                format!("param {index}")
+            }
             RawMirInstruction::U8(value) => {
                 format!("u8 {value}")
             }
@@ -210,6 +293,12 @@ impl<'a> RawMirInstruction<'a> {
             RawMirInstruction::U128(value) => {
                 format!("u128 {value}")
             }
+            RawMirInstruction::F32(value) => {
+                format!("f32 {value}")
+            }
+            RawMirInstruction::F64(value) => {
+                format!("f64 {value}")
+            }
             RawMirInstruction::Return(right) => {
                 format!("return .{right}")
             }
@@ -225,15 +314,22 @@ impl<'a> RawMirInstruction<'a> {
             RawMirInstruction::Deref(right) => {
                 format!("deref .{right}")
             }
+            RawMirInstruction::Cast(right) => {
+                format!("cast .{right}")
+            }
+            RawMirInstruction::Asm(value) => {
+                format!("asm {value:?}")
+            }
             RawMirInstruction::IfCondition {
                 code,
                 check_n,
                 right,
                 offset,
-                id: _,
+                id,
             } => {
                 let mut out = String::new();
-                output_mir(&code[*offset..], &mut out, &0, info, blocks);
+                out.push_str(&block_header(blocks, *id, color));
+                output_mir(&code[*offset..], &mut out, &0, info, blocks, color);
                 out = out
                     .split('\n')
                     .map(|x| String::from("    ") + x)
@@ -245,6 +341,31 @@ impl<'a> RawMirInstruction<'a> {
                     format!("ifcondition #{check_n} {{\n{out}}}")
                 }
             }
+            RawMirInstruction::Break(id) => {
+                format!("break block {id}")
+            }
+            RawMirInstruction::Continue(id) => {
+                format!("continue block {id}")
+            }
+            RawMirInstruction::Loop {
+                code,
+                right,
+                offset,
+                id,
+            } => {
+                let mut out = String::new();
+                out.push_str(&block_header(blocks, *id, color));
+                output_mir(&code[*offset..], &mut out, &0, info, blocks, color);
+                out = out
+                    .split('\n')
+                    .map(|x| String::from("    ") + x)
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                match right {
+                    Some(right) => format!("loop while .{right} {{\n{out}}}"),
+                    None => format!("loop {{\n{out}}}"),
+                }
+            }
             RawMirInstruction::InstructionWrapper(_) => "".into(),
             RawMirInstruction::NoOp => "noop".into(),
         })
@@ -252,35 +373,83 @@ impl<'a> RawMirInstruction<'a> {
 }
 
 pub fn new<'a>(
-    info: FileInfo<'a>,
-    builtins: BuiltinTypes<'a>,
-    types: CustomTypes<'a>,
+    session: &'a Session<'a>,
+    types: TypeRegistry<'a>,
     functions: CodegenFunctions<'a>,
+    std_fns: StdFunctions<'a>,
     fn_name: String,
     fn_pos: Position,
-    debug_mir: bool,
 ) -> Mir<'a> {
     let cur = Block {
-        namespace_check: HashMap::new(),
+        namespace_check: IndexMap::new(),
         namespace: HashMap::new(),
         parents: vec![0],
         blockid: 0,
         instructions: None,
+        is_unsafe: false,
+        is_loop: false,
     };
     let mut positions = HashMap::new();
     positions.insert(0, (0, 0));
     Mir {
-        info,
+        session,
         fn_name,
         fn_pos,
         instructions: Vec::new(),
-        builtins,
         types,
         functions,
-        debug_mir,
+        std_fns,
         cur_block: 0,
         blocks: vec![cur],
         block_positions: positions,
+        init: HashMap::new(),
+        mut_bindings: Vec::new(),
+        loops: Vec::new(),
+        output: String::new(),
+    }
+}
+
+/// Merges the initialization states at the end of each path that falls through a branch.
+fn merge_init(
+    init: &HashMap<BlockName, InitState>,
+    paths: &[HashMap<BlockName, InitState>],
+) -> HashMap<BlockName, InitState> {
+    if paths.is_empty() {
+        return init.clone();
+    }
+
+    init.keys()
+        .map(|name| {
+            let mut states = paths.iter().map(|path| *path.get(name).unwrap());
+            let first = states.next().unwrap();
+            let state = if states.all(|state| state == first) {
+                first
+            } else {
+                InitState::MaybeInit
+            };
+            (name.clone(), state)
+        })
+        .collect()
+}
+
+/// The signature of a user function as it is written in source, with its types resolved.
+fn signature(name: &str, fnnode: &Node, params: &[Type], rettp: &Type) -> String {
+    let params = fnnode
+        .data
+        .get_data()
+        .mapping
+        .unwrap()
+        .iter()
+        .zip(params)
+        .map(|((param, _), tp)| {
+            format!("{}: {}", param.data.get_data().raw.get("value").unwrap(), tp.qualname())
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    if rettp.basictype == BasicType::Void {
+        format!("fn {name}({params})")
+    } else {
+        format!("fn {name}({params}): {}", rettp.qualname())
     }
 }
 
@@ -292,9 +461,13 @@ pub fn check<'a>(
 ) {
     let references = check::generate_lifetimes(this, instructions, block_res, blockid);
     check::check_references(this, instructions, &references, blockid);
-    check::check_return(this, instructions);
     if block_res.is_none() {
-        if !this.debug_mir {
+        check::check_return(this, instructions);
+        check::check_unused_mut(this);
+        if this.session.mir_stats {
+            print_stats(this, instructions);
+        }
+        if !this.session.debug_mir {
             write_mir(
                 this,
                 instructions.clone(),
@@ -307,37 +480,118 @@ pub fn check<'a>(
                 instructions,
                 this.blocks.first().unwrap().clone(),
                 references.clone(),
-                this.info.clone(),
             );
         }
     }
 }
 
+/// Wraps `text` in the ANSI escapes for `fg`, if `color` is set.
+fn paint(text: &str, fg: Color, color: bool) -> String {
+    if color {
+        format!("\x1b[{}m{text}\x1b[0m", fg.to_fg_str())
+    } else {
+        text.to_string()
+    }
+}
+
+/// A header naming the block and its enclosing blocks (outermost first), followed by the
+/// bindings declared in it and their lifetimes.
+fn block_header(blocks: &[Block], blockid: usize, color: bool) -> String {
+    let block = blocks.get(blockid).unwrap();
+
+    let parents = block
+        .parents
+        .iter()
+        .filter(|parent| **parent != blockid)
+        .map(|parent| parent.to_string())
+        .collect::<Vec<_>>();
+    let mut header = format!("block {blockid}");
+    if !parents.is_empty() {
+        header.push_str(&format!(" (parents: {})", parents.join(", ")));
+    }
+    let mut out = paint(&header, Color::Cyan, color);
+    out.push('\n');
+
+    let mut bindings = block.namespace_check.iter().collect::<Vec<_>>();
+    bindings.sort_by_key(|(_, (declaration, _, _))| *declaration);
+    for (name, (_, _, tag)) in bindings {
+        out.push_str(&format!(
+            "    binding {}{name}{}\n",
+            if tag.is_mut { "mut " } else { "" },
+            paint(&tag.lifetime.to_string(), Color::Magenta, color)
+        ));
+    }
+
+    out
+}
+
 pub fn output_mir(
     instructions: &[MirInstruction<'_>],
     out: &mut String,
     start: &usize,
     info: &FileInfo,
-    blocks: Vec<Block>,
+    blocks: &[Block],
+    color: bool,
 ) {
     let mut cur_line = None;
 
-    for (i, instruction) in instructions.iter().enumerate() {
+    let lines = instructions
+        .iter()
+        .map(|instruction| {
+            let mut line = String::new();
+            instruction
+                .instruction
+                .fmt(&mut line, blocks, info, color);
+            line
+        })
+        .collect::<Vec<_>>();
+
+    //Types line up in one column, past the longest single line instruction
+    let width = lines
+        .iter()
+        .filter(|line| !line.contains('\n'))
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    for (i, (instruction, line)) in instructions.iter().zip(lines).enumerate() {
         if let RawMirInstruction::InstructionWrapper(_) = &instruction.instruction {
             continue;
         }
         if Some(instruction.pos.line) != cur_line {
             cur_line = Some(instruction.pos.line);
             out.push_str("    ");
-            out.push_str(&format!("{}:{}\n", info.name, instruction.pos.line + 1));
+            out.push_str(&paint(
+                &format!("{}:{}", info.name, instruction.pos.line + 1),
+                Color::BrightBlack,
+                color,
+            ));
+            out.push('\n');
         }
 
         out.push_str("    ");
-        out.push_str(&format!(".{:<5}", format!("{}:", i + start)));
-        instruction.instruction.fmt(out, blocks.clone(), info);
+        out.push_str(&paint(
+            &format!(".{:<5}", format!("{}:", i + start)),
+            Color::BrightBlack,
+            color,
+        ));
+
+        let padding = if line.contains('\n') {
+            0
+        } else {
+            width - line.chars().count()
+        };
+        match line.split_once(' ') {
+            Some((opcode, operands)) => {
+                out.push_str(&paint(opcode, Color::Blue, color));
+                out.push(' ');
+                out.push_str(operands);
+            }
+            None => out.push_str(&paint(&line, Color::Blue, color)),
+        }
 
         if let RawMirInstruction::Declare { name, is_mut: _ } = &instruction.instruction {
-            out.push_str(
+            out.push_str(&paint(
                 &blocks
                     .get(name.blockid)
                     .unwrap()
@@ -347,40 +601,111 @@ pub fn output_mir(
                     .2
                     .lifetime
                     .to_string(),
-            );
+                Color::Magenta,
+                color,
+            ));
         }
 
         if instruction.tp.is_some() {
-            out.push_str(&format!(
-                " -> {}",
-                instruction.tp.as_ref().unwrap().qualname()
+            out.push_str(&" ".repeat(padding));
+            out.push_str(" -> ");
+            out.push_str(&paint(
+                &instruction.tp.as_ref().unwrap().qualname(),
+                Color::Green,
+                color,
+            ));
+            out.push_str(&paint(
+                &instruction.tp.as_ref().unwrap().lifetime.to_string(),
+                Color::Magenta,
+                color,
             ));
-            out.push_str(&format!("{}", instruction.tp.as_ref().unwrap().lifetime));
         }
 
         if instruction.last_use.is_some() {
-            out.push_str("  dropbinding ");
-            out.push_str(instruction.last_use.as_ref().unwrap());
+            out.push_str("  ");
+            out.push_str(&paint(
+                &format!("dropbinding {}", instruction.last_use.as_ref().unwrap()),
+                Color::Red,
+                color,
+            ));
         }
 
         out.push('\n');
     }
 }
 
+/// Prints the size of a function's MIR and its longest lifetime, to spot pathological lowering.
+fn print_stats(this: &Mir, instructions: &[MirInstruction]) {
+    let references = instructions
+        .iter()
+        .filter(|instruction| match &instruction.instruction {
+            RawMirInstruction::InstructionWrapper(inner) => {
+                matches!(inner.instruction, RawMirInstruction::Reference(_))
+            }
+            instruction => matches!(instruction, RawMirInstruction::Reference(_)),
+        })
+        .count();
+
+    let bindings = this
+        .blocks
+        .iter()
+        .flat_map(|block| block.namespace_check.values().map(|(_, _, tag)| &tag.lifetime));
+    let values = instructions
+        .iter()
+        .filter_map(|instruction| instruction.tp.as_ref().map(|tp| &tp.lifetime));
+    let longest = bindings
+        .chain(values)
+        .filter_map(|lifetime| match lifetime {
+            Lifetime::ImplicitLifetime {
+                name: _,
+                start_mir,
+                end_mir,
+            } => Some((end_mir - start_mir, lifetime)),
+            Lifetime::Static => None,
+        })
+        .max_by_key(|(len, _)| *len);
+
+    println!(
+        "fn {}: {} instructions, {} blocks, {} references, longest lifetime {}",
+        this.fn_name,
+        instructions.len(),
+        this.blocks.len(),
+        references,
+        match longest {
+            Some((len, lifetime)) => format!("{lifetime} ({len} instructions)"),
+            None => "none".into(),
+        }
+    );
+}
+
 pub fn write_mir(
     this: &mut Mir,
     instructions: Vec<MirInstruction<'_>>,
     _namespace: Block,
     references: &IndexMap<usize, MirReference>,
 ) {
+    //The generated main of a file without one is not in the function table, and returns 0
+    let rettp = match this.functions.get(&this.fn_name) {
+        Some(func) => func.1 .1.qualname(),
+        None => this.types.builtins.get(&BasicType::I32).unwrap().qualname(),
+    };
     let mut out = String::new();
-    out.push_str(&format!(
-        "fn {}: {} {{\n",
-        this.fn_name,
-        this.functions.get(&this.fn_name).unwrap().1 .1.qualname()
-    ));
+    out.push_str(&format!("fn {}: {} {{\n", this.fn_name, rettp));
+
+    for line in block_header(&this.blocks, 0, this.session.mir_color).lines() {
+        out.push_str("    ");
+        out.push_str(line);
+        out.push('\n');
+    }
 
-    output_mir(&instructions, &mut out, &0, &this.info, this.blocks.clone());
+    output_mir(
+        &instructions,
+        &mut out,
+        &0,
+        &this.session.info,
+        &this.blocks,
+        this.session.mir_color,
+    );
 
     out.push('\n');
 
@@ -405,20 +730,59 @@ pub fn write_mir(
 
     out.push('}');
 
-    let mut f = OpenOptions::new()
-        .write(true)
-        .append(true)
-        .open("a.mir")
-        .expect("Unable to open MIR output file.");
-
-    if f.metadata().unwrap().len() > 0 {
-        f.write_all("\n\n".as_bytes())
-            .expect("Unable to write MIR.");
-    }
-    f.write_all(out.as_bytes()).expect("Unable to write MIR.");
+    this.output = out;
 }
 
 impl<'a> Mir<'a> {
+    /// Binds each parameter of the function, as if by a `let` of the argument passed for it.
+    pub fn generate_params(&mut self, params: &[(Node, Node)], tps: &[Type<'a>]) {
+        for (index, ((param, paramtp), tp)) in params.iter().zip(tps).enumerate() {
+            let name = param.data.get_data().raw.get("value").unwrap().clone();
+            let blockname = BlockName {
+                name: name.clone(),
+                blockid: self.cur_block,
+            };
+
+            self.instructions.push(MirInstruction {
+                instruction: RawMirInstruction::Declare {
+                    name: blockname.clone(),
+                    is_mut: false,
+                },
+                pos: param.pos.clone(),
+                tp: None,
+                last_use: None,
+            });
+            self.instructions.push(MirInstruction {
+                instruction: RawMirInstruction::Param(index),
+                pos: param.pos.clone(),
+                tp: Some(tp.clone()),
+                last_use: None,
+            });
+            let right = self.instructions.len() - 1;
+            self.instructions.push(MirInstruction {
+                instruction: RawMirInstruction::Own(right),
+                pos: param.pos.clone(),
+                tp: None,
+                last_use: None,
+            });
+            self.instructions.push(MirInstruction {
+                instruction: RawMirInstruction::Store {
+                    name: blockname.clone(),
+                    right,
+                },
+                pos: param.pos.clone(),
+                tp: Some(self.types.builtins.get(&BasicType::Void).unwrap().clone()),
+                last_use: None,
+            });
+
+            self.blocks.get_mut(self.cur_block).unwrap().namespace.insert(
+                name,
+                (tp.clone(), BindingTags { is_mut: false }, paramtp.pos.clone()),
+            );
+            self.init.insert(blockname, InitState::Init);
+        }
+    }
+
     pub fn generate(&mut self, ast: &Vec<Node>) -> Vec<MirInstruction<'a>> {
         let n = self.blocks.len() - 1;
         for node in ast {
@@ -431,498 +795,236 @@ impl<'a> Mir<'a> {
 
     fn generate_expr(&mut self, node: &Node) -> MirResult<'a> {
         match node.tp {
-            NodeType::I8 => self.generate_i8(node),
-            NodeType::I16 => self.generate_i16(node),
-            NodeType::I32 => self.generate_i32(node),
-            NodeType::I64 => self.generate_i64(node),
-            NodeType::I128 => self.generate_i128(node),
+            NodeType::I8 => self.generate_int(node, BasicType::I8),
+            NodeType::I16 => self.generate_int(node, BasicType::I16),
+            NodeType::I32 => self.generate_int(node, BasicType::I32),
+            NodeType::I64 => self.generate_int(node, BasicType::I64),
+            NodeType::I128 => self.generate_int(node, BasicType::I128),
             NodeType::Binary => self.generate_binary(node),
             NodeType::Let => self.generate_let(node),
             NodeType::Identifier => self.generate_load(node),
             NodeType::Store => self.generate_store(node),
             NodeType::Reference => self.generate_reference(node),
             NodeType::Bool => self.generate_bool(node),
-            NodeType::U8 => self.generate_u8(node),
-            NodeType::U16 => self.generate_u16(node),
-            NodeType::U32 => self.generate_u32(node),
-            NodeType::U64 => self.generate_u64(node),
-            NodeType::U128 => self.generate_u128(node),
+            NodeType::U8 => self.generate_int(node, BasicType::U8),
+            NodeType::U16 => self.generate_int(node, BasicType::U16),
+            NodeType::U32 => self.generate_int(node, BasicType::U32),
+            NodeType::U64 => self.generate_int(node, BasicType::U64),
+            NodeType::U128 => self.generate_int(node, BasicType::U128),
+            NodeType::F32 => self.generate_float(node, BasicType::F32),
+            NodeType::F64 => self.generate_float(node, BasicType::F64),
             NodeType::Return => self.generate_return(node),
             NodeType::Fn => unreachable!(),
             NodeType::Call => self.generate_call(node),
             NodeType::Deref => self.generate_deref(node),
             NodeType::Conditional => self.generate_if(node),
-            NodeType::Enum => self.generate_enum(node), //TODO
+            NodeType::Enum | NodeType::TypeAlias => self.generate_type_def(node),
+            NodeType::Str => self.generate_str(node),
+            NodeType::Unit => self.generate_unit(node),
+            NodeType::RawPtr | NodeType::Slice => raise_error(
+                "Expected an expression, got a type.",
+                ErrorType::InvalidTok,
+                &node.pos,
+                &self.session.info,
+            ),
+            NodeType::Cast => self.generate_cast(node),
+            NodeType::Unsafe => self.generate_unsafe(node),
+            NodeType::While => {
+                self.generate_loop(node, Some(node.data.get_data().nodes.get("expr").unwrap()))
+            }
+            NodeType::Loop => self.generate_loop(node, None),
+            NodeType::Break | NodeType::Continue => self.generate_jump(node),
+            NodeType::Macro => self.generate_macro(node),
         }
     }
 }
 
 impl<'a> Mir<'a> {
-    fn generate_i8(&mut self, node: &Node) -> MirResult<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<i8>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "i8 literal in radix 10 out of bounds ({} to {}).",
-                i8::MAX,
-                i8::MIN
-            );
-            raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                &self.info,
-            );
+    fn generate_int(&mut self, node: &Node, basictype: BasicType) -> MirResult<'a> {
+        let value = node.data.get_data().raw.get("value").unwrap().to_string();
+        if let Err(msg) = check_int_literal(&basictype, &value) {
+            raise_error(&msg, ErrorType::InvalidLiteralForRadix, &node.pos, &self.session.info);
         }
 
+        self.generate_int_value(node, basictype, value)
+    }
+
+    fn generate_int_value(
+        &mut self,
+        node: &Node,
+        basictype: BasicType,
+        value: String,
+    ) -> MirResult<'a> {
+
+        let instruction = match basictype {
+            BasicType::I8 => RawMirInstruction::I8(value),
+            BasicType::I16 => RawMirInstruction::I16(value),
+            BasicType::I32 => RawMirInstruction::I32(value),
+            BasicType::I64 => RawMirInstruction::I64(value),
+            BasicType::I128 => RawMirInstruction::I128(value),
+            BasicType::U8 => RawMirInstruction::U8(value),
+            BasicType::U16 => RawMirInstruction::U16(value),
+            BasicType::U32 => RawMirInstruction::U32(value),
+            BasicType::U64 => RawMirInstruction::U64(value),
+            BasicType::U128 => RawMirInstruction::U128(value),
+            _ => unreachable!(),
+        };
+        let tp = self.types.builtins.get(&basictype).unwrap().clone();
+
         self.instructions.push(MirInstruction {
-            instruction: RawMirInstruction::I8(
-                node.data.get_data().raw.get("value").unwrap().to_string(),
-            ),
+            instruction,
             pos: node.pos.clone(),
-            tp: Some(self.builtins.get(&BasicType::I8).unwrap().clone()),
+            tp: Some(tp.clone()),
             last_use: None,
         });
 
-        (
-            self.instructions.len() - 1,
-            self.builtins.get(&BasicType::I8).unwrap().clone(),
-        )
+        (self.instructions.len() - 1, tp)
     }
 
-    fn generate_i16(&mut self, node: &Node) -> MirResult<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<i16>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "i16 literal in radix 10 out of bounds ({} to {}).",
-                i16::MAX,
-                i16::MIN
-            );
-            raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                &self.info,
-            );
+    fn generate_float(&mut self, node: &Node, basictype: BasicType) -> MirResult<'a> {
+        let value = node.data.get_data().raw.get("value").unwrap().to_string();
+        if let Err(msg) = check_float_literal(&basictype, &value) {
+            raise_error(&msg, ErrorType::InvalidLiteralForRadix, &node.pos, &self.session.info);
         }
 
+        let instruction = match basictype {
+            BasicType::F32 => RawMirInstruction::F32(value),
+            _ => RawMirInstruction::F64(value),
+        };
+        let tp = self.types.builtins.get(&basictype).unwrap().clone();
+
         self.instructions.push(MirInstruction {
-            instruction: RawMirInstruction::I16(
-                node.data.get_data().raw.get("value").unwrap().to_string(),
-            ),
+            instruction,
             pos: node.pos.clone(),
-            tp: Some(self.builtins.get(&BasicType::I16).unwrap().clone()),
+            tp: Some(tp.clone()),
             last_use: None,
         });
 
-        (
-            self.instructions.len() - 1,
-            self.builtins.get(&BasicType::I16).unwrap().clone(),
-        )
+        (self.instructions.len() - 1, tp)
     }
 
-    fn generate_i32(&mut self, node: &Node) -> MirResult<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<i32>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "i32 literal in radix 10 out of bounds ({} to {}).",
-                i32::MAX,
-                i32::MIN
-            );
-            raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                &self.info,
-            );
-        }
-
+    fn generate_bool(&mut self, node: &Node) -> MirResult<'a> {
         self.instructions.push(MirInstruction {
-            instruction: RawMirInstruction::I32(
-                node.data.get_data().raw.get("value").unwrap().to_string(),
+            instruction: RawMirInstruction::Bool(
+                *node.data.get_data().booleans.get("value").unwrap(),
             ),
             pos: node.pos.clone(),
-            tp: Some(self.builtins.get(&BasicType::I32).unwrap().clone()),
+            tp: Some(self.types.builtins.get(&BasicType::Bool).unwrap().clone()),
             last_use: None,
         });
 
         (
             self.instructions.len() - 1,
-            self.builtins.get(&BasicType::I32).unwrap().clone(),
+            self.types.builtins.get(&BasicType::Bool).unwrap().clone(),
         )
     }
 
-    fn generate_i64(&mut self, node: &Node) -> MirResult<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<i64>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "i64 literal in radix 10 out of bounds ({} to {}).",
-                i64::MAX,
-                i64::MIN
-            );
-            raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                &self.info,
-            );
-        }
-
+    fn generate_str(&mut self, node: &Node) -> MirResult<'a> {
         self.instructions.push(MirInstruction {
-            instruction: RawMirInstruction::I64(
-                node.data.get_data().raw.get("value").unwrap().to_string(),
+            instruction: RawMirInstruction::Str(
+                node.data.get_data().raw.get("value").unwrap().clone(),
             ),
             pos: node.pos.clone(),
-            tp: Some(self.builtins.get(&BasicType::I64).unwrap().clone()),
+            tp: Some(self.types.builtins.get(&BasicType::Str).unwrap().clone()),
             last_use: None,
         });
 
         (
             self.instructions.len() - 1,
-            self.builtins.get(&BasicType::I64).unwrap().clone(),
+            self.types.builtins.get(&BasicType::Str).unwrap().clone(),
         )
     }
 
-    fn generate_i128(&mut self, node: &Node) -> MirResult<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<i128>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "i128 literal in radix 10 out of bounds ({} to {}).",
-                i128::MAX,
-                i128::MIN
-            );
-            raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                &self.info,
-            );
-        }
-
+    fn generate_unit(&mut self, node: &Node) -> MirResult<'a> {
         self.instructions.push(MirInstruction {
-            instruction: RawMirInstruction::I128(
-                node.data.get_data().raw.get("value").unwrap().to_string(),
-            ),
+            instruction: RawMirInstruction::Unit,
             pos: node.pos.clone(),
-            tp: Some(self.builtins.get(&BasicType::I128).unwrap().clone()),
+            tp: Some(self.types.builtins.get(&BasicType::Void).unwrap().clone()),
             last_use: None,
         });
 
         (
             self.instructions.len() - 1,
-            self.builtins.get(&BasicType::I128).unwrap().clone(),
+            self.types.builtins.get(&BasicType::Void).unwrap().clone(),
         )
     }
 
-    fn generate_u8(&mut self, node: &Node) -> MirResult<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<u8>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "u8 literal in radix 10 out of bounds ({} to {}).",
-                u8::MAX,
-                u8::MIN
-            );
-            raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                &self.info,
-            );
-        }
+    fn generate_binary(&mut self, node: &Node) -> MirResult<'a> {
+        let binary = node.data.get_data();
+        let left = self.generate_expr(binary.nodes.get("left").unwrap());
+        let right = self.generate_expr(binary.nodes.get("right").unwrap());
 
-        self.instructions.push(MirInstruction {
-            instruction: RawMirInstruction::U8(
-                node.data.get_data().raw.get("value").unwrap().to_string(),
-            ),
-            pos: node.pos.clone(),
-            tp: Some(self.builtins.get(&BasicType::U8).unwrap().clone()),
-            last_use: None,
-        });
+        let (traittp, name) = operator_trait(&binary.op.unwrap());
 
-        (
-            self.instructions.len() - 1,
-            self.builtins.get(&BasicType::U8).unwrap().clone(),
-        )
+        self.generate_operator(node, traittp, name, left, right)
     }
 
-    fn generate_u16(&mut self, node: &Node) -> MirResult<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<u16>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "u16 literal in radix 10 out of bounds ({} to {}).",
-                u16::MAX,
-                u16::MIN
-            );
+    fn generate_operator(
+        &mut self,
+        node: &Node,
+        traittp: TraitType,
+        name: &str,
+        left: (usize, Type<'a>),
+        right: (usize, Type<'a>),
+    ) -> MirResult<'a> {
+        //Errors in a binary expression point at its operator, and in a method call at the call
+        let pos = match node.data.get_data().op {
+            Some(op) => node.pos.operator(op.symbol().len()),
+            None => node.pos.clone(),
+        };
+        let res = if let Some((_, skeleton)) = left.1.operator(&traittp) {
+            skeleton(self, &pos, left.1, right.1)
+        } else {
             raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                &self.info,
+                &format!("Type '{}' does not implement '{name}'.", left.1.qualname()),
+                ErrorType::TraitNotImplemented,
+                &pos,
+                &self.session.info,
             );
-        }
+        };
+
+        let instruction = match traittp {
+            TraitType::Add => RawMirInstruction::Add {
+                left: left.0,
+                right: right.0,
+            },
+            TraitType::Sub => RawMirInstruction::Sub {
+                left: left.0,
+                right: right.0,
+            },
+            TraitType::Mul => RawMirInstruction::Mul {
+                left: left.0,
+                right: right.0,
+            },
+            TraitType::Div => RawMirInstruction::Div {
+                left: left.0,
+                right: right.0,
+            },
+            TraitType::Rem => RawMirInstruction::Rem {
+                left: left.0,
+                right: right.0,
+            },
+            TraitType::Eq => RawMirInstruction::Eq {
+                left: left.0,
+                right: right.0,
+            },
+            TraitType::Ne => RawMirInstruction::Ne {
+                left: left.0,
+                right: right.0,
+            },
+            _ => {
+                unreachable!();
+            }
+        };
 
         self.instructions.push(MirInstruction {
-            instruction: RawMirInstruction::U16(
-                node.data.get_data().raw.get("value").unwrap().to_string(),
-            ),
+            instruction,
             pos: node.pos.clone(),
-            tp: Some(self.builtins.get(&BasicType::U16).unwrap().clone()),
+            tp: Some(res.clone()),
             last_use: None,
         });
 
-        (
-            self.instructions.len() - 1,
-            self.builtins.get(&BasicType::U16).unwrap().clone(),
-        )
-    }
-
-    fn generate_u32(&mut self, node: &Node) -> MirResult<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<u32>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "u32 literal in radix 10 out of bounds ({} to {}).",
-                u32::MAX,
-                u32::MIN
-            );
-            raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                &self.info,
-            );
-        }
-
-        self.instructions.push(MirInstruction {
-            instruction: RawMirInstruction::U32(
-                node.data.get_data().raw.get("value").unwrap().to_string(),
-            ),
-            pos: node.pos.clone(),
-            tp: Some(self.builtins.get(&BasicType::U32).unwrap().clone()),
-            last_use: None,
-        });
-
-        (
-            self.instructions.len() - 1,
-            self.builtins.get(&BasicType::U32).unwrap().clone(),
-        )
-    }
-
-    fn generate_u64(&mut self, node: &Node) -> MirResult<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<u64>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "u64 literal in radix 10 out of bounds ({} to {}).",
-                u64::MAX,
-                u64::MIN
-            );
-            raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                &self.info,
-            );
-        }
-
-        self.instructions.push(MirInstruction {
-            instruction: RawMirInstruction::U64(
-                node.data.get_data().raw.get("value").unwrap().to_string(),
-            ),
-            pos: node.pos.clone(),
-            tp: Some(self.builtins.get(&BasicType::U64).unwrap().clone()),
-            last_use: None,
-        });
-
-        (
-            self.instructions.len() - 1,
-            self.builtins.get(&BasicType::U64).unwrap().clone(),
-        )
-    }
-
-    fn generate_u128(&mut self, node: &Node) -> MirResult<'a> {
-        if node
-            .data
-            .get_data()
-            .raw
-            .get("value")
-            .unwrap()
-            .parse::<u128>()
-            .is_err()
-        {
-            let fmt: String = format!(
-                "u128 literal in radix 10 out of bounds ({} to {}).",
-                u128::MAX,
-                u128::MIN
-            );
-            raise_error(
-                &fmt,
-                ErrorType::InvalidLiteralForRadix,
-                &node.pos,
-                &self.info,
-            );
-        }
-
-        self.instructions.push(MirInstruction {
-            instruction: RawMirInstruction::U128(
-                node.data.get_data().raw.get("value").unwrap().to_string(),
-            ),
-            pos: node.pos.clone(),
-            tp: Some(self.builtins.get(&BasicType::U128).unwrap().clone()),
-            last_use: None,
-        });
-
-        (
-            self.instructions.len() - 1,
-            self.builtins.get(&BasicType::U128).unwrap().clone(),
-        )
-    }
-
-    fn generate_bool(&mut self, node: &Node) -> MirResult<'a> {
-        self.instructions.push(MirInstruction {
-            instruction: RawMirInstruction::Bool(
-                *node.data.get_data().booleans.get("value").unwrap(),
-            ),
-            pos: node.pos.clone(),
-            tp: Some(self.builtins.get(&BasicType::Bool).unwrap().clone()),
-            last_use: None,
-        });
-
-        (
-            self.instructions.len() - 1,
-            self.builtins.get(&BasicType::Bool).unwrap().clone(),
-        )
-    }
-
-    fn generate_binary(&mut self, node: &Node) -> MirResult<'a> {
-        let binary = node.data.get_data();
-        let left = self.generate_expr(binary.nodes.get("left").unwrap());
-        let right = self.generate_expr(binary.nodes.get("right").unwrap());
-
-        let (traittp, name) = match binary.op.unwrap() {
-            OpType::Add => (TraitType::Add, "Add"),
-            OpType::Eq => (TraitType::Eq, "Eq"),
-            OpType::Ne => (TraitType::Ne, "Ne"),
-        };
-
-        let t = left.1.traits.get(&traittp);
-
-        let res = if let Some(Trait::Add {
-            code: _,
-            skeleton,
-            ref_n: _,
-        }) = t
-        {
-            skeleton(self, &node.pos, left.1, right.1)
-        } else if let Some(Trait::Eq {
-            code: _,
-            skeleton,
-            ref_n: _,
-        }) = t
-        {
-            skeleton(self, &node.pos, left.1, right.1)
-        } else if let Some(Trait::Ne {
-            code: _,
-            skeleton,
-            ref_n: _,
-        }) = t
-        {
-            skeleton(self, &node.pos, left.1, right.1)
-        } else {
-            raise_error(
-                &format!("Type '{}' does not implement '{name}'.", left.1.qualname()),
-                ErrorType::TraitNotImplemented,
-                &node.pos,
-                &self.info,
-            );
-        };
-
-        let instruction = match traittp {
-            TraitType::Add => RawMirInstruction::Add {
-                left: left.0,
-                right: right.0,
-            },
-            TraitType::Eq => RawMirInstruction::Eq {
-                left: left.0,
-                right: right.0,
-            },
-            TraitType::Ne => RawMirInstruction::Ne {
-                left: left.0,
-                right: right.0,
-            },
-            _ => {
-                unreachable!();
-            }
-        };
-
-        self.instructions.push(MirInstruction {
-            instruction,
-            pos: node.pos.clone(),
-            tp: Some(res.clone()),
-            last_use: None,
-        });
-
-        (self.instructions.len() - 1, res)
+        (self.instructions.len() - 1, res)
     }
 
     fn generate_let(&mut self, node: &Node) -> MirResult<'a> {
@@ -935,6 +1037,11 @@ impl<'a> Mir<'a> {
             blockid: self.cur_block,
         };
 
+        self.check_shadowing(name, node);
+        if *is_mut {
+            self.mut_bindings.push((blockname.clone(), node.pos.clone(), false));
+        }
+
         self.instructions.push(MirInstruction {
             instruction: RawMirInstruction::Declare {
                 name: blockname.clone(),
@@ -945,18 +1052,59 @@ impl<'a> Mir<'a> {
             last_use: None,
         });
 
-        if letnode.nodes.get("expr").unwrap().tp == NodeType::Conditional {
-            if let None = letnode.nodes.get("expr").unwrap().data.get_data().nodearr_else {
+        let annotation = letnode.nodes.get("tp");
+        let annotated = annotation.map(|tp| {
+            self.types.resolve(&self.session.info, tp)
+        });
+
+        let expr = match letnode.nodes.get("expr") {
+            Some(expr) => *expr,
+            None => {
+                let mut get = self.blocks.get_mut(self.cur_block);
+                let block = get.as_mut().unwrap();
+                block.namespace.insert(
+                    name.clone(),
+                    (
+                        annotated.unwrap(),
+                        BindingTags { is_mut: *is_mut },
+                        annotation.unwrap().pos.clone(),
+                    ),
+                );
+                self.init.insert(blockname, InitState::Uninit);
+
+                return (
+                    self.instructions.len() - 1,
+                    self.types.builtins.get(&BasicType::Void).unwrap().clone(),
+                );
+            }
+        };
+
+        if expr.tp == NodeType::Conditional {
+            if let None = expr.data.get_data().nodearr_else {
                 raise_error(
                     "Conditional expression is missing else clause.",
                     ErrorType::MissingElseClause,
                     &node.pos,
-                    &self.info,
+                    &self.session.info,
                 );
             }
         }
 
-        let right = self.generate_expr(letnode.nodes.get("expr").unwrap());
+        let right = self.generate_expr(expr);
+
+        if let Some(tp) = annotated {
+            if right.1 != tp {
+                raise_error_multi(
+                    vec![
+                        format!("Expected '{}', got '{}'", tp.qualname(), right.1.qualname()),
+                        "Expected because of this:".into(),
+                    ],
+                    ErrorType::TypeMismatch,
+                    vec![Some(&expr.pos), Some(&annotation.unwrap().pos)],
+                    &self.session.info,
+                );
+            }
+        }
 
         self.instructions.push(MirInstruction {
             instruction: RawMirInstruction::Own(right.0),
@@ -970,19 +1118,25 @@ impl<'a> Mir<'a> {
                 right: right.0,
             },
             pos: node.pos.clone(),
-            tp: Some(self.builtins.get(&BasicType::Void).unwrap().clone()),
+            tp: Some(self.types.builtins.get(&BasicType::Void).unwrap().clone()),
             last_use: None,
         });
 
         let mut get = self.blocks.get_mut(self.cur_block);
         let block = get.as_mut().unwrap();
-        block
-            .namespace
-            .insert(name.clone(), (right.1, BindingTags { is_mut: *is_mut }));
+        block.namespace.insert(
+            name.clone(),
+            (
+                right.1,
+                BindingTags { is_mut: *is_mut },
+                annotation.copied().unwrap_or(expr).pos.clone(),
+            ),
+        );
+        self.init.insert(blockname, InitState::Init);
 
         (
             self.instructions.len() - 1,
-            self.builtins.get(&BasicType::Void).unwrap().clone(),
+            self.types.builtins.get(&BasicType::Void).unwrap().clone(),
         )
     }
 
@@ -990,6 +1144,14 @@ impl<'a> Mir<'a> {
         let identifiernode = node.data.get_data();
         let name = identifiernode.raw.get("value").unwrap();
 
+        //Associated constants like `i32::MAX` are evaluated at compile time
+        if let Some((basictype, value)) =
+            self.types
+                .assoc_const(&self.session.info, name, &node.pos)
+        {
+            return self.generate_int_value(node, basictype, value);
+        }
+
         for blockid in self
             .blocks
             .get(self.cur_block)
@@ -1010,6 +1172,25 @@ impl<'a> Mir<'a> {
                 blockid: block.blockid,
             };
 
+            match self.init.get(&blockname) {
+                Some(InitState::Uninit) => raise_error(
+                    &format!("Binding '{}' is used before it is assigned.", name),
+                    ErrorType::UninitializedBinding,
+                    &node.pos,
+                    &self.session.info,
+                ),
+                Some(InitState::MaybeInit) => raise_error(
+                    &format!(
+                        "Binding '{}' is used before it is assigned on every path.",
+                        name
+                    ),
+                    ErrorType::UninitializedBinding,
+                    &node.pos,
+                    &self.session.info,
+                ),
+                _ => {}
+            }
+
             self.instructions.push(MirInstruction {
                 instruction: RawMirInstruction::Load(blockname),
                 pos: node.pos.clone(),
@@ -1033,7 +1214,7 @@ impl<'a> Mir<'a> {
         }
 
         let fmt: String = format!("Binding '{}' not found in scope.", name);
-        raise_error(&fmt, ErrorType::BindingNotFound, &node.pos, &self.info);
+        raise_error(&fmt, ErrorType::BindingNotFound, &node.pos, &self.session.info);
     }
 
     fn generate_store(&mut self, node: &Node) -> MirResult<'a> {
@@ -1047,35 +1228,54 @@ impl<'a> Mir<'a> {
                     "Conditional expression is missing else clause.",
                     ErrorType::MissingElseClause,
                     &node.pos,
-                    &self.info,
+                    &self.session.info,
                 );
             }
         }
         let right = self.generate_expr(expr);
 
-        let block = self.blocks.get(self.cur_block).unwrap();
+        let block = self
+            .blocks
+            .get(self.cur_block)
+            .unwrap()
+            .parents
+            .iter()
+            .rev()
+            .map(|blockid| self.blocks.get(*blockid).unwrap())
+            .find(|block| block.namespace.contains_key(name));
 
-        if block.namespace.get(name).is_none() {
+        if block.is_none() {
             let fmt: String = format!("Binding '{}' not found in scope.", name);
-            raise_error(&fmt, ErrorType::BindingNotFound, &node.pos, &self.info);
+            raise_error(&fmt, ErrorType::BindingNotFound, &node.pos, &self.session.info);
         }
 
+        let block = block.unwrap();
         let binding = block.namespace.get(name).unwrap();
 
+        let blockname = BlockName {
+            name: name.clone(),
+            blockid: block.blockid,
+        };
+
         if right.1 != binding.0 {
-            raise_error(
-                &format!(
-                    "Expected '{}', got '{}'",
-                    binding.0.qualname(),
-                    right.1.qualname()
-                ),
+            raise_error_multi(
+                vec![
+                    format!(
+                        "Expected '{}', got '{}'",
+                        binding.0.qualname(),
+                        right.1.qualname()
+                    ),
+                    "Expected because of this:".into(),
+                ],
                 ErrorType::TypeMismatch,
-                &expr.pos,
-                &self.info,
+                vec![Some(&expr.pos), Some(&binding.2)],
+                &self.session.info,
             );
         }
 
-        if !binding.1.is_mut {
+        //An immutable binding declared without an initializer may be assigned once
+        let reassigned = self.init.get(&blockname) != Some(&InitState::Uninit);
+        if !binding.1.is_mut && reassigned {
             raise_error(
                 &format!(
                     "Binding '{}' is not mutable, so it cannot be assigned to.",
@@ -1083,15 +1283,10 @@ impl<'a> Mir<'a> {
                 ),
                 ErrorType::BindingNotMutable,
                 &node.pos,
-                &self.info,
+                &self.session.info,
             );
         }
 
-        let blockname = BlockName {
-            name: name.clone(),
-            blockid: self.cur_block,
-        };
-
         self.instructions.push(MirInstruction {
             instruction: RawMirInstruction::Own(right.0),
             pos: node.pos.clone(),
@@ -1100,18 +1295,28 @@ impl<'a> Mir<'a> {
         });
         self.instructions.push(MirInstruction {
             instruction: RawMirInstruction::Store {
-                name: blockname,
+                name: blockname.clone(),
                 right: right.0,
             },
             pos: node.pos.clone(),
-            tp: Some(self.builtins.get(&BasicType::Void).unwrap().clone()),
+            tp: Some(self.types.builtins.get(&BasicType::Void).unwrap().clone()),
             last_use: None,
         });
+        let tp = binding.0.clone();
+        if reassigned {
+            //A redeclaration in the same block replaces the binding, so the latest `let` is the one stored to
+            if let Some(binding) = self
+                .mut_bindings
+                .iter_mut()
+                .rev()
+                .find(|(name, _, _)| *name == blockname)
+            {
+                binding.2 = true;
+            }
+        }
+        self.init.insert(blockname, InitState::Init);
 
-        (
-            self.instructions.len() - 1,
-            block.namespace.get(name).unwrap().0.clone(),
-        )
+        (self.instructions.len() - 1, tp)
     }
 
     fn generate_reference(&mut self, node: &Node) -> MirResult<'a> {
@@ -1134,16 +1339,6 @@ impl<'a> Mir<'a> {
         let returnnode = node.data.get_data();
         let expr = self.generate_expr(returnnode.nodes.get("expr").unwrap());
 
-        //TODO: Actual lifetime check
-        if expr.1.ref_n != 0 {
-            raise_error(
-                "Cannot return reference.",
-                ErrorType::ReturnReference,
-                &node.pos,
-                &self.info,
-            );
-        }
-
         self.instructions.push(MirInstruction {
             instruction: RawMirInstruction::Own(expr.0),
             pos: node.pos.clone(),
@@ -1164,37 +1359,209 @@ impl<'a> Mir<'a> {
         let callnode = node.data.get_data();
         let name = callnode.raw.get("name").unwrap().clone();
 
-        let func = self.functions.get(&name);
+        //Functions are resolved before `Type::method` paths. Types have no inherent methods yet, so
+        //a path always names a method of one of the type's traits
+        if !self.functions.contains_key(&name) && !self.std_fns.contains_key(&name) {
+            if let Some((tpname, method)) = name.rsplit_once("::") {
+                if let (Some(tp), Some((traittp, traitname))) =
+                    (self.types.get(tpname), method_trait(method))
+                {
+                    return self.generate_method_call(node, tp, traittp, traitname);
+                }
+            }
+        }
 
-        match func {
-            Some(func) => {
-                self.instructions.push(MirInstruction {
-                    instruction: RawMirInstruction::CallFunction(name),
-                    pos: node.pos.clone(),
-                    tp: Some(func.1 .1.clone()),
-                    last_use: None,
-                });
+        if let Some(stdfn) = self.std_fns.get(&name).cloned() {
+            let argnodes = callnode.nodearr.unwrap();
+            let args = argnodes
+                .iter()
+                .map(|arg| self.generate_call_operand(arg).1)
+                .collect::<Vec<_>>();
+            if name == "std::printf" && !argnodes.is_empty() {
+                check_format(self, &argnodes[0], &argnodes[1..], &args[1..]);
             }
+            let tp = (stdfn.skeleton)(self, &node.pos, args);
+
+            self.instructions.push(MirInstruction {
+                instruction: RawMirInstruction::CallFunction(name),
+                pos: node.pos.clone(),
+                tp: Some(tp.clone()),
+                last_use: None,
+            });
+
+            return (self.instructions.len() - 1, tp);
+        }
+
+        let func = match self.functions.get(&name) {
+            Some(func) => func.clone(),
             None => {
-                let fmt: String = format!("Function '{}' not found.", name);
-                raise_error(&fmt, ErrorType::FunctionNotFound, &node.pos, &self.info);
+                //Suggest the functions whose names are a few typos away, closest first
+                let mut candidates = self
+                    .functions
+                    .iter()
+                    .map(|(other, func)| (edit_distance(&name, other), other, func))
+                    .filter(|(distance, other, _)| *distance <= (other.chars().count() / 3).max(1))
+                    .collect::<Vec<_>>();
+                candidates.sort_by_key(|(distance, _, _)| *distance);
+
+                let mut err = vec![format!("Function '{}' not found.", name)];
+                let mut pos = vec![Some(&node.pos)];
+                for (_, other, func) in candidates.iter().take(3) {
+                    err.push(format!(
+                        "Did you mean '{}'? It is defined here:",
+                        signature(other, &func.0, &func.1 .0, &func.1 .1)
+                    ));
+                    pos.push(Some(&func.0.pos));
+                }
+                raise_error_multi(err, ErrorType::FunctionNotFound, pos, &self.session.info);
             }
+        };
+        let signature = signature(&name, &func.0, &func.1 .0, &func.1 .1);
+
+        let args = callnode.nodearr.unwrap();
+        let params = func.1 .0.clone();
+
+        if args.len() != params.len() {
+            raise_error_multi(
+                vec![
+                    format!(
+                        "Expected {} arguments, got {}.",
+                        params.len(),
+                        args.len()
+                    ),
+                    format!("Function '{signature}' defined here:"),
+                ],
+                ErrorType::WrongArgumentCount,
+                vec![Some(&node.pos), Some(&func.0.pos)],
+                &self.session.info,
+            );
+        }
+
+        let fndata = func.0.data.get_data();
+        for ((arg, param), (_, paramtp)) in args
+            .iter()
+            .zip(&params)
+            .zip(fndata.mapping.unwrap().iter())
+        {
+            let res = self.generate_call_operand(arg);
+            if &res.1 != param {
+                raise_error_multi(
+                    vec![
+                        format!("Expected '{}', got '{}'", param.qualname(), res.1.qualname()),
+                        format!("Parameter type of '{signature}' specified here:"),
+                    ],
+                    ErrorType::TypeMismatch,
+                    vec![Some(&arg.pos), Some(&paramtp.pos)],
+                    &self.session.info,
+                );
+            }
+        }
+
+        self.instructions.push(MirInstruction {
+            instruction: RawMirInstruction::CallFunction(name),
+            pos: node.pos.clone(),
+            tp: Some(func.1 .1.clone()),
+            last_use: None,
+        });
+
+        (self.instructions.len() - 1, func.1 .1.clone())
+    }
+
+    //`T::default()` constructs the value given by `T`'s `Default` implementation
+    /// `Type::method(args)`, which calls the type's implementation of a trait method.
+    fn generate_method_call(
+        &mut self,
+        node: &Node,
+        tp: Type<'a>,
+        traittp: TraitType,
+        traitname: &str,
+    ) -> MirResult<'a> {
+        if traittp == TraitType::Default {
+            return self.generate_default(node, tp);
+        }
+
+        let args = node.data.get_data().nodearr.unwrap();
+        if args.len() != 2 {
+            let fmt: String = format!("Expected 2 arguments, got {}.", args.len());
+            raise_error(&fmt, ErrorType::WrongArgumentCount, &node.pos, &self.session.info);
+        }
+
+        let left = self.generate_expr(&args[0]);
+        if left.1 != tp {
+            raise_error(
+                &format!("Expected '{}', got '{}'", tp.qualname(), left.1.qualname()),
+                ErrorType::TypeMismatch,
+                &args[0].pos,
+                &self.session.info,
+            );
+        }
+        let right = self.generate_expr(&args[1]);
+
+        self.generate_operator(node, traittp, traitname, left, right)
+    }
+
+    fn generate_default(&mut self, node: &Node, tp: Type<'a>) -> MirResult<'a> {
+        let args = node.data.get_data().nodearr.unwrap();
+        if !args.is_empty() {
+            let fmt: String = format!("Expected 0 arguments, got {}.", args.len());
+            raise_error(&fmt, ErrorType::WrongArgumentCount, &node.pos, &self.session.info);
         }
 
-        (self.instructions.len() - 1, func.unwrap().1 .1.clone())
+        if !implements_trait(&tp, TraitType::Default) {
+            raise_error(
+                &format!("Type '{}' does not implement 'Default'.", tp.qualname()),
+                ErrorType::TraitNotImplemented,
+                &node.pos,
+                &self.session.info,
+            );
+        }
+
+        self.instructions.push(MirInstruction {
+            instruction: RawMirInstruction::CallFunction(
+                node.data.get_data().raw.get("name").unwrap().clone(),
+            ),
+            pos: node.pos.clone(),
+            tp: Some(tp.clone()),
+            last_use: None,
+        });
+
+        (self.instructions.len() - 1, tp)
+    }
+
+    //Arguments are moved into the callee (or copied, if Copy), and references are used until the call
+    fn generate_call_operand(&mut self, node: &Node) -> MirResult<'a> {
+        let res = self.generate_expr(node);
+
+        self.instructions.push(MirInstruction {
+            instruction: RawMirInstruction::Own(res.0),
+            pos: node.pos.clone(),
+            tp: None,
+            last_use: None,
+        });
+
+        res
     }
 
     fn generate_deref(&mut self, node: &Node) -> MirResult<'a> {
         let derefnode = node.data.get_data();
         let mut expr = self.generate_expr(derefnode.nodes.get("expr").unwrap());
 
-        if expr.1.ref_n == 0 {
+        if let Some((pointee, _)) = expr.1.pointee() {
+            if !self.in_unsafe() {
+                let fmt: String = format!(
+                    "Dereference of raw pointer '{}' requires an unsafe block.",
+                    expr.1.qualname()
+                );
+                raise_error(&fmt, ErrorType::UnsafeRequired, &node.pos, &self.session.info);
+            }
+            expr.1 = pointee.clone();
+        } else if expr.1.ref_n == 0 {
             let fmt: String = format!("Cannot deref non-reference type '{}'.", expr.1.qualname());
-            raise_error(&fmt, ErrorType::DerefNonref, &node.pos, &self.info);
+            raise_error(&fmt, ErrorType::DerefNonref, &node.pos, &self.session.info);
+        } else {
+            expr.1.ref_n -= 1;
         }
 
-        expr.1.ref_n -= 1;
-
         self.instructions.push(MirInstruction {
             instruction: RawMirInstruction::Deref(expr.0),
             pos: node.pos.clone(),
@@ -1205,36 +1572,343 @@ impl<'a> Mir<'a> {
         (self.instructions.len() - 1, expr.1.clone())
     }
 
+    fn in_unsafe(&self) -> bool {
+        self.blocks
+            .get(self.cur_block)
+            .unwrap()
+            .parents
+            .iter()
+            .any(|blockid| self.blocks.get(*blockid).unwrap().is_unsafe)
+    }
+
+    //A reference may become a raw pointer to the same type, raw pointers may change mutability, and
+    //integers may become any other integer type
+    fn generate_cast(&mut self, node: &Node) -> MirResult<'a> {
+        let castnode = node.data.get_data();
+        let expr = self.generate_expr(castnode.nodes.get("expr").unwrap());
+        let tp = self
+            .types
+            .resolve(&self.session.info, castnode.nodes.get("tp").unwrap());
+
+        let valid = match (expr.1.pointee(), tp.pointee()) {
+            _ if expr.1.signedness().is_some() && tp.signedness().is_some() => true,
+            (Some((from, _)), Some((to, _))) => from == to,
+            (None, Some((to, _))) if expr.1.ref_n > 0 => {
+                let mut from = expr.1.clone();
+                from.ref_n -= 1;
+                &from == to
+            }
+            _ => false,
+        };
+
+        if !valid {
+            let fmt: String = format!(
+                "Cannot cast '{}' to '{}'.",
+                expr.1.qualname(),
+                tp.qualname()
+            );
+            raise_error(&fmt, ErrorType::InvalidCast, &node.pos, &self.session.info);
+        }
+
+        self.instructions.push(MirInstruction {
+            instruction: RawMirInstruction::Cast(expr.0),
+            pos: node.pos.clone(),
+            tp: Some(tp.clone()),
+            last_use: None,
+        });
+
+        (self.instructions.len() - 1, tp)
+    }
+
+    //The body is generated in place, in a block whose flag allows dereferencing raw pointers
+    fn generate_unsafe(&mut self, node: &Node) -> MirResult<'a> {
+        let unsafenode = node.data.get_data();
+
+        let mut parents = self.blocks.get(self.cur_block).unwrap().parents.clone();
+        parents.push(self.blocks.len());
+        let cur_block = Block {
+            namespace_check: IndexMap::new(),
+            parents,
+            blockid: self.blocks.len(),
+            namespace: HashMap::new(),
+            instructions: None,
+            is_unsafe: true,
+            is_loop: false,
+        };
+
+        self.blocks.push(cur_block.clone());
+
+        let old_block = self.cur_block;
+        self.cur_block = cur_block.blockid;
+
+        let blockstart = self.instructions.len();
+        let mut res = None;
+        for node in unsafenode.nodearr.unwrap() {
+            res = Some(self.generate_expr(node));
+        }
+        let blockend = self.instructions.len();
+
+        self.blocks.get_mut(cur_block.blockid).unwrap().instructions =
+            Some(self.instructions.clone());
+        self.block_positions
+            .insert(cur_block.blockid, (blockstart, blockend));
+
+        self.cur_block = old_block;
+
+        match res {
+            Some(res) => res,
+            None => {
+                self.instructions.push(MirInstruction {
+                    instruction: RawMirInstruction::NoOp,
+                    pos: node.pos.clone(),
+                    tp: Some(self.types.builtins.get(&BasicType::Void).unwrap().clone()),
+                    last_use: None,
+                });
+
+                (
+                    self.instructions.len() - 1,
+                    self.types.builtins.get(&BasicType::Void).unwrap().clone(),
+                )
+            }
+        }
+    }
+
+    /// A `while` or, without a condition, a `loop`. The condition is the start of the loop's block,
+    /// so it is evaluated again on each iteration.
+    fn generate_loop(&mut self, node: &Node, exprnode: Option<&Node>) -> MirResult<'a> {
+        let loopnode = node.data.get_data();
+
+        let mut parents = self.blocks.get(self.cur_block).unwrap().parents.clone();
+        parents.push(self.blocks.len());
+        let cur_block = Block {
+            namespace_check: IndexMap::new(),
+            parents,
+            blockid: self.blocks.len(),
+            namespace: HashMap::new(),
+            instructions: None,
+            is_unsafe: false,
+            is_loop: true,
+        };
+
+        self.blocks.push(cur_block.clone());
+
+        let old_block = self.cur_block;
+        self.cur_block = cur_block.blockid;
+
+        let init = self.init.clone();
+        self.loops.push(LoopJumps {
+            id: cur_block.blockid,
+            breaks: Vec::new(),
+            continues: Vec::new(),
+        });
+
+        let blockstart = self.instructions.len();
+        let right = exprnode.map(|exprnode| {
+            let expr = self.generate_expr(exprnode);
+            self.check_condition(&expr.1, exprnode);
+            expr.0
+        });
+        let instructions = self.generate(loopnode.nodearr.unwrap());
+        let blockend = self.instructions.len();
+
+        //Control goes back to the start from each continue, and from the end of the body
+        let jumps = self.loops.pop().unwrap();
+        let mut repeats = jumps.continues;
+        if check::falls_through(
+            &self.instructions,
+            &self.block_positions,
+            blockstart,
+            blockend,
+            &node.pos,
+        )
+        .is_some()
+        {
+            repeats.push(self.init.clone());
+        }
+
+        //An immutable binding initialized in the body would be assigned again by the next iteration
+        for (name, state) in repeats.iter().flatten() {
+            if init.get(name) != Some(&InitState::Uninit) || *state == InitState::Uninit {
+                continue;
+            }
+            let block = self.blocks.get(name.blockid).unwrap();
+            if block.namespace.get(&name.name).unwrap().1.is_mut {
+                continue;
+            }
+            let store = self.instructions[blockstart..blockend]
+                .iter()
+                .find(|instruction| {
+                    matches!(
+                        check::raw_instruction(instruction),
+                        RawMirInstruction::Store { name: stored, right: _ } if stored == name
+                    )
+                })
+                .unwrap();
+            raise_error(
+                &format!(
+                    "Binding '{}' is not mutable, so it cannot be assigned to in a loop.",
+                    name.name
+                ),
+                ErrorType::BindingNotMutable,
+                &store.pos,
+                &self.session.info,
+            );
+        }
+
+        //A while exits when its condition is false, which it may be before any iteration
+        let mut exits = jumps.breaks;
+        if right.is_some() {
+            exits.push(init.clone());
+            exits.extend(repeats);
+        }
+        self.init = merge_init(&init, &exits);
+
+        self.block_positions
+            .insert(cur_block.blockid, (blockstart, blockend));
+
+        self.cur_block = old_block;
+
+        let void = self.types.builtins.get(&BasicType::Void).unwrap().clone();
+        self.instructions.push(MirInstruction {
+            instruction: RawMirInstruction::Loop {
+                code: Rc::new(instructions),
+                right,
+                offset: blockstart,
+                id: cur_block.blockid,
+            },
+            pos: node.pos.clone(),
+            tp: Some(void.clone()),
+            last_use: None,
+        });
+
+        self.wrap_block(blockstart, blockend);
+
+        (self.instructions.len() - 1, void)
+    }
+
+    fn generate_jump(&mut self, node: &Node) -> MirResult<'a> {
+        let is_break = node.tp == NodeType::Break;
+        let init = self.init.clone();
+        let Some(jumps) = self.loops.last_mut() else {
+            raise_error(
+                &format!(
+                    "'{}' is only allowed in a loop.",
+                    if is_break { "break" } else { "continue" }
+                ),
+                ErrorType::JumpOutsideLoop,
+                &node.pos,
+                &self.session.info,
+            );
+        };
+
+        let id = jumps.id;
+        let instruction = if is_break {
+            jumps.breaks.push(init);
+            RawMirInstruction::Break(id)
+        } else {
+            jumps.continues.push(init);
+            RawMirInstruction::Continue(id)
+        };
+        self.instructions.push(MirInstruction {
+            instruction,
+            pos: node.pos.clone(),
+            tp: None,
+            last_use: None,
+        });
+
+        (
+            self.instructions.len() - 1,
+            self.types.builtins.get(&BasicType::Void).unwrap().clone(),
+        )
+    }
+
+    fn generate_macro(&mut self, node: &Node) -> MirResult<'a> {
+        let macronode = node.data.get_data();
+        let name = macronode.raw.get("name").unwrap();
+        let args = macronode.nodearr.unwrap();
+
+        match name.as_str() {
+            "asm" => self.generate_asm(node, args),
+            _ => {
+                let fmt: String = format!("Macro '{}' not found.", name);
+                raise_error(&fmt, ErrorType::MacroNotFound, &node.pos, &self.session.info);
+            }
+        }
+    }
+
+    //The assembly is spliced in verbatim, so it can do anything and needs an unsafe block
+    fn generate_asm(&mut self, node: &Node, args: &[Node]) -> MirResult<'a> {
+        if args.len() != 1 {
+            let fmt: String = format!("Expected 1 argument, got {}.", args.len());
+            raise_error(&fmt, ErrorType::WrongArgumentCount, &node.pos, &self.session.info);
+        }
+        let arg = args.first().unwrap();
+        if arg.tp != NodeType::Str {
+            raise_error(
+                "Expected a string literal.",
+                ErrorType::TypeMismatch,
+                &arg.pos,
+                &self.session.info,
+            );
+        }
+
+        if !self.in_unsafe() {
+            raise_error(
+                "Inline assembly requires an unsafe block.",
+                ErrorType::UnsafeRequired,
+                &node.pos,
+                &self.session.info,
+            );
+        }
+
+        self.instructions.push(MirInstruction {
+            instruction: RawMirInstruction::Asm(arg.data.get_data().raw.get("value").unwrap().clone()),
+            pos: node.pos.clone(),
+            tp: Some(self.types.builtins.get(&BasicType::Void).unwrap().clone()),
+            last_use: None,
+        });
+
+        (
+            self.instructions.len() - 1,
+            self.types.builtins.get(&BasicType::Void).unwrap().clone(),
+        )
+    }
+
     fn generate_if(&mut self, node: &Node) -> MirResult<'a> {
         let ifnode = node.data.get_data();
         let codes = ifnode.nodearr_codes.unwrap().clone();
         let exprs = ifnode.nodearr.unwrap();
         let positions = ifnode.positions;
+        let is_logical = *ifnode.booleans.get("is_logical").unwrap();
 
         let mut finaltp: Option<(Type<'_>, Position)> = None;
         let mut check_n = 0;
 
-        for (position, (code, expr)) in std::iter::zip(positions, std::iter::zip(codes, exprs)) {
-            let expr = self.generate_expr(expr);
+        let init = self.init.clone();
+        let mut paths = Vec::new();
 
-            if expr.1.basictype != BasicType::Bool {
-                raise_error(
-                    &format!("Expected 'std::bool', got '{}'", expr.1.qualname()),
-                    ErrorType::TypeMismatch,
-                    &node.pos,
-                    &self.info,
-                );
+        let mut taken = false;
+        for (i, (position, (code, exprnode))) in
+            std::iter::zip(positions.clone(), std::iter::zip(codes, exprs)).enumerate()
+        {
+            let expr = self.generate_expr(exprnode);
+            self.check_condition(&expr.1, exprnode);
+            //Branches after one that is always taken are already reported as dead, and the
+            //operands of && and || are not branches the user wrote
+            if !taken && !is_logical {
+                taken = self.check_constant_condition(expr.0, exprnode, &positions[i..]);
             }
-            let block = self.blocks.get(self.cur_block).unwrap().clone();
-
-            let mut parents = block.parents.clone();
+            let cond_init = self.init.clone();
+            let mut parents = self.blocks.get(self.cur_block).unwrap().parents.clone();
             parents.push(self.blocks.len());
             let cur_block = Block {
-                namespace_check: HashMap::new(),
+                namespace_check: IndexMap::new(),
                 parents,
                 blockid: self.blocks.len(),
                 namespace: HashMap::new(),
                 instructions: None,
+                is_unsafe: false,
+                is_loop: false,
             };
 
             self.blocks.push(cur_block.clone());
@@ -1246,6 +1920,20 @@ impl<'a> Mir<'a> {
             let instructions = self.generate(&code);
             let blockend = self.instructions.len();
 
+            //A branch that returns does not produce the value of the if
+            let falls = check::falls_through(
+                &self.instructions,
+                &self.block_positions,
+                blockstart,
+                blockend,
+                &node.pos,
+            )
+            .is_some();
+            if falls {
+                paths.push(self.init.clone());
+            }
+            self.init = cond_init;
+
             self.block_positions
                 .insert(cur_block.blockid, (blockstart, blockend));
 
@@ -1255,11 +1943,11 @@ impl<'a> Mir<'a> {
                 .iter()
                 .map(|x| {
                     x.tp.as_ref()
-                        .unwrap_or(self.builtins.get(&BasicType::Void).unwrap())
+                        .unwrap_or(self.types.builtins.get(&BasicType::Void).unwrap())
                         .clone()
                 })
                 .last()
-                .unwrap_or(self.builtins.get(&BasicType::Void).unwrap().clone());
+                .unwrap_or(self.types.builtins.get(&BasicType::Void).unwrap().clone());
 
             let pos_cur = instructions
                 .iter()
@@ -1268,6 +1956,7 @@ impl<'a> Mir<'a> {
                 .unwrap_or(node.pos.clone());
 
             match finaltp {
+                _ if !falls => {}
                 Some(ref tp) => {
                     if tp.0 != tp_cur {
                         raise_error_multi(
@@ -1277,11 +1966,11 @@ impl<'a> Mir<'a> {
                                     tp.0.qualname(),
                                     tp_cur.qualname()
                                 ),
-                                format!("Original type:"),
+                                "Expected because of this:".into(),
                             ],
                             ErrorType::TypeMismatch,
                             vec![Some(&pos_cur), Some(&tp.1)],
-                            &self.info,
+                            &self.session.info,
                         );
                     }
                 }
@@ -1292,7 +1981,7 @@ impl<'a> Mir<'a> {
 
             self.instructions.push(MirInstruction {
                 instruction: RawMirInstruction::IfCondition {
-                    code: instructions.clone(),
+                    code: Rc::new(instructions.clone()),
                     check_n,
                     right: Some(expr.0),
                     offset: blockstart,
@@ -1303,33 +1992,25 @@ impl<'a> Mir<'a> {
                 last_use: None,
             });
 
-            for instruction in &mut self.instructions[blockstart..blockend] {
-                *instruction = MirInstruction {
-                    instruction: RawMirInstruction::InstructionWrapper(Box::new(
-                        instruction.clone(),
-                    )),
-                    pos: instruction.pos.clone(),
-                    tp: instruction.tp.clone(),
-                    last_use: instruction.last_use.clone(),
-                }
-            }
+            self.wrap_block(blockstart, blockend);
 
             check_n += 1;
         }
 
         if ifnode.nodearr_else.is_some() {
             let code = ifnode.nodearr_else.as_ref().unwrap().clone();
+            let cond_init = self.init.clone();
 
-            let block = self.blocks.get(self.cur_block).unwrap().clone();
-
-            let mut parents = block.parents.clone();
+            let mut parents = self.blocks.get(self.cur_block).unwrap().parents.clone();
             parents.push(self.blocks.len());
             let cur_block = Block {
-                namespace_check: HashMap::new(),
+                namespace_check: IndexMap::new(),
                 parents,
                 blockid: self.blocks.len(),
                 namespace: HashMap::new(),
                 instructions: None,
+                is_unsafe: false,
+                is_loop: false,
             };
 
             self.blocks.push(cur_block.clone());
@@ -1341,6 +2022,20 @@ impl<'a> Mir<'a> {
             let instructions = self.generate(&code);
             let blockend = self.instructions.len();
 
+            //A branch that returns does not produce the value of the if
+            let falls = check::falls_through(
+                &self.instructions,
+                &self.block_positions,
+                blockstart,
+                blockend,
+                &node.pos,
+            )
+            .is_some();
+            if falls {
+                paths.push(self.init.clone());
+            }
+            self.init = cond_init;
+
             self.block_positions
                 .insert(cur_block.blockid, (blockstart, blockend));
 
@@ -1350,11 +2045,11 @@ impl<'a> Mir<'a> {
                 .iter()
                 .map(|x| {
                     x.tp.as_ref()
-                        .unwrap_or(self.builtins.get(&BasicType::Void).unwrap())
+                        .unwrap_or(self.types.builtins.get(&BasicType::Void).unwrap())
                         .clone()
                 })
                 .last()
-                .unwrap_or(self.builtins.get(&BasicType::Void).unwrap().clone());
+                .unwrap_or(self.types.builtins.get(&BasicType::Void).unwrap().clone());
 
             let pos_cur = instructions
                 .iter()
@@ -1363,6 +2058,7 @@ impl<'a> Mir<'a> {
                 .unwrap_or(node.pos.clone());
 
             match finaltp {
+                _ if !falls => {}
                 Some(ref tp) => {
                     if tp.0 != tp_cur {
                         raise_error_multi(
@@ -1372,11 +2068,11 @@ impl<'a> Mir<'a> {
                                     tp.0.qualname(),
                                     tp_cur.qualname()
                                 ),
-                                format!("Original type:"),
+                                "Expected because of this:".into(),
                             ],
                             ErrorType::TypeMismatch,
                             vec![Some(&pos_cur), Some(&tp.1)],
-                            &self.info,
+                            &self.session.info,
                         );
                     }
                 }
@@ -1387,7 +2083,7 @@ impl<'a> Mir<'a> {
 
             self.instructions.push(MirInstruction {
                 instruction: RawMirInstruction::IfCondition {
-                    code: instructions.clone(),
+                    code: Rc::new(instructions.clone()),
                     check_n,
                     right: None,
                     offset: blockstart,
@@ -1397,22 +2093,145 @@ impl<'a> Mir<'a> {
                 tp: Some(tp_cur),
                 last_use: None,
             });
+
+            self.wrap_block(blockstart, blockend);
+        } else {
+            paths.push(self.init.clone());
+        }
+
+        self.init = merge_init(&init, &paths);
+
+        let tp = finaltp
+            .map(|tp| tp.0)
+            .unwrap_or(self.types.builtins.get(&BasicType::Void).unwrap().clone());
+        self.instructions.last_mut().unwrap().tp = Some(tp.clone());
+
+        (self.instructions.len() - 1, tp)
+    }
+
+    /// Hides the instructions of a nested block from its parent, which reaches them through the
+    /// block's `IfCondition` instead. Instructions of deeper blocks are already hidden.
+    fn wrap_block(&mut self, blockstart: usize, blockend: usize) {
+        for instruction in &mut self.instructions[blockstart..blockend] {
+            if let RawMirInstruction::InstructionWrapper(_) = instruction.instruction {
+                continue;
+            }
+            let pos = instruction.pos.clone();
+            let tp = instruction.tp.clone();
+            let last_use = instruction.last_use.clone();
+            let inner = std::mem::replace(
+                instruction,
+                MirInstruction {
+                    instruction: RawMirInstruction::NoOp,
+                    pos: pos.clone(),
+                    tp: None,
+                    last_use: None,
+                },
+            );
+            *instruction = MirInstruction {
+                instruction: RawMirInstruction::InstructionWrapper(Box::new(inner)),
+                pos,
+                tp,
+                last_use,
+            }
         }
+    }
+
+    /// Conditions must be bools, other types are never implicitly truthy.
+    fn check_condition(&self, tp: &Type<'a>, node: &Node) {
+        if tp.basictype == BasicType::Bool && tp.ref_n == 0 {
+            return;
+        }
+
+        let hint = if tp.signedness().is_some() {
+            " Integers are not truthy, compare with '!= 0'."
+        } else if tp.basictype == BasicType::Bool {
+            " Dereference it with '*'."
+        } else {
+            ""
+        };
+        raise_error(
+            &format!(
+                "Condition must be 'std::bool', got '{}'.{hint}",
+                tp.qualname()
+            ),
+            ErrorType::NonBoolCondition,
+            &node.pos,
+            &self.session.info,
+        );
+    }
 
-        (self.instructions.len() - 1, finaltp.unwrap().0)
+    /// Warn when a `let` reuses the name of a binding that is still in scope.
+    fn check_shadowing(&self, name: &String, node: &Node) {
+        let shadowed = self
+            .blocks
+            .get(self.cur_block)
+            .unwrap()
+            .parents
+            .iter()
+            .rev()
+            .find(|blockid| self.blocks.get(**blockid).unwrap().namespace.contains_key(name));
+
+        let blockname = match shadowed {
+            Some(blockid) => BlockName {
+                name: name.clone(),
+                blockid: *blockid,
+            },
+            None => return,
+        };
+
+        let declared = self.instructions.iter().rev().find(|instruction| {
+            matches!(
+                &instruction.instruction,
+                RawMirInstruction::Declare { name, is_mut: _ } if *name == blockname
+            )
+        });
+
+        let mut warnings = vec![format!("Binding '{name}' shadows an earlier binding.")];
+        let mut positions = vec![Some(&node.pos)];
+        if let Some(declared) = declared {
+            warnings.push("It was declared here:".to_string());
+            positions.push(Some(&declared.pos));
+        }
+        raise_warning_multi(warnings, WarningType::Shadowing, positions, self.session);
+    }
+
+    /// Warn about a condition that folds to a constant, pointing at the branches it makes dead.
+    /// `branches` starts with the position of this branch. Returns whether it is always taken.
+    fn check_constant_condition(&self, right: usize, node: &Node, branches: &[Position]) -> bool {
+        let value = match fold::const_bool(&self.instructions, right) {
+            Some(value) => value,
+            None => return false,
+        };
+
+        let dead = if value { &branches[1..] } else { &branches[..1] };
+        let mut warnings = vec![format!("Condition is always {value}.")];
+        let mut positions = vec![Some(&node.pos)];
+        for branch in dead {
+            warnings.push("This branch is never taken:".to_string());
+            positions.push(Some(branch));
+        }
+        raise_warning_multi(
+            warnings,
+            WarningType::ConstantCondition,
+            positions,
+            self.session,
+        );
+
+        value
     }
 
-    fn generate_enum(&mut self, node: &Node) -> MirResult<'a> {        
+    fn generate_type_def(&mut self, node: &Node) -> MirResult<'a> {        
         self.instructions.push(MirInstruction {
             instruction: RawMirInstruction::NoOp,
             pos: node.pos.clone(),
-            tp: Some(self.builtins.get(&BasicType::Void).unwrap().clone()),
+            tp: Some(self.types.builtins.get(&BasicType::Void).unwrap().clone()),
             last_use: None,
         });
 
         (
             self.instructions.len() - 1,
-            self.builtins.get(&BasicType::Void).unwrap().clone(),
+            self.types.builtins.get(&BasicType::Void).unwrap().clone(),
         )
     }
 }
@@ -1,24 +1,37 @@
-use std::collections::HashMap;
+use indexmap::IndexMap;
 
 use crate::{
-    errors::{raise_error, ErrorType},
-    lexer::{Token, TokenType},
-    utils::{FileInfo, Position}, parser::nodes::EnumNode,
+    errors::{raise_error, raise_error_multi, ErrorType},
+    lexer::{is_keyword, Token, TokenType},
+    types::is_builtin_type_name,
+    session::Session,
+    utils::Position, parser::nodes::EnumNode,
 };
 
 pub mod nodes;
+mod pretty;
+use self::pretty::pretty;
 use self::nodes::{
-    BinaryNode, BoolNode, CallNode, ConditionalNode, DecimalNode, DerefNode, FnNode,
-    IdentifierNode, LetNode, Node, NodeType, OpType, ReferenceNode, ReturnNode, StoreNode,
+    BinaryNode, BoolNode, CallNode, CastNode, ConditionalNode, DecimalNode, DerefNode, FnNode,
+    IdentifierNode, LetNode, MacroNode, Node, NodeType, OpType, RawPtrNode, ReferenceNode, ReturnNode,
+    SliceNode, StoreNode, StrNode, TypeAliasNode, UnitNode, UnsafeNode, WhileNode, LoopNode, JumpNode,
 };
 
 pub struct Parser<'a> {
     current: Token,
-    info: FileInfo<'a>,
+    session: &'a Session<'a>,
     tokens: Vec<Token>,
     idx: usize,
+    depth: usize,
+    /// The text before and after each operand being parsed, outermost first, which reconstructs the
+    /// expression around a parse error.
+    partial: Vec<(String, String)>,
 }
 
+/// How deeply expressions and blocks may nest. The parser, MIR generator, checker and codegen all
+/// recurse over the tree, so deeper input would overflow the compiler's stack.
+const MAX_NESTING: usize = 256;
+
 #[allow(dead_code)]
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum Precedence {
@@ -51,12 +64,14 @@ pub enum Precedence {
 //Expressions + Keywords: leave off on next
 
 impl<'a> Parser<'a> {
-    pub fn new(tokens: Vec<Token>, info: &FileInfo<'a>) -> Self {
+    pub fn new(tokens: Vec<Token>, session: &'a Session<'a>) -> Self {
         Self {
             current: tokens.first().unwrap().clone(),
-            info: info.clone(),
+            session,
             tokens,
             idx: 1,
+            depth: 0,
+            partial: Vec::new(),
         }
     }
 
@@ -65,17 +80,49 @@ impl<'a> Parser<'a> {
     }
 
     fn block(&mut self) -> Vec<Node> {
+        self.depth += 1;
+        self.check_nesting(0);
+
         self.skip_newlines();
         let mut nodes = Vec::new();
 
         while !self.current_is_type(TokenType::Eof) && !self.current_is_type(TokenType::RCurly) {
             nodes.push(self.parse_statement());
+            //A statement may be explicitly terminated with a ';'
+            if self.current_is_type(TokenType::Semicolon) {
+                self.advance();
+            }
             self.skip_newlines();
         }
 
+        self.depth -= 1;
         nodes
     }
 
+    /// `chained` counts the operators folded into the left operand, each of which nests the tree one level deeper.
+    fn check_nesting(&mut self, chained: usize) {
+        if self.depth + chained <= MAX_NESTING {
+            return;
+        }
+        //A flat chain such as `a + a + ... + a` is not nested in the source, only in the tree
+        if chained > 0 {
+            self.raise_error(
+                &format!(
+                    "Expression is too long, with more than {} operators and levels of nesting.",
+                    MAX_NESTING
+                ),
+                ErrorType::NestingTooDeep,
+            );
+        }
+        self.raise_error(
+            &format!(
+                "Expression is nested more than {} levels deep.",
+                MAX_NESTING
+            ),
+            ErrorType::NestingTooDeep,
+        );
+    }
+
     fn skip_newlines(&mut self) {
         while self.current_is_type(TokenType::Newline) {
             self.advance();
@@ -85,6 +132,7 @@ impl<'a> Parser<'a> {
     fn parse_statement(&mut self) -> Node {
         match self.current.tp {
             TokenType::Keyword => self.keyword(),
+            TokenType::HashBracket => self.generate_attributes(),
             _ => self.expr(Precedence::Lowest),
         }
     }
@@ -103,26 +151,26 @@ impl<'a> Parser<'a> {
         res
     }
 
+    //The next token has type `tp` and directly follows the current one
+    fn next_is_adjacent(&self, tp: TokenType) -> bool {
+        match self.tokens.get(self.idx) {
+            Some(next) => {
+                next.tp == tp
+                    && next.start.line == self.current.end.line
+                    && next.start.startcol == self.current.end.endcol
+            }
+            None => false,
+        }
+    }
+
     fn current_is_keyword(&mut self, name: &str) -> bool {
+        debug_assert!(is_keyword(name), "'{name}' is not a keyword");
         if !self.current_is_type(TokenType::Keyword) {
             return false;
         }
         self.current.data == name
     }
 
-    fn if_kwd_expect_keyword(&mut self, name: &str) {
-        if self.current_is_type(TokenType::Keyword) && self.current.data != name {
-            self.raise_error(
-                format!(
-                    "Because there is a keyword here, '{}' was expected, but got '{}'.",
-                    name, self.current.data
-                )
-                .as_str(),
-                ErrorType::InvalidTok,
-            )
-        }
-    }
-
     fn expect(&mut self, tp: TokenType) {
         if !self.current_is_type(tp.clone()) {
             self.raise_error(
@@ -137,19 +185,64 @@ impl<'a> Parser<'a> {
     }
 
     fn raise_error(&mut self, error: &str, errtp: ErrorType) -> ! {
-        crate::errors::raise_error(
-            error,
+        let pos = Position {
+            startcol: self.current.start.startcol,
+            endcol: self.current.end.endcol,
+            opcol: None,
+            line: self.current.start.line,
+        };
+        if self.partial.is_empty() {
+            crate::errors::raise_error(error, errtp, &pos, &self.session.info);
+        }
+
+        let (before, after): (Vec<_>, Vec<_>) = self.partial.iter().cloned().unzip();
+        let parsed = format!(
+            "{}_{}",
+            before.concat(),
+            after.into_iter().rev().collect::<String>()
+        );
+        raise_error_multi(
+            vec![
+                error.into(),
+                format!("The expression was parsed as '{parsed}', where '_' is this token."),
+            ],
             errtp,
-            &Position {
-                startcol: self.current.start.startcol,
-                endcol: self.current.end.endcol,
-                opcol: None,
-                line: self.current.start.line,
-            },
-            &self.info,
+            vec![Some(&pos), None],
+            &self.session.info,
         );
     }
 
+    /// Parses the operand of an operator, between the already parsed `before` and `after` it.
+    fn operand(&mut self, before: String, after: &str, prec: Precedence) -> Node {
+        self.partial.push((before, after.into()));
+        let res = self.expr(prec);
+        self.partial.pop();
+        res
+    }
+
+    /// Expects the name of a new binding, function or type, which may not be a keyword or builtin type.
+    fn expect_name(&mut self, kind: &str) {
+        if self.current_is_type(TokenType::Keyword) {
+            self.raise_error(
+                &format!(
+                    "'{}' is a keyword, so it cannot be used as a {} name.",
+                    self.current.data, kind
+                ),
+                ErrorType::ReservedName,
+            );
+        }
+        self.expect(TokenType::Identifier);
+        if is_builtin_type_name(&self.current.data) {
+            self.raise_error(
+                &format!(
+                    "'{}' is a builtin type, so it cannot be used as a {} name.",
+                    self.current.data, kind
+                ),
+                ErrorType::ReservedName,
+            );
+        }
+    }
+
     fn advance(&mut self) {
         let next = self.tokens.get(self.idx);
         self.idx += 1;
@@ -210,9 +303,17 @@ impl<'a> Parser<'a> {
 
     fn get_precedence(&self) -> Precedence {
         match self.current.tp {
-            TokenType::Plus => Precedence::Sum,
+            TokenType::Plus | TokenType::Hyphen => Precedence::Sum,
+            //A leading asterisk is a dereference, this is only reached after an operand
+            TokenType::Asterisk | TokenType::Slash | TokenType::Percent => Precedence::Product,
             TokenType::Equal => Precedence::Assign,
             TokenType::DoubleEqual | TokenType::NotEqual => Precedence::Comparison,
+            TokenType::Keyword if self.current.data == "as" => Precedence::To,
+            TokenType::DoublePipe => Precedence::LogicalOr,
+            //`&&` is lexed as two ampersands so that `&&x` stays a double reference
+            TokenType::Ampersand if self.next_is_adjacent(TokenType::Ampersand) => {
+                Precedence::LogicalAnd
+            }
 
             _ => Precedence::Lowest,
         }
@@ -233,15 +334,29 @@ impl<'a> Parser<'a> {
                 self.advance();
                 res
             }
-            "fn" => self.generate_fn(),
+            "fn" => self.generate_fn(false, None),
+            "export" => {
+                self.advance();
+                if !self.current_is_keyword("fn") {
+                    self.raise_error("Expected 'fn' after 'export'.", ErrorType::InvalidTok);
+                }
+                self.generate_fn(true, None)
+            }
             "return" => self.generate_return(),
             "if" => self.generate_if(),
             "else" => self.raise_error("'else' is not allowed here", ErrorType::FloatingElse),
             "elif" => self.raise_error("'elif' is not allowed here", ErrorType::FloatingElif),
             "enum" => self.generate_enum(),
-            _ => {
-                unreachable!();
-            }
+            "type" => self.generate_type_alias(),
+            "unsafe" => self.generate_unsafe(),
+            "while" => self.generate_while(),
+            "loop" => self.generate_loop(),
+            "break" => self.generate_jump(nodes::NodeType::Break),
+            "continue" => self.generate_jump(nodes::NodeType::Continue),
+            _ => self.raise_error(
+                &format!("Keyword '{}' is not allowed here.", self.current.data),
+                ErrorType::InvalidTok,
+            ),
         }
     }
 
@@ -250,27 +365,41 @@ impl<'a> Parser<'a> {
         self.advance();
 
         let is_mut = self.current_is_keyword("mut");
-        self.if_kwd_expect_keyword("mut");
         if is_mut {
             self.advance();
         }
 
-        self.expect(TokenType::Identifier);
+        self.expect_name("binding");
 
         let name = self.atom().unwrap();
 
         self.advance();
 
-        self.expect(TokenType::Equal);
+        let mut tp = None;
+        if self.current_is_type(TokenType::Colon) {
+            self.advance();
+            //Stop before the '=', which would otherwise parse the type as the target of an assignment
+            tp = Some(self.expr(Precedence::Assign));
+        }
 
-        self.advance();
+        //Without an initializer, the binding must be assigned before it is read
+        let expr = if tp.is_some() && !self.current_is_type(TokenType::Equal) {
+            None
+        } else {
+            self.expect(TokenType::Equal);
+            self.advance();
+            Some(self.expr(Precedence::Lowest))
+        };
 
-        let expr = self.expr(Precedence::Lowest);
+        let endcol = match (&expr, &tp) {
+            (Some(expr), _) | (None, Some(expr)) => expr.pos.endcol,
+            (None, None) => unreachable!(),
+        };
 
         Node::new(
             Position {
                 startcol,
-                endcol: expr.pos.endcol,
+                endcol,
                 opcol: None,
                 line: name.pos.line,
             },
@@ -278,6 +407,7 @@ impl<'a> Parser<'a> {
             Box::new(LetNode {
                 name: name.data.get_data().raw.get("value").unwrap().clone(),
                 expr,
+                tp,
                 is_mut,
             }),
         )
@@ -287,7 +417,7 @@ impl<'a> Parser<'a> {
         Node::new(
             Position {
                 startcol: self.current.start.startcol,
-                endcol: self.current.start.endcol,
+                endcol: self.current.end.endcol,
                 opcol: None,
                 line: self.current.start.line,
             },
@@ -300,7 +430,7 @@ impl<'a> Parser<'a> {
         Node::new(
             Position {
                 startcol: self.current.start.startcol,
-                endcol: self.current.start.endcol,
+                endcol: self.current.end.endcol,
                 opcol: None,
                 line: self.current.start.line,
             },
@@ -309,17 +439,98 @@ impl<'a> Parser<'a> {
         )
     }
 
-    fn generate_fn(&mut self) -> Node {
+    /// Attributes like `#[visibility = "hidden"]`, followed by the function they apply to.
+    fn generate_attributes(&mut self) -> Node {
+        let mut visibility: Option<(String, Position)> = None;
+
+        while self.current_is_type(TokenType::HashBracket) {
+            self.advance();
+
+            self.expect(TokenType::Identifier);
+            let name = self.current.data.clone();
+            let pos = Position {
+                startcol: self.current.start.startcol,
+                endcol: self.current.end.endcol,
+                opcol: None,
+                line: self.current.start.line,
+            };
+            self.advance();
+
+            self.expect(TokenType::Equal);
+            self.advance();
+
+            self.expect(TokenType::Str);
+            let value = self.current.data.clone();
+
+            match name.as_str() {
+                "visibility" => {
+                    if !["default", "hidden", "protected"].contains(&value.as_str()) {
+                        self.raise_error(
+                            &format!(
+                                "Invalid visibility '{value}', expected 'default', 'hidden' or 'protected'."
+                            ),
+                            ErrorType::InvalidAttribute,
+                        );
+                    }
+                    if visibility.is_some() {
+                        crate::errors::raise_error(
+                            "Visibility is specified multiple times.",
+                            ErrorType::InvalidAttribute,
+                            &pos,
+                            &self.session.info,
+                        );
+                    }
+                    visibility = Some((value, pos));
+                }
+                _ => crate::errors::raise_error(
+                    &format!("Attribute '{name}' not found."),
+                    ErrorType::InvalidAttribute,
+                    &pos,
+                    &self.session.info,
+                ),
+            }
+            self.advance();
+
+            self.expect(TokenType::RBracket);
+            self.advance();
+            self.skip_newlines();
+        }
+
+        let is_export = self.current_is_keyword("export");
+        if is_export {
+            self.advance();
+        }
+        if !self.current_is_keyword("fn") {
+            self.raise_error(
+                "Expected a function definition after its attributes.",
+                ErrorType::InvalidTok,
+            );
+        }
+
+        //Other functions have internal linkage, so they are never visible outside the module
+        if let Some((_, pos)) = visibility.as_ref().filter(|_| !is_export) {
+            crate::errors::raise_error(
+                "Only an 'export fn' can set its visibility.",
+                ErrorType::InvalidAttribute,
+                pos,
+                &self.session.info,
+            );
+        }
+
+        self.generate_fn(is_export, visibility.map(|(value, _)| value))
+    }
+
+    fn generate_fn(&mut self, is_export: bool, visibility: Option<String>) -> Node {
         let startcol = self.current.start.startcol;
 
         self.advance();
 
-        self.expect(TokenType::Identifier);
+        self.expect_name("function");
         let name = self.current.data.clone();
 
         self.advance();
 
-        let mut args = Vec::new();
+        let mut args: Vec<(Node, Node)> = Vec::new();
 
         let endcol = self.current.end.endcol;
         let endline = self.current.end.line;
@@ -328,13 +539,50 @@ impl<'a> Parser<'a> {
 
         self.advance();
 
-        while self.current_is_type(TokenType::Identifier) {
-            args.push(self.current.data.clone());
-            if !self.current_is_type(TokenType::Comma) && self.current_is_type(TokenType::RParen) {
+        while !self.current_is_type(TokenType::RParen) {
+            self.expect_name("parameter");
+            let argname = Node::new(
+                Position {
+                    startcol: self.current.start.startcol,
+                    endcol: self.current.end.endcol,
+                    opcol: None,
+                    line: self.current.start.line,
+                },
+                nodes::NodeType::Identifier,
+                Box::new(IdentifierNode {
+                    value: self.current.data.clone(),
+                }),
+            );
+            self.advance();
+
+            let argname_str = argname.data.get_data().raw.get("value").unwrap().clone();
+            if let Some((first, _)) = args
+                .iter()
+                .find(|(arg, _)| arg.data.get_data().raw.get("value") == Some(&argname_str))
+            {
+                raise_error_multi(
+                    vec![
+                        format!("Parameter '{}' is defined multiple times.", argname_str),
+                        "First definition here:".into(),
+                    ],
+                    ErrorType::MultipleParameterDefinitions,
+                    vec![Some(&argname.pos), Some(&first.pos)],
+                    &self.session.info,
+                );
+            }
+
+            self.expect(TokenType::Colon);
+            self.advance();
+            let argtp = self.expr(Precedence::Lowest);
+
+            args.push((argname, argtp));
+
+            //Allow a trailing comma
+            if self.current_is_type(TokenType::RParen) {
                 break;
             }
+            self.expect(TokenType::Comma);
             self.advance();
-            self.skip_newlines();
         }
 
         self.expect(TokenType::RParen);
@@ -375,6 +623,8 @@ impl<'a> Parser<'a> {
                 args,
                 code,
                 rettp: tp,
+                is_export,
+                visibility,
             }),
         )
     }
@@ -430,6 +680,7 @@ impl<'a> Parser<'a> {
         }];
 
         while self.current_is_keyword("elif") {
+            let branchcol = self.current.start.startcol;
             self.advance();
 
             let expr = self.expr(Precedence::Lowest);
@@ -454,7 +705,7 @@ impl<'a> Parser<'a> {
             codes.push(code);
             exprs.push(expr);
             positions.push(Position {
-                startcol,
+                startcol: branchcol,
                 endcol,
                 opcol: None,
                 line: endline,
@@ -462,6 +713,7 @@ impl<'a> Parser<'a> {
         }
 
         let elsecode = if self.current_is_keyword("else") {
+            let branchcol = self.current.start.startcol;
             self.advance();
 
             self.skip_newlines();
@@ -481,7 +733,7 @@ impl<'a> Parser<'a> {
             self.advance();
             self.skip_newlines();
             positions.push(Position {
-                startcol,
+                startcol: branchcol,
                 endcol,
                 opcol: None,
                 line: endline,
@@ -505,16 +757,146 @@ impl<'a> Parser<'a> {
                 codes,
                 elsecode,
                 positions,
+                is_logical: false,
             }),
         )
     }
 
+    fn generate_unsafe(&mut self) -> Node {
+        let startcol = self.current.start.startcol;
+        let line = self.current.start.line;
+
+        self.advance();
+        self.skip_newlines();
+
+        self.expect(TokenType::LCurly);
+
+        let endcol = self.current.end.endcol;
+
+        self.advance();
+        self.skip_newlines();
+
+        let code = self.block();
+
+        self.expect(TokenType::RCurly);
+
+        self.advance();
+
+        Node::new(
+            Position {
+                startcol,
+                endcol,
+                opcol: None,
+                line,
+            },
+            nodes::NodeType::Unsafe,
+            Box::new(UnsafeNode { code }),
+        )
+    }
+
+    fn generate_while(&mut self) -> Node {
+        let startcol = self.current.start.startcol;
+        let line = self.current.start.line;
+
+        self.advance();
+
+        let expr = self.expr(Precedence::Lowest);
+
+        self.skip_newlines();
+
+        self.expect(TokenType::LCurly);
+
+        let endcol = self.current.end.endcol;
+
+        self.advance();
+        self.skip_newlines();
+
+        let code = self.block();
+
+        self.expect(TokenType::RCurly);
+
+        self.advance();
+
+        Node::new(
+            Position {
+                startcol,
+                endcol,
+                opcol: None,
+                line,
+            },
+            nodes::NodeType::While,
+            Box::new(WhileNode { expr, code }),
+        )
+    }
+
+    fn generate_loop(&mut self) -> Node {
+        let startcol = self.current.start.startcol;
+        let line = self.current.start.line;
+
+        self.advance();
+        self.skip_newlines();
+
+        self.expect(TokenType::LCurly);
+
+        let endcol = self.current.end.endcol;
+
+        self.advance();
+        self.skip_newlines();
+
+        let code = self.block();
+
+        self.expect(TokenType::RCurly);
+
+        self.advance();
+
+        Node::new(
+            Position {
+                startcol,
+                endcol,
+                opcol: None,
+                line,
+            },
+            nodes::NodeType::Loop,
+            Box::new(LoopNode { code }),
+        )
+    }
+
+    fn generate_jump(&mut self, tp: nodes::NodeType) -> Node {
+        let pos = Position {
+            startcol: self.current.start.startcol,
+            endcol: self.current.end.endcol,
+            opcol: None,
+            line: self.current.start.line,
+        };
+
+        let is_break = tp == nodes::NodeType::Break;
+        self.advance();
+
+        //Anything else on the line would otherwise be parsed as the next statement
+        if !self.current_is_type(TokenType::Newline)
+            && !self.current_is_type(TokenType::Semicolon)
+            && !self.current_is_type(TokenType::RCurly)
+            && !self.current_is_type(TokenType::Eof)
+        {
+            self.raise_error(
+                if is_break {
+                    "'break' does not take a value yet."
+                } else {
+                    "'continue' does not take a value."
+                },
+                ErrorType::JumpWithValue,
+            );
+        }
+
+        Node::new(pos, tp, Box::new(JumpNode {}))
+    }
+
     fn generate_enum(&mut self) -> Node {
         let startcol = self.current.start.startcol;
 
         self.advance();
         
-        self.expect(TokenType::Identifier);
+        self.expect_name("type");
         let name = self.current.data.clone();
         self.advance();
 
@@ -528,16 +910,33 @@ impl<'a> Parser<'a> {
         self.advance();
         self.skip_newlines();
 
-        let mut variants = HashMap::new();
+        let mut variants: IndexMap<String, Node> = IndexMap::new();
         while self.current_is_type(TokenType::Identifier) {
+            let pos = Position {
+                startcol: self.current.start.startcol,
+                endcol: self.current.end.endcol,
+                opcol: None,
+                line: self.current.start.line,
+            };
+
+            if let Some(first) = variants.get(&self.current.data) {
+                raise_error_multi(
+                    vec![
+                        format!(
+                            "Variant '{}' of enum '{}' is defined multiple times.",
+                            self.current.data, name
+                        ),
+                        "First definition here:".into(),
+                    ],
+                    ErrorType::MultipleVariantDefinitions,
+                    vec![Some(&pos), Some(&first.pos)],
+                    &self.session.info,
+                );
+            }
+
             variants.insert(self.current.data.clone(), 
             Node::new(
-                Position {
-                    startcol: self.current.start.startcol,
-                    endcol: self.current.end.endcol,
-                    opcol: None,
-                    line: self.current.start.line,
-                },
+                pos,
                 nodes::NodeType::Identifier,
                 Box::new(IdentifierNode {
                     value: "void".into(),
@@ -574,6 +973,31 @@ impl<'a> Parser<'a> {
         )
     }
 
+    fn generate_type_alias(&mut self) -> Node {
+        let startcol = self.current.start.startcol;
+        self.advance();
+
+        self.expect_name("type");
+        let name = self.current.data.clone();
+        self.advance();
+
+        self.expect(TokenType::Equal);
+        self.advance();
+
+        let tp = self.expr(Precedence::Lowest);
+
+        Node::new(
+            Position {
+                startcol,
+                endcol: tp.pos.endcol,
+                opcol: None,
+                line: tp.pos.line,
+            },
+            nodes::NodeType::TypeAlias,
+            Box::new(TypeAliasNode { name, tp }),
+        )
+    }
+
     // =======================
 
     fn atom(&mut self) -> Option<Node> {
@@ -588,6 +1012,8 @@ impl<'a> Parser<'a> {
             TokenType::U32 => Some(self.generate_u32()),
             TokenType::U64 => Some(self.generate_u64()),
             TokenType::U128 => Some(self.generate_u128()),
+            TokenType::F32 => Some(self.generate_f32()),
+            TokenType::F64 => Some(self.generate_f64()),
             TokenType::Identifier => Some(self.generate_identifier()),
             TokenType::Ampersand => Some(self.generate_reference()),
             TokenType::Keyword => {
@@ -596,6 +1022,9 @@ impl<'a> Parser<'a> {
                 Some(res)
             }
             TokenType::Asterisk => Some(self.generate_asterisk()),
+            TokenType::LBracket => Some(self.generate_slice()),
+            TokenType::Str => Some(self.generate_str()),
+            TokenType::LParen => Some(self.generate_unit()),
             _ => None,
         }
     }
@@ -605,6 +1034,10 @@ impl<'a> Parser<'a> {
     }
 
     fn expr(&mut self, prec: Precedence) -> Node {
+        self.depth += 1;
+        self.check_nesting(0);
+
+        let mut chained = 0;
         let mut left: Node;
         match self.atom() {
             None => self.raise_error("Invalid token.", ErrorType::InvalidTok),
@@ -616,19 +1049,31 @@ impl<'a> Parser<'a> {
             && (prec as u32) < (self.get_precedence() as u32)
         {
             match self.current.tp {
-                TokenType::Plus | TokenType::DoubleEqual | TokenType::NotEqual => {
-                    left = self.generate_binary(left, self.get_precedence())
-                }
+                TokenType::Plus
+                | TokenType::Hyphen
+                | TokenType::Asterisk
+                | TokenType::Slash
+                | TokenType::Percent
+                | TokenType::DoubleEqual
+                | TokenType::NotEqual => left = self.generate_binary(left, self.get_precedence()),
                 TokenType::Equal => left = self.generate_assign(left),
+                TokenType::Ampersand | TokenType::DoublePipe => {
+                    left = self.generate_logical(left, self.get_precedence())
+                }
+                TokenType::Keyword if self.current.data == "as" => left = self.generate_cast(left),
                 _ => {
                     break;
                 }
             }
+            chained += 1;
+            self.check_nesting(chained);
         }
 
         if self.is_atomic() {
+            self.partial.push((format!("{} ", pretty(&left)), String::new()));
             self.raise_error("Unexpected token.", ErrorType::InvalidTok);
         }
+        self.depth -= 1;
         left
     }
 
@@ -783,25 +1228,105 @@ impl<'a> Parser<'a> {
         )
     }
 
+    fn generate_f32(&mut self) -> Node {
+        Node::new(
+            Position {
+                startcol: self.current.start.startcol,
+                endcol: self.current.end.endcol,
+                opcol: None,
+                line: self.current.start.line,
+            },
+            nodes::NodeType::F32,
+            Box::new(DecimalNode {
+                value: self.current.data.clone(),
+            }),
+        )
+    }
+
+    fn generate_f64(&mut self) -> Node {
+        Node::new(
+            Position {
+                startcol: self.current.start.startcol,
+                endcol: self.current.end.endcol,
+                opcol: None,
+                line: self.current.start.line,
+            },
+            nodes::NodeType::F64,
+            Box::new(DecimalNode {
+                value: self.current.data.clone(),
+            }),
+        )
+    }
+
+    fn generate_str(&mut self) -> Node {
+        Node::new(
+            Position {
+                startcol: self.current.start.startcol,
+                endcol: self.current.end.endcol,
+                opcol: None,
+                line: self.current.start.line,
+            },
+            nodes::NodeType::Str,
+            Box::new(StrNode {
+                value: self.current.data.clone(),
+            }),
+        )
+    }
+
+    fn generate_unit(&mut self) -> Node {
+        let startcol = self.current.start.startcol;
+
+        self.advance();
+        self.expect(TokenType::RParen);
+
+        Node::new(
+            Position {
+                startcol,
+                endcol: self.current.end.endcol,
+                opcol: None,
+                line: self.current.start.line,
+            },
+            nodes::NodeType::Unit,
+            Box::new(UnitNode {}),
+        )
+    }
+
     fn generate_identifier(&mut self) -> Node {
-        if self.next_is_type(TokenType::LParen) {
-            let startcol = self.current.start.startcol;
-            let line = self.current.start.line;
+        let startcol = self.current.start.startcol;
+        let line = self.current.start.line;
 
-            let name = self.current.data.clone();
+        let mut name = self.current.data.clone();
 
+        while self.next_is_type(TokenType::DoubleColon) {
             self.advance();
             self.advance();
-            let mut args = Vec::new();
-            while !self.current_is_type(TokenType::RParen) {
-                args.push(self.expr(Precedence::Lowest));
-                if self.current_is_type(TokenType::RParen) {
-                    continue;
-                }
-                self.expect(TokenType::Comma);
-                self.advance();
-            }
-            self.expect(TokenType::RParen);
+            self.expect(TokenType::Identifier);
+            name.push_str("::");
+            name.push_str(&self.current.data);
+        }
+
+        if self.next_is_type(TokenType::Bang) {
+            self.advance();
+            self.advance();
+            self.expect(TokenType::LParen);
+            let args = self.call_args();
+            let endcol = self.current.end.endcol;
+
+            return Node::new(
+                Position {
+                    startcol,
+                    endcol,
+                    opcol: None,
+                    line,
+                },
+                nodes::NodeType::Macro,
+                Box::new(MacroNode { name, args }),
+            );
+        }
+
+        if self.next_is_type(TokenType::LParen) {
+            self.advance();
+            let args = self.call_args();
             let endcol = self.current.end.endcol;
 
             return Node::new(
@@ -818,22 +1343,36 @@ impl<'a> Parser<'a> {
 
         Node::new(
             Position {
-                startcol: self.current.start.startcol,
+                startcol,
                 endcol: self.current.end.endcol,
                 opcol: None,
-                line: self.current.start.line,
+                line,
             },
             nodes::NodeType::Identifier,
-            Box::new(IdentifierNode {
-                value: self.current.data.clone(),
-            }),
+            Box::new(IdentifierNode { value: name }),
         )
     }
 
+    //Parses a parenthesized argument list, from the '(' to the ')'
+    fn call_args(&mut self) -> Vec<Node> {
+        self.advance();
+        let mut args = Vec::new();
+        while !self.current_is_type(TokenType::RParen) {
+            args.push(self.expr(Precedence::Lowest));
+            if self.current_is_type(TokenType::RParen) {
+                continue;
+            }
+            self.expect(TokenType::Comma);
+            self.advance();
+        }
+        self.expect(TokenType::RParen);
+        args
+    }
+
     fn generate_reference(&mut self) -> Node {
         let pos = self.current.start.clone();
         self.advance();
-        let expr = self.expr(Precedence::Lowest);
+        let expr = self.expr(Precedence::Unary);
         self.backadvance();
         Node::new(
             Position {
@@ -850,7 +1389,26 @@ impl<'a> Parser<'a> {
     fn generate_asterisk(&mut self) -> Node {
         let pos = self.current.start.clone();
         self.advance();
-        let expr = self.expr(Precedence::Lowest);
+
+        //`*const T` and `*mut T` are raw pointer types
+        if self.current_is_keyword("const") || self.current_is_keyword("mut") {
+            let is_mut = self.current_is_keyword("mut");
+            self.advance();
+            let tp = self.expr(Precedence::Unary);
+            self.backadvance();
+            return Node::new(
+                Position {
+                    startcol: pos.startcol,
+                    endcol: tp.pos.endcol,
+                    opcol: None,
+                    line: pos.line,
+                },
+                nodes::NodeType::RawPtr,
+                Box::new(RawPtrNode { tp, is_mut }),
+            );
+        }
+
+        let expr = self.expr(Precedence::Unary);
         self.backadvance();
         Node::new(
             Position {
@@ -864,10 +1422,32 @@ impl<'a> Parser<'a> {
         )
     }
 
+    //`[T]` is a slice type
+    fn generate_slice(&mut self) -> Node {
+        let pos = self.current.start.clone();
+        self.advance();
+        let tp = self.expr(Precedence::Lowest);
+        self.expect(TokenType::RBracket);
+        Node::new(
+            Position {
+                startcol: pos.startcol,
+                endcol: self.current.end.endcol,
+                opcol: None,
+                line: pos.line,
+            },
+            nodes::NodeType::Slice,
+            Box::new(SliceNode { tp }),
+        )
+    }
+
     // ============ Expr ============
     fn generate_binary(&mut self, left: Node, prec: Precedence) -> Node {
         let op = match self.current.tp {
             TokenType::Plus => OpType::Add,
+            TokenType::Hyphen => OpType::Sub,
+            TokenType::Asterisk => OpType::Mul,
+            TokenType::Slash => OpType::Div,
+            TokenType::Percent => OpType::Rem,
             TokenType::DoubleEqual => OpType::Eq,
             TokenType::NotEqual => OpType::Ne,
             _ => {
@@ -879,7 +1459,7 @@ impl<'a> Parser<'a> {
 
         self.advance();
 
-        let right = self.expr(prec);
+        let right = self.operand(format!("({} {} ", pretty(&left), op.symbol()), ")", prec);
 
         Node::new(
             Position {
@@ -893,7 +1473,79 @@ impl<'a> Parser<'a> {
         )
     }
 
+    //`a && b` is `if a { b } else { false }` and `a || b` is `if a { true } else { b }`, so the
+    //right side is conditionally executed everywhere after parsing, including the borrow checker
+    fn generate_logical(&mut self, left: Node, prec: Precedence) -> Node {
+        let is_and = self.current_is_type(TokenType::Ampersand);
+        let opcol = self.current.start.startcol;
+
+        if is_and {
+            self.advance();
+        }
+        let oppos = Position {
+            startcol: opcol,
+            endcol: self.current.end.endcol,
+            opcol: None,
+            line: self.current.start.line,
+        };
+
+        self.advance();
+
+        let op = if is_and { "&&" } else { "||" };
+        let right = self.operand(format!("({} {op} ", pretty(&left)), ")", prec);
+
+        let pos = Position {
+            startcol: left.pos.startcol,
+            endcol: right.pos.endcol,
+            opcol: Some(opcol),
+            line: left.pos.line,
+        };
+        let literal = Node::new(
+            oppos.clone(),
+            nodes::NodeType::Bool,
+            Box::new(BoolNode { value: !is_and }),
+        );
+        let (code, elsecode) = if is_and {
+            (right, literal)
+        } else {
+            (literal, right)
+        };
+
+        Node::new(
+            pos,
+            nodes::NodeType::Conditional,
+            Box::new(ConditionalNode {
+                exprs: vec![left],
+                codes: vec![vec![code]],
+                elsecode: Some(vec![elsecode]),
+                positions: vec![oppos.clone(), oppos],
+                is_logical: true,
+            }),
+        )
+    }
+
+    fn generate_cast(&mut self, left: Node) -> Node {
+        let opcol = self.current.start.startcol;
+
+        self.advance();
+
+        let tp = self.operand(format!("({} as ", pretty(&left)), ")", Precedence::To);
+
+        Node::new(
+            Position {
+                startcol: left.pos.startcol,
+                endcol: tp.pos.endcol,
+                opcol: Some(opcol),
+                line: left.pos.line,
+            },
+            nodes::NodeType::Cast,
+            Box::new(CastNode { expr: left, tp }),
+        )
+    }
+
     fn generate_assign(&mut self, left: Node) -> Node {
+        let opcol = self.current.start.startcol;
+
         self.advance();
 
         if left.tp != NodeType::Identifier {
@@ -901,17 +1553,17 @@ impl<'a> Parser<'a> {
                 "Expected identifier node.",
                 ErrorType::InvalidTok,
                 &left.pos,
-                &self.info,
+                &self.session.info,
             )
         }
 
-        let expr = self.expr(Precedence::Lowest);
+        let expr = self.operand(format!("{} = ", pretty(&left)), "", Precedence::Lowest);
 
         Node::new(
             Position {
                 startcol: left.pos.startcol,
                 endcol: expr.pos.endcol,
-                opcol: None,
+                opcol: Some(opcol),
                 line: left.pos.line,
             },
             nodes::NodeType::Store,
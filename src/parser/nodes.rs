@@ -1,5 +1,7 @@
 use std::{collections::HashMap, fmt::Debug};
 
+use indexmap::IndexMap;
+
 use trc::Trc;
 
 use crate::utils::Position;
@@ -49,12 +51,26 @@ pub enum NodeType {
     U32,
     U64,
     U128,
+    F32,
+    F64,
     Fn,
     Return,
     Call,
     Deref,
     Conditional,
     Enum,
+    Str,
+    Unit,
+    RawPtr,
+    Slice,
+    Cast,
+    Unsafe,
+    While,
+    Loop,
+    Break,
+    Continue,
+    Macro,
+    TypeAlias,
 }
 
 #[derive(Debug)]
@@ -63,14 +79,13 @@ pub struct NodeValue<'a> {
     pub nodes: HashMap<String, &'a Node>,
     pub op: Option<OpType>,
     pub nodearr: Option<&'a Vec<Node>>,
-    pub args: Option<Vec<String>>,
     pub mapping: Option<&'a Vec<(Node, Node)>>,
     pub booleans: HashMap<String, bool>,
     pub tp: Option<Node>,
     pub nodearr_codes: Option<&'a Vec<Vec<Node>>>,
     pub nodearr_else: &'a Option<Vec<Node>>,
     pub positions: Vec<Position>,
-    pub nodes_owned: HashMap<String, Node>,
+    pub nodes_owned: IndexMap<String, Node>,
 }
 
 pub trait NodeData {
@@ -90,14 +105,13 @@ impl<'a> NodeValue<'a> {
             nodes: HashMap::new(),
             op: None,
             nodearr: None,
-            args: None,
             mapping: None,
             booleans: HashMap::new(),
             tp: None,
             nodearr_codes: None,
             nodearr_else: &None,
             positions: Vec::new(),
-            nodes_owned: HashMap::new(),
+            nodes_owned: IndexMap::new(),
         }
     }
 }
@@ -122,13 +136,58 @@ impl NodeData for DecimalNode {
 
 // ========================
 
+pub struct UnitNode {}
+
+impl NodeData for UnitNode {
+    fn get_data(&self) -> NodeValue<'_> {
+        NodeValue::new()
+    }
+}
+
+// ========================
+
+pub struct StrNode {
+    pub value: String,
+}
+
+impl NodeData for StrNode {
+    fn get_data(&self) -> NodeValue<'_> {
+        let mut value = NodeValue::new();
+        value
+            .raw
+            .insert(String::from("value"), self.value.to_owned());
+
+        value
+    }
+}
+
+// ========================
+
 #[derive(Debug, Copy, Clone)]
 pub enum OpType {
     Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
     Eq,
     Ne,
 }
 
+impl OpType {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            OpType::Add => "+",
+            OpType::Sub => "-",
+            OpType::Mul => "*",
+            OpType::Div => "/",
+            OpType::Rem => "%",
+            OpType::Eq => "==",
+            OpType::Ne => "!=",
+        }
+    }
+}
+
 pub struct BinaryNode {
     pub left: Node,
     pub right: Node,
@@ -167,7 +226,8 @@ impl NodeData for IdentifierNode {
 
 pub struct LetNode {
     pub name: String,
-    pub expr: Node,
+    pub expr: Option<Node>,
+    pub tp: Option<Node>,
     pub is_mut: bool,
 }
 
@@ -175,7 +235,12 @@ impl NodeData for LetNode {
     fn get_data(&self) -> NodeValue {
         let mut value = NodeValue::new();
         value.raw.insert(String::from("name"), self.name.to_owned());
-        value.nodes.insert(String::from("expr"), &self.expr);
+        if let Some(expr) = &self.expr {
+            value.nodes.insert(String::from("expr"), expr);
+        }
+        if let Some(tp) = &self.tp {
+            value.nodes.insert(String::from("tp"), tp);
+        }
         value.booleans.insert(String::from("is_mut"), self.is_mut);
 
         value
@@ -233,9 +298,13 @@ impl NodeData for BoolNode {
 
 pub struct FnNode {
     pub name: String,
-    pub args: Vec<String>,
+    pub args: Vec<(Node, Node)>,
     pub code: Vec<Node>,
     pub rettp: Option<Node>,
+    /// Whether the function is exported from a shared library under its own name.
+    pub is_export: bool,
+    /// The symbol visibility of an exported function, from `#[visibility = "..."]`.
+    pub visibility: Option<String>,
 }
 
 impl NodeData for FnNode {
@@ -243,8 +312,12 @@ impl NodeData for FnNode {
         let mut value = NodeValue::new();
         value.nodearr = Some(&self.code);
         value.raw.insert(String::from("name"), self.name.clone());
-        value.args = Some(self.args.clone());
+        value.mapping = Some(&self.args);
         value.tp = self.rettp.clone();
+        value.booleans.insert(String::from("is_export"), self.is_export);
+        if let Some(visibility) = &self.visibility {
+            value.raw.insert(String::from("visibility"), visibility.clone());
+        }
 
         value
     }
@@ -304,6 +377,8 @@ pub struct ConditionalNode {
     pub codes: Vec<Vec<Node>>,
     pub elsecode: Option<Vec<Node>>,
     pub positions: Vec<Position>,
+    /// Lowered from `&&` or `||` rather than written as an if.
+    pub is_logical: bool,
 }
 
 impl NodeData for ConditionalNode {
@@ -313,6 +388,9 @@ impl NodeData for ConditionalNode {
         value.nodearr_else = &self.elsecode;
         value.nodearr_codes = Some(&self.codes);
         value.positions = self.positions.clone();
+        value
+            .booleans
+            .insert(String::from("is_logical"), self.is_logical);
 
         value
     }
@@ -323,7 +401,8 @@ impl NodeData for ConditionalNode {
 
 pub struct EnumNode {
     pub name: String,
-    pub variants: HashMap<String, Node>,
+    /// In declaration order, which gives each variant its discriminant.
+    pub variants: IndexMap<String, Node>,
 }
 
 impl NodeData for EnumNode {
@@ -335,3 +414,144 @@ impl NodeData for EnumNode {
         value
     }
 }
+
+// ========================
+
+pub struct TypeAliasNode {
+    pub name: String,
+    pub tp: Node,
+}
+
+impl NodeData for TypeAliasNode {
+    fn get_data(&self) -> NodeValue<'_> {
+        let mut value = NodeValue::new();
+        value.raw.insert("name".into(), self.name.clone());
+        value.nodes.insert("tp".into(), &self.tp);
+
+        value
+    }
+}
+
+// ========================
+
+pub struct RawPtrNode {
+    pub tp: Node,
+    pub is_mut: bool,
+}
+
+impl NodeData for RawPtrNode {
+    fn get_data(&self) -> NodeValue<'_> {
+        let mut value = NodeValue::new();
+        value.nodes.insert(String::from("tp"), &self.tp);
+        value.booleans.insert(String::from("is_mut"), self.is_mut);
+
+        value
+    }
+}
+
+// ========================
+
+pub struct SliceNode {
+    pub tp: Node,
+}
+
+impl NodeData for SliceNode {
+    fn get_data(&self) -> NodeValue<'_> {
+        let mut value = NodeValue::new();
+        value.nodes.insert(String::from("tp"), &self.tp);
+
+        value
+    }
+}
+
+// ========================
+
+pub struct CastNode {
+    pub expr: Node,
+    pub tp: Node,
+}
+
+impl NodeData for CastNode {
+    fn get_data(&self) -> NodeValue<'_> {
+        let mut value = NodeValue::new();
+        value.nodes.insert(String::from("expr"), &self.expr);
+        value.nodes.insert(String::from("tp"), &self.tp);
+
+        value
+    }
+}
+
+// ========================
+
+pub struct UnsafeNode {
+    pub code: Vec<Node>,
+}
+
+impl NodeData for UnsafeNode {
+    fn get_data(&self) -> NodeValue<'_> {
+        let mut value = NodeValue::new();
+        value.nodearr = Some(&self.code);
+
+        value
+    }
+}
+
+// ========================
+
+pub struct WhileNode {
+    pub expr: Node,
+    pub code: Vec<Node>,
+}
+
+impl NodeData for WhileNode {
+    fn get_data(&self) -> NodeValue<'_> {
+        let mut value = NodeValue::new();
+        value.nodes.insert(String::from("expr"), &self.expr);
+        value.nodearr = Some(&self.code);
+
+        value
+    }
+}
+
+// ========================
+
+pub struct LoopNode {
+    pub code: Vec<Node>,
+}
+
+impl NodeData for LoopNode {
+    fn get_data(&self) -> NodeValue<'_> {
+        let mut value = NodeValue::new();
+        value.nodearr = Some(&self.code);
+
+        value
+    }
+}
+
+// ========================
+
+/// A `break` or `continue`, which applies to the innermost loop.
+pub struct JumpNode {}
+
+impl NodeData for JumpNode {
+    fn get_data(&self) -> NodeValue<'_> {
+        NodeValue::new()
+    }
+}
+
+// ========================
+
+pub struct MacroNode {
+    pub name: String,
+    pub args: Vec<Node>,
+}
+
+impl NodeData for MacroNode {
+    fn get_data(&self) -> NodeValue<'_> {
+        let mut value = NodeValue::new();
+        value.raw.insert(String::from("name"), self.name.clone());
+        value.nodearr = Some(&self.args);
+
+        value
+    }
+}
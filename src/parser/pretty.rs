@@ -0,0 +1,126 @@
+use super::nodes::{Node, NodeType};
+
+/// Renders `node` back into source on one line, with every operator parenthesized, so it shows
+/// how the parser grouped an expression.
+pub fn pretty(node: &Node) -> String {
+    let data = node.data.get_data();
+    let raw = |name: &str| data.raw.get(name).unwrap().clone();
+    let expr = |name: &str| pretty(data.nodes.get(name).unwrap());
+    let list = |nodes: &[Node]| nodes.iter().map(pretty).collect::<Vec<_>>().join(", ");
+
+    match node.tp {
+        NodeType::I32 | NodeType::Identifier => raw("value"),
+        NodeType::I8 => format!("{}i8", raw("value")),
+        NodeType::I16 => format!("{}i16", raw("value")),
+        NodeType::I64 => format!("{}i64", raw("value")),
+        NodeType::I128 => format!("{}i128", raw("value")),
+        NodeType::U8 => format!("{}u8", raw("value")),
+        NodeType::U16 => format!("{}u16", raw("value")),
+        NodeType::U32 => format!("{}u32", raw("value")),
+        NodeType::U64 => format!("{}u64", raw("value")),
+        NodeType::U128 => format!("{}u128", raw("value")),
+        NodeType::F32 => format!("{}f32", raw("value")),
+        NodeType::F64 => format!("{}f64", raw("value")),
+        NodeType::Bool => data.booleans.get("value").unwrap().to_string(),
+        NodeType::Str => format!("{:?}", raw("value")),
+        NodeType::Unit => "()".into(),
+        NodeType::Binary => format!(
+            "({} {} {})",
+            expr("left"),
+            data.op.unwrap().symbol(),
+            expr("right")
+        ),
+        NodeType::Store => format!("{} = {}", raw("name"), expr("expr")),
+        NodeType::Reference => format!("&{}", expr("expr")),
+        NodeType::Deref => format!("*{}", expr("expr")),
+        NodeType::Cast => format!("({} as {})", expr("expr"), expr("tp")),
+        NodeType::RawPtr => format!(
+            "*{} {}",
+            if *data.booleans.get("is_mut").unwrap() {
+                "mut"
+            } else {
+                "const"
+            },
+            expr("tp")
+        ),
+        NodeType::Slice => format!("[{}]", expr("tp")),
+        NodeType::Call => format!("{}({})", raw("name"), list(data.nodearr.unwrap())),
+        NodeType::Macro => format!("{}!({})", raw("name"), list(data.nodearr.unwrap())),
+        NodeType::Return => format!("return {}", expr("expr")),
+        NodeType::Let => {
+            let mut out = format!(
+                "let {}{}",
+                if *data.booleans.get("is_mut").unwrap() {
+                    "mut "
+                } else {
+                    ""
+                },
+                raw("name")
+            );
+            if data.nodes.contains_key("tp") {
+                out.push_str(&format!(": {}", expr("tp")));
+            }
+            if data.nodes.contains_key("expr") {
+                out.push_str(&format!(" = {}", expr("expr")));
+            }
+            out
+        }
+        NodeType::Conditional => {
+            let mut out = data
+                .nodearr
+                .unwrap()
+                .iter()
+                .zip(data.nodearr_codes.unwrap())
+                .map(|(cond, code)| format!("if {} {}", pretty(cond), block(code)))
+                .collect::<Vec<_>>()
+                .join(" el");
+            if let Some(code) = data.nodearr_else {
+                out.push_str(&format!(" else {}", block(code)));
+            }
+            out
+        }
+        NodeType::Unsafe => format!("unsafe {}", block(data.nodearr.unwrap())),
+        NodeType::While => format!("while {} {}", expr("expr"), block(data.nodearr.unwrap())),
+        NodeType::Loop => format!("loop {}", block(data.nodearr.unwrap())),
+        NodeType::Break => "break".into(),
+        NodeType::Continue => "continue".into(),
+        NodeType::Fn => {
+            let params = data
+                .mapping
+                .unwrap()
+                .iter()
+                .map(|(name, tp)| format!("{}: {}", pretty(name), pretty(tp)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let rettp = match &data.tp {
+                Some(tp) => format!(": {}", pretty(tp)),
+                None => String::new(),
+            };
+            format!(
+                "fn {}({params}){rettp} {}",
+                raw("name"),
+                block(data.nodearr.unwrap())
+            )
+        }
+        NodeType::Enum => format!(
+            "enum {} {{ {} }}",
+            raw("name"),
+            data.nodes_owned
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        NodeType::TypeAlias => format!("type {} = {}", raw("name"), expr("tp")),
+    }
+}
+
+fn block(code: &[Node]) -> String {
+    if code.is_empty() {
+        return "{}".into();
+    }
+    format!(
+        "{{ {} }}",
+        code.iter().map(pretty).collect::<Vec<_>>().join("; ")
+    )
+}
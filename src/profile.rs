@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::errors::{raise_error_no_pos, ErrorType};
+
+pub const MANIFEST_NAME: &str = "Kestrel.toml";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Profile {
+    pub optimize: bool,
+    pub overflow_checks: bool,
+    pub debug_info: bool,
+}
+
+impl Profile {
+    pub fn debug() -> Self {
+        Profile {
+            optimize: false,
+            overflow_checks: true,
+            debug_info: true,
+        }
+    }
+
+    pub fn release() -> Self {
+        Profile {
+            optimize: true,
+            overflow_checks: false,
+            debug_info: false,
+        }
+    }
+
+    fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "debug" => Some(Self::debug()),
+            "release" => Some(Self::release()),
+            _ => None,
+        }
+    }
+
+    fn apply(&mut self, overrides: &ProfileOverrides) {
+        if let Some(optimize) = overrides.optimize {
+            self.optimize = optimize;
+        }
+        if let Some(overflow_checks) = overrides.overflow_checks {
+            self.overflow_checks = overflow_checks;
+        }
+        if let Some(debug_info) = overrides.debug_info {
+            self.debug_info = debug_info;
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct Manifest {
+    #[serde(default)]
+    profile: HashMap<String, ProfileOverrides>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct ProfileOverrides {
+    inherits: Option<String>,
+    optimize: Option<bool>,
+    overflow_checks: Option<bool>,
+    debug_info: Option<bool>,
+}
+
+fn load_manifest() -> Manifest {
+    let data = match std::fs::read_to_string(MANIFEST_NAME) {
+        Ok(data) => data,
+        Err(_) => return Manifest::default(),
+    };
+
+    match toml::from_str(&data) {
+        Ok(manifest) => manifest,
+        Err(err) => raise_error_no_pos(
+            &format!("Unable to parse '{MANIFEST_NAME}': {}", err.message()),
+            ErrorType::InvalidManifest,
+        ),
+    }
+}
+
+/// Resolve a profile by name: the builtin `debug` and `release` profiles may be overridden
+/// in the manifest, and any other profile defined there inherits from `debug` by default.
+pub fn resolve_profile(name: &str) -> Profile {
+    let manifest = load_manifest();
+
+    let overrides = manifest.profile.get(name);
+
+    let mut profile = match (Profile::builtin(name), overrides) {
+        (Some(profile), _) => profile,
+        (None, Some(overrides)) => {
+            let base = overrides.inherits.as_deref().unwrap_or("debug");
+            match Profile::builtin(base) {
+                Some(profile) => profile,
+                None => raise_error_no_pos(
+                    &format!("Profile '{name}' inherits from unknown profile '{base}'"),
+                    ErrorType::ProfileNotFound,
+                ),
+            }
+        }
+        (None, None) => raise_error_no_pos(
+            &format!("Profile '{name}' is not a builtin profile or defined in '{MANIFEST_NAME}'"),
+            ErrorType::ProfileNotFound,
+        ),
+    };
+
+    if let Some(overrides) = overrides {
+        profile.apply(overrides);
+    }
+
+    profile
+}
@@ -0,0 +1,70 @@
+use crate::{errors::WarningType, profile::Profile, utils::FileInfo};
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
+pub enum Flags {
+    NoOUChecks,
+    Sanitize,
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
+pub enum Emit {
+    DepInfo,
+    Globals,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum CrateType {
+    Bin,
+    Cdylib,
+    /// An object file to be linked by the user, so no entry point is generated.
+    Obj,
+}
+
+/// How the executable links its libraries.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum LinkMode {
+    Dynamic,
+    CrtStatic,
+    Static,
+}
+
+/// Hardening for shipped binaries: a stack protector in every function, or a read-only GOT.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Hardening {
+    /// Protect functions with character arrays or `alloca`s (`ssp`).
+    StackProtector,
+    /// Protect every function with a local array or a local whose address is taken (`sspstrong`).
+    StackProtectorStrong,
+    /// Make relocated data read-only after startup (ELF only).
+    Relro,
+    /// Resolve every symbol at startup, so with `Relro` the whole GOT is read-only (ELF only).
+    Now,
+}
+
+/// Profile-guided optimization: either instrument the program, or optimize it with a profile.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Pgo {
+    Generate,
+    Use(String),
+}
+
+/// Everything one compilation is invoked with: the source file and the resolved options. It is
+/// created once by the driver and borrowed by the lexer, parser, MIR and codegen.
+#[derive(Debug)]
+pub struct Session<'a> {
+    pub info: FileInfo<'a>,
+    pub profile: Profile,
+    pub flags: Vec<Flags>,
+    pub emit: Vec<Emit>,
+    pub pgo: Option<Pgo>,
+    pub link_mode: LinkMode,
+    pub crate_type: CrateType,
+    pub hardening: Vec<Hardening>,
+    /// The warnings that are reported, after applying `--warn` and `--allow` to the defaults.
+    pub lints: Vec<WarningType>,
+    /// Additional LLVM module passes, run after the default pipeline.
+    pub passes: Vec<String>,
+    pub debug_mir: bool,
+    pub mir_color: bool,
+    pub mir_stats: bool,
+}
@@ -0,0 +1,269 @@
+use inkwell::{module::Linkage, values::FunctionValue, AddressSpace, IntPredicate};
+
+use crate::{
+    codegen::{CodeGen, Data},
+    errors::{raise_error, ErrorType},
+    mir::Mir,
+    types::{BasicType, StdFunction, Type},
+    utils::{build_rt_check, Position},
+};
+
+use super::{
+    slice::build_slice,
+    str::{build_str, index_value, str_type},
+};
+
+pub const ARGC_GLOBAL: &str = "__kestrel_argc";
+pub const ARGV_GLOBAL: &str = "__kestrel_argv";
+const ARGS_GLOBAL: &str = "__kestrel_args";
+const ARGS: &str = "kestrel_args";
+
+fn argc<'a>(codegen: &mut CodeGen<'a>, _pos: &Position, _args: Vec<Data<'a>>) -> Data<'a> {
+    let global = codegen.module.get_global(ARGC_GLOBAL).unwrap();
+    let res = codegen.builder.build_load(global.as_pointer_value(), "");
+
+    Data {
+        data: Some(res),
+        tp: codegen.types.builtins.get(&BasicType::I32).unwrap().clone(),
+    }
+}
+
+fn argc_skeleton<'a>(mir: &mut Mir<'a>, pos: &Position, args: Vec<Type<'a>>) -> Type<'a> {
+    if !args.is_empty() {
+        raise_error(
+            &format!("Expected 0 arguments, got {}.", args.len()),
+            ErrorType::TypeMismatch,
+            pos,
+            &mir.session.info,
+        );
+    }
+    mir.types.builtins.get(&BasicType::I32).unwrap().clone()
+}
+
+/// The argument at an index, which must be less than `std::argc()`. Index 0 is the program name.
+fn arg<'a>(codegen: &mut CodeGen<'a>, pos: &Position, args: Vec<Data<'a>>) -> Data<'a> {
+    let i64_tp = codegen.context.i64_type();
+    let argc = codegen
+        .builder
+        .build_load(codegen.module.get_global(ARGC_GLOBAL).unwrap().as_pointer_value(), "")
+        .into_int_value();
+    let argc = codegen.builder.build_int_z_extend(argc, i64_tp, "");
+    let index = index_value(codegen, &args[0]);
+
+    let out_of_bounds = codegen
+        .builder
+        .build_int_compare(IntPredicate::UGE, index, argc, "");
+    build_rt_check(codegen, out_of_bounds, "std::arg index out of bounds!", pos);
+
+    let argv = codegen
+        .builder
+        .build_load(codegen.module.get_global(ARGV_GLOBAL).unwrap().as_pointer_value(), "")
+        .into_pointer_value();
+    let arg_ptr = unsafe { codegen.builder.build_in_bounds_gep(argv, &[index], "") };
+    let ptr = codegen.builder.build_load(arg_ptr, "").into_pointer_value();
+
+    let len = codegen
+        .builder
+        .build_call(*codegen.extern_fns.get("strlen").unwrap(), &[ptr.into()], "")
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value();
+
+    build_str(codegen, ptr, len)
+}
+
+fn arg_skeleton<'a>(mir: &mut Mir<'a>, pos: &Position, args: Vec<Type<'a>>) -> Type<'a> {
+    if args.len() != 1 {
+        raise_error(
+            &format!("Expected 1 argument, got {}.", args.len()),
+            ErrorType::WrongArgumentCount,
+            pos,
+            &mir.session.info,
+        );
+    }
+    let tp = args.first().unwrap();
+    if tp.ref_n != 0
+        || tp.signedness().is_none()
+        || matches!(tp.basictype, BasicType::I128 | BasicType::U128)
+    {
+        raise_error(
+            &format!(
+                "Expected an integer of at most 64 bits as the index, got '{}'",
+                tp.qualname()
+            ),
+            ErrorType::TypeMismatch,
+            pos,
+            &mir.session.info,
+        );
+    }
+    mir.types.builtins.get(&BasicType::Str).unwrap().clone()
+}
+
+/// The runtime shim `str* kestrel_args()`, which converts argv to `str`s the first time it is
+/// called and returns the same array every time after that.
+fn args_shim<'a>(codegen: &mut CodeGen<'a>) -> FunctionValue<'a> {
+    if let Some(function) = codegen.module.get_function(ARGS) {
+        return function;
+    }
+
+    let i64_tp = codegen.context.i64_type();
+    let str_tp = str_type(codegen.context);
+    let str_ptr_tp = str_tp.ptr_type(AddressSpace::from(0u16));
+    let function = codegen
+        .module
+        .add_function(ARGS, str_ptr_tp.fn_type(&[], false), Some(Linkage::Private));
+
+    let entry = codegen.context.append_basic_block(function, "");
+    let fill = codegen.context.append_basic_block(function, "");
+    let cond = codegen.context.append_basic_block(function, "");
+    let body = codegen.context.append_basic_block(function, "");
+    let done = codegen.context.append_basic_block(function, "");
+
+    let builder = codegen.context.create_builder();
+    builder.position_at_end(entry);
+    let global = codegen.module.get_global(ARGS_GLOBAL).unwrap().as_pointer_value();
+    let cached = builder.build_load(global, "").into_pointer_value();
+    let argc = builder
+        .build_load(codegen.module.get_global(ARGC_GLOBAL).unwrap().as_pointer_value(), "")
+        .into_int_value();
+    let argc = builder.build_int_z_extend(argc, i64_tp, "");
+    builder.build_conditional_branch(builder.build_is_null(cached, ""), fill, done);
+
+    builder.position_at_end(fill);
+    let size = builder.build_int_mul(argc, str_tp.size_of().unwrap(), "");
+    let raw = builder
+        .build_call(*codegen.extern_fns.get("malloc").unwrap(), &[size.into()], "")
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_pointer_value();
+    let args = builder.build_pointer_cast(raw, str_ptr_tp, "");
+    builder.build_store(global, args);
+    builder.build_unconditional_branch(cond);
+
+    builder.position_at_end(cond);
+    let index = builder.build_phi(i64_tp, "");
+    let index_value = index.as_basic_value().into_int_value();
+    let more = builder.build_int_compare(IntPredicate::ULT, index_value, argc, "");
+    builder.build_conditional_branch(more, body, done);
+
+    builder.position_at_end(body);
+    let argv = builder
+        .build_load(codegen.module.get_global(ARGV_GLOBAL).unwrap().as_pointer_value(), "")
+        .into_pointer_value();
+    let arg_ptr = unsafe { builder.build_in_bounds_gep(argv, &[index_value], "") };
+    let ptr = builder.build_load(arg_ptr, "").into_pointer_value();
+    let len = builder
+        .build_call(*codegen.extern_fns.get("strlen").unwrap(), &[ptr.into()], "")
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value();
+    let mut arg = str_tp.get_undef();
+    arg = builder
+        .build_insert_value(arg, ptr, 0, "")
+        .unwrap()
+        .into_struct_value();
+    arg = builder
+        .build_insert_value(arg, len, 1, "")
+        .unwrap()
+        .into_struct_value();
+    let out = unsafe { builder.build_in_bounds_gep(args, &[index_value], "") };
+    builder.build_store(out, arg);
+    let next = builder.build_int_add(index_value, i64_tp.const_int(1, false), "");
+    builder.build_unconditional_branch(cond);
+    index.add_incoming(&[(&i64_tp.const_zero(), fill), (&next, body)]);
+
+    builder.position_at_end(done);
+    let res = builder.build_load(global, "");
+    builder.build_return(Some(&res));
+
+    function
+}
+
+/// Every argument, starting with the program name, as a `[str]`.
+fn args<'a>(codegen: &mut CodeGen<'a>, _pos: &Position, _args: Vec<Data<'a>>) -> Data<'a> {
+    let shim = args_shim(codegen);
+    let ptr = codegen
+        .builder
+        .build_call(shim, &[], "")
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_pointer_value();
+    let argc = codegen
+        .builder
+        .build_load(codegen.module.get_global(ARGC_GLOBAL).unwrap().as_pointer_value(), "")
+        .into_int_value();
+    let len = codegen
+        .builder
+        .build_int_z_extend(argc, codegen.context.i64_type(), "");
+
+    let tp = codegen.types.builtins.get(&BasicType::Str).unwrap().slice();
+    build_slice(codegen, ptr, len, tp)
+}
+
+fn args_skeleton<'a>(mir: &mut Mir<'a>, pos: &Position, args: Vec<Type<'a>>) -> Type<'a> {
+    if !args.is_empty() {
+        raise_error(
+            &format!("Expected 0 arguments, got {}.", args.len()),
+            ErrorType::WrongArgumentCount,
+            pos,
+            &mir.session.info,
+        );
+    }
+    mir.types.builtins.get(&BasicType::Str).unwrap().slice()
+}
+
+pub fn init_args(codegen: &mut CodeGen) {
+    let argc_global = codegen.module.add_global(
+        codegen.context.i32_type(),
+        Some(AddressSpace::from(0u16)),
+        ARGC_GLOBAL,
+    );
+    argc_global.set_linkage(Linkage::Private);
+    argc_global.set_initializer(&codegen.context.i32_type().const_zero());
+
+    let argv_tp = codegen
+        .context
+        .i8_type()
+        .ptr_type(AddressSpace::from(0u16))
+        .ptr_type(AddressSpace::from(0u16));
+    let argv_global =
+        codegen
+            .module
+            .add_global(argv_tp, Some(AddressSpace::from(0u16)), ARGV_GLOBAL);
+    argv_global.set_linkage(Linkage::Private);
+    argv_global.set_initializer(&argv_tp.const_null());
+
+    let args_tp = str_type(codegen.context).ptr_type(AddressSpace::from(0u16));
+    let args_global =
+        codegen
+            .module
+            .add_global(args_tp, Some(AddressSpace::from(0u16)), ARGS_GLOBAL);
+    args_global.set_linkage(Linkage::Private);
+    args_global.set_initializer(&args_tp.const_null());
+
+    codegen.std_fns.insert(
+        String::from("std::argc"),
+        StdFunction {
+            code: argc,
+            skeleton: argc_skeleton,
+        },
+    );
+    codegen.std_fns.insert(
+        String::from("std::arg"),
+        StdFunction {
+            code: arg,
+            skeleton: arg_skeleton,
+        },
+    );
+    codegen.std_fns.insert(
+        String::from("std::args"),
+        StdFunction {
+            code: args,
+            skeleton: args_skeleton,
+        },
+    );
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use crate::{
+    codegen::{CodeGen, Data},
+    errors::{raise_error, ErrorType},
+    mir::Mir,
+    types::{BasicType, Lifetime, Trait, TraitCode, TraitType, Type},
+    utils::Position,
+};
+
+fn bool_eq<'a>(
+    codegen: &mut CodeGen<'a>,
+    _pos: &Position,
+    this: Data<'a>,
+    other: Data<'a>,
+) -> Data<'a> {
+    let res = codegen.builder.build_int_compare(
+        inkwell::IntPredicate::EQ,
+        this.data.unwrap().into_int_value(),
+        other.data.unwrap().into_int_value(),
+        "",
+    );
+
+    Data {
+        data: Some(res.into()),
+        tp: codegen.types.builtins.get(&BasicType::Bool).unwrap().clone(),
+    }
+}
+
+fn bool_ne<'a>(
+    codegen: &mut CodeGen<'a>,
+    _pos: &Position,
+    this: Data<'a>,
+    other: Data<'a>,
+) -> Data<'a> {
+    let res = codegen.builder.build_int_compare(
+        inkwell::IntPredicate::NE,
+        this.data.unwrap().into_int_value(),
+        other.data.unwrap().into_int_value(),
+        "",
+    );
+
+    Data {
+        data: Some(res.into()),
+        tp: codegen.types.builtins.get(&BasicType::Bool).unwrap().clone(),
+    }
+}
+
+fn bool_skeleton_cmp<'a>(
+    mir: &mut Mir<'a>,
+    pos: &Position,
+    this: Type<'a>,
+    other: Type<'a>,
+) -> Type<'a> {
+    if this != other {
+        raise_error(
+            &format!("Expected 'std::bool', got '{}'", other.qualname()),
+            ErrorType::TypeMismatch,
+            pos,
+            &mir.session.info,
+        );
+    }
+    mir.types.builtins.get(&BasicType::Bool).unwrap().clone()
+}
+
+fn bool_default<'a>(codegen: &mut CodeGen<'a>, _pos: &Position, tp: Type<'a>) -> Data<'a> {
+    Data {
+        data: Some(codegen.context.bool_type().const_zero().into()),
+        tp,
+    }
+}
+
+pub fn init_bool(codegen: &mut CodeGen) {
+    let tp = Type {
+        basictype: BasicType::Bool,
+        traits: HashMap::from([
+            (
+                TraitType::Eq,
+                Trait {
+                    code: TraitCode::Operator {
+                        code: bool_eq,
+                        skeleton: bool_skeleton_cmp,
+                    },
+                    ref_n: 0,
+                },
+            ),
+            (
+                TraitType::Ne,
+                Trait {
+                    code: TraitCode::Operator {
+                        code: bool_ne,
+                        skeleton: bool_skeleton_cmp,
+                    },
+                    ref_n: 0,
+                },
+            ),
+            (
+                TraitType::Copy,
+                Trait {
+                    code: TraitCode::Marker,
+                    ref_n: 0,
+                },
+            ),
+            (
+                TraitType::Default,
+                Trait {
+                    code: TraitCode::Default(bool_default),
+                    ref_n: 0,
+                },
+            ),
+        ]),
+        qualname: "std::bool".into(),
+        lifetime: Lifetime::Static,
+        ref_n: 0,
+        usertype: None,
+    };
+    codegen.types.builtins.insert(BasicType::Bool, tp);
+}
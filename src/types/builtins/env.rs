@@ -0,0 +1,109 @@
+use inkwell::values::PointerValue;
+
+use crate::{
+    codegen::{CodeGen, Data},
+    errors::{raise_error, ErrorType},
+    mir::Mir,
+    types::{BasicType, StdFunction, Type},
+    utils::{global_string, Position},
+};
+
+use super::str::build_str;
+
+fn getenv<'a>(codegen: &mut CodeGen<'a>, args: Vec<Data<'a>>) -> PointerValue<'a> {
+    let name = codegen
+        .builder
+        .build_extract_value(args.first().unwrap().data.unwrap().into_struct_value(), 0, "")
+        .unwrap();
+
+    codegen
+        .builder
+        .build_call(*codegen.extern_fns.get("getenv").unwrap(), &[name.into()], "")
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_pointer_value()
+}
+
+/// Lowered to `getenv`. An unset variable yields an empty `str`, see `std::env_is_set`.
+fn env<'a>(codegen: &mut CodeGen<'a>, _pos: &Position, args: Vec<Data<'a>>) -> Data<'a> {
+    let ptr = getenv(codegen, args);
+
+    let is_null = codegen.builder.build_is_null(ptr, "");
+    let empty = global_string(codegen, "");
+    let safe_ptr = codegen
+        .builder
+        .build_select(is_null, empty, ptr, "")
+        .into_pointer_value();
+
+    let len = codegen
+        .builder
+        .build_call(*codegen.extern_fns.get("strlen").unwrap(), &[safe_ptr.into()], "")
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value();
+
+    build_str(codegen, safe_ptr, len)
+}
+
+/// Whether the variable is set, which tells an unset variable from one set to "".
+fn env_is_set<'a>(codegen: &mut CodeGen<'a>, _pos: &Position, args: Vec<Data<'a>>) -> Data<'a> {
+    let ptr = getenv(codegen, args);
+
+    Data {
+        data: Some(codegen.builder.build_is_not_null(ptr, "").into()),
+        tp: codegen.types.builtins.get(&BasicType::Bool).unwrap().clone(),
+    }
+}
+
+fn check_name_arg<'a>(mir: &mut Mir<'a>, pos: &Position, args: &[Type<'a>]) {
+    if args.len() != 1 {
+        raise_error(
+            &format!("Expected 1 argument, got {}.", args.len()),
+            ErrorType::TypeMismatch,
+            pos,
+            &mir.session.info,
+        );
+    }
+    let str_tp = mir.types.builtins.get(&BasicType::Str).unwrap().clone();
+    if args.first().unwrap() != &str_tp {
+        raise_error(
+            &format!(
+                "Expected '{}', got '{}'",
+                str_tp.qualname(),
+                args.first().unwrap().qualname()
+            ),
+            ErrorType::TypeMismatch,
+            pos,
+            &mir.session.info,
+        );
+    }
+}
+
+fn env_skeleton<'a>(mir: &mut Mir<'a>, pos: &Position, args: Vec<Type<'a>>) -> Type<'a> {
+    check_name_arg(mir, pos, &args);
+    mir.types.builtins.get(&BasicType::Str).unwrap().clone()
+}
+
+fn env_is_set_skeleton<'a>(mir: &mut Mir<'a>, pos: &Position, args: Vec<Type<'a>>) -> Type<'a> {
+    check_name_arg(mir, pos, &args);
+    mir.types.builtins.get(&BasicType::Bool).unwrap().clone()
+}
+
+pub fn init_env(codegen: &mut CodeGen) {
+    codegen.std_fns.insert(
+        String::from("std::env"),
+        StdFunction {
+            code: env,
+            skeleton: env_skeleton,
+        },
+    );
+    codegen.std_fns.insert(
+        String::from("std::env_is_set"),
+        StdFunction {
+            code: env_is_set,
+            skeleton: env_is_set_skeleton,
+        },
+    );
+}
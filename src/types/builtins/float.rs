@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use inkwell::FloatPredicate;
+
+use crate::{
+    codegen::{CodeGen, Data},
+    errors::{raise_error, ErrorType},
+    mir::Mir,
+    types::{BasicType, Lifetime, Trait, TraitCode, TraitType, Type},
+    utils::Position,
+};
+
+/// IEEE 754 arithmetic, which never traps: overflow gives an infinity, and dividing by zero an
+/// infinity or NaN.
+fn float_arith<'a>(
+    codegen: &mut CodeGen<'a>,
+    this: Data<'a>,
+    other: Data<'a>,
+    name: &str,
+) -> Data<'a> {
+    let left = this.data.unwrap().into_float_value();
+    let right = other.data.unwrap().into_float_value();
+    let res = match name {
+        "add" => codegen.builder.build_float_add(left, right, ""),
+        "sub" => codegen.builder.build_float_sub(left, right, ""),
+        "mul" => codegen.builder.build_float_mul(left, right, ""),
+        "div" => codegen.builder.build_float_div(left, right, ""),
+        _ => codegen.builder.build_float_rem(left, right, ""),
+    };
+
+    Data {
+        data: Some(res.into()),
+        tp: this.tp,
+    }
+}
+
+fn float_add<'a>(
+    codegen: &mut CodeGen<'a>,
+    _pos: &Position,
+    this: Data<'a>,
+    other: Data<'a>,
+) -> Data<'a> {
+    float_arith(codegen, this, other, "add")
+}
+
+fn float_sub<'a>(
+    codegen: &mut CodeGen<'a>,
+    _pos: &Position,
+    this: Data<'a>,
+    other: Data<'a>,
+) -> Data<'a> {
+    float_arith(codegen, this, other, "sub")
+}
+
+fn float_mul<'a>(
+    codegen: &mut CodeGen<'a>,
+    _pos: &Position,
+    this: Data<'a>,
+    other: Data<'a>,
+) -> Data<'a> {
+    float_arith(codegen, this, other, "mul")
+}
+
+fn float_div<'a>(
+    codegen: &mut CodeGen<'a>,
+    _pos: &Position,
+    this: Data<'a>,
+    other: Data<'a>,
+) -> Data<'a> {
+    float_arith(codegen, this, other, "div")
+}
+
+fn float_rem<'a>(
+    codegen: &mut CodeGen<'a>,
+    _pos: &Position,
+    this: Data<'a>,
+    other: Data<'a>,
+) -> Data<'a> {
+    float_arith(codegen, this, other, "rem")
+}
+
+/// NaN is unequal to everything, itself included, so `==` is ordered and `!=` is unordered.
+fn float_compare<'a>(
+    codegen: &mut CodeGen<'a>,
+    this: Data<'a>,
+    other: Data<'a>,
+    predicate: FloatPredicate,
+) -> Data<'a> {
+    let res = codegen.builder.build_float_compare(
+        predicate,
+        this.data.unwrap().into_float_value(),
+        other.data.unwrap().into_float_value(),
+        "",
+    );
+
+    Data {
+        data: Some(res.into()),
+        tp: codegen.types.builtins.get(&BasicType::Bool).unwrap().clone(),
+    }
+}
+
+fn float_eq<'a>(
+    codegen: &mut CodeGen<'a>,
+    _pos: &Position,
+    this: Data<'a>,
+    other: Data<'a>,
+) -> Data<'a> {
+    float_compare(codegen, this, other, FloatPredicate::OEQ)
+}
+
+fn float_ne<'a>(
+    codegen: &mut CodeGen<'a>,
+    _pos: &Position,
+    this: Data<'a>,
+    other: Data<'a>,
+) -> Data<'a> {
+    float_compare(codegen, this, other, FloatPredicate::UNE)
+}
+
+fn check_float_operands<'a>(mir: &Mir, pos: &Position, this: &Type<'a>, other: &Type<'a>) {
+    if this != other {
+        raise_error(
+            &format!("Expected '{}', got '{}'", this.qualname(), other.qualname()),
+            ErrorType::TypeMismatch,
+            pos,
+            &mir.session.info,
+        );
+    }
+}
+
+fn float_skeleton_op<'a>(
+    mir: &mut Mir,
+    pos: &Position,
+    this: Type<'a>,
+    other: Type<'a>,
+) -> Type<'a> {
+    check_float_operands(mir, pos, &this, &other);
+    this
+}
+
+fn float_skeleton_cmp<'a>(
+    mir: &mut Mir<'a>,
+    pos: &Position,
+    this: Type<'a>,
+    other: Type<'a>,
+) -> Type<'a> {
+    check_float_operands(mir, pos, &this, &other);
+    mir.types.builtins.get(&BasicType::Bool).unwrap().clone()
+}
+
+fn float_default<'a>(codegen: &mut CodeGen<'a>, _pos: &Position, tp: Type<'a>) -> Data<'a> {
+    Data {
+        data: Some(
+            CodeGen::kestrel_to_inkwell_tp(codegen.context, &tp)
+                .into_float_type()
+                .const_zero()
+                .into(),
+        ),
+        tp,
+    }
+}
+
+pub fn init_float(codegen: &mut CodeGen) {
+    for basictype in [BasicType::F32, BasicType::F64] {
+        let mut traits = HashMap::from([
+            (
+                TraitType::Copy,
+                Trait {
+                    code: TraitCode::Marker,
+                    ref_n: 0,
+                },
+            ),
+            (
+                TraitType::Default,
+                Trait {
+                    code: TraitCode::Default(float_default),
+                    ref_n: 0,
+                },
+            ),
+        ]);
+        for (traittp, code, skeleton) in [
+            (TraitType::Add, float_add as _, float_skeleton_op as _),
+            (TraitType::Sub, float_sub as _, float_skeleton_op as _),
+            (TraitType::Mul, float_mul as _, float_skeleton_op as _),
+            (TraitType::Div, float_div as _, float_skeleton_op as _),
+            (TraitType::Rem, float_rem as _, float_skeleton_op as _),
+            (TraitType::Eq, float_eq as _, float_skeleton_cmp as _),
+            (TraitType::Ne, float_ne as _, float_skeleton_cmp as _),
+        ] {
+            traits.insert(
+                traittp,
+                Trait {
+                    code: TraitCode::Operator { code, skeleton },
+                    ref_n: 0,
+                },
+            );
+        }
+
+        let tp = Type {
+            basictype: basictype.clone(),
+            traits,
+            qualname: format!("std::{basictype}"),
+            lifetime: Lifetime::Static,
+            ref_n: 0,
+            usertype: None,
+        };
+        codegen.types.builtins.insert(basictype, tp);
+    }
+}
@@ -0,0 +1,314 @@
+use inkwell::{
+    module::Linkage,
+    types::FunctionType,
+    values::{FunctionValue, PointerValue},
+    AddressSpace, IntPredicate,
+};
+
+use crate::{
+    codegen::{is_msvc, CodeGen, Data},
+    errors::{raise_error, ErrorType},
+    mir::Mir,
+    types::{BasicType, StdFunction, Type},
+    utils::{build_rt_check, global_string, Position},
+};
+
+use super::str::build_str;
+
+const SEEK_END: u64 = 2;
+
+/// A C library function, declared the first time it is used.
+fn libc_fn<'a>(codegen: &CodeGen<'a>, name: &str, tp: FunctionType<'a>) -> FunctionValue<'a> {
+    codegen
+        .module
+        .get_function(name)
+        .unwrap_or_else(|| codegen.module.add_function(name, tp, Some(Linkage::External)))
+}
+
+/// Calls `fopen` on the bytes of a `str`, which are NUL-terminated.
+fn fopen<'a>(codegen: &mut CodeGen<'a>, path: &Data<'a>, mode: &str) -> PointerValue<'a> {
+    let ptr_tp = codegen.context.i8_type().ptr_type(AddressSpace::from(0u16));
+    let fopen = libc_fn(
+        codegen,
+        "fopen",
+        ptr_tp.fn_type(&[ptr_tp.into(), ptr_tp.into()], false),
+    );
+
+    let path = codegen
+        .builder
+        .build_extract_value(path.data.unwrap().into_struct_value(), 0, "")
+        .unwrap();
+    let mode = global_string(codegen, mode);
+    codegen
+        .builder
+        .build_call(fopen, &[path.into(), mode.into()], "")
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_pointer_value()
+}
+
+fn fclose<'a>(codegen: &mut CodeGen<'a>, file: PointerValue<'a>) -> Data<'a> {
+    let fclose = libc_fn(
+        codegen,
+        "fclose",
+        codegen
+            .context
+            .i32_type()
+            .fn_type(&[file.get_type().into()], false),
+    );
+    let res = codegen
+        .builder
+        .build_call(fclose, &[file.into()], "")
+        .try_as_basic_value()
+        .left();
+
+    Data {
+        data: res,
+        tp: codegen.types.builtins.get(&BasicType::I32).unwrap().clone(),
+    }
+}
+
+/// Reads a whole file into a buffer from `malloc`, which is never freed like `std::to_string`. A
+/// file that cannot be opened or sized is a runtime error.
+fn read_to_string<'a>(codegen: &mut CodeGen<'a>, pos: &Position, args: Vec<Data<'a>>) -> Data<'a> {
+    let i32_tp = codegen.context.i32_type();
+    let i64_tp = codegen.context.i64_type();
+    let ptr_tp = codegen.context.i8_type().ptr_type(AddressSpace::from(0u16));
+    //`long` is 32 bits on Windows
+    let long_tp = if is_msvc(codegen.module.get_triple().as_str().to_str().unwrap()) {
+        i32_tp
+    } else {
+        i64_tp
+    };
+
+    let file = fopen(codegen, &args[0], "rb");
+    let is_null = codegen.builder.build_is_null(file, "");
+    build_rt_check(
+        codegen,
+        is_null,
+        "std::fs::read_to_string could not open the file!",
+        pos,
+    );
+
+    let fseek = libc_fn(
+        codegen,
+        "fseek",
+        i32_tp.fn_type(&[ptr_tp.into(), long_tp.into(), i32_tp.into()], false),
+    );
+    let ftell = libc_fn(codegen, "ftell", long_tp.fn_type(&[ptr_tp.into()], false));
+    let rewind = libc_fn(
+        codegen,
+        "rewind",
+        codegen.context.void_type().fn_type(&[ptr_tp.into()], false),
+    );
+    let fread = libc_fn(
+        codegen,
+        "fread",
+        i64_tp.fn_type(
+            &[ptr_tp.into(), i64_tp.into(), i64_tp.into(), ptr_tp.into()],
+            false,
+        ),
+    );
+
+    //ftell fails with -1 on files that cannot seek, such as directories and pipes
+    codegen.builder.build_call(
+        fseek,
+        &[
+            file.into(),
+            long_tp.const_zero().into(),
+            i32_tp.const_int(SEEK_END, false).into(),
+        ],
+        "",
+    );
+    let size = codegen
+        .builder
+        .build_call(ftell, &[file.into()], "")
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value();
+    let unsized_file =
+        codegen
+            .builder
+            .build_int_compare(IntPredicate::SLT, size, long_tp.const_zero(), "");
+    build_rt_check(
+        codegen,
+        unsized_file,
+        "std::fs::read_to_string could not read the file!",
+        pos,
+    );
+    let size = codegen.builder.build_int_s_extend_or_bit_cast(size, i64_tp, "");
+    codegen.builder.build_call(rewind, &[file.into()], "");
+
+    let capacity = codegen
+        .builder
+        .build_int_add(size, i64_tp.const_int(1, false), "");
+    let buffer = codegen
+        .builder
+        .build_call(*codegen.extern_fns.get("malloc").unwrap(), &[capacity.into()], "")
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_pointer_value();
+    //Directories can be opened, but report a size that cannot be allocated
+    let no_buffer = codegen.builder.build_is_null(buffer, "");
+    build_rt_check(
+        codegen,
+        no_buffer,
+        "std::fs::read_to_string could not read the file!",
+        pos,
+    );
+
+    let len = codegen
+        .builder
+        .build_call(
+            fread,
+            &[
+                buffer.into(),
+                i64_tp.const_int(1, false).into(),
+                size.into(),
+                file.into(),
+            ],
+            "",
+        )
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value();
+    fclose(codegen, file);
+
+    let nul = unsafe { codegen.builder.build_in_bounds_gep(buffer, &[len], "") };
+    codegen
+        .builder
+        .build_store(nul, codegen.context.i8_type().const_zero());
+
+    build_str(codegen, buffer, len)
+}
+
+/// Creates or truncates a file and writes the contents to it, returning whether every byte was
+/// written and the file was closed without an error.
+fn write<'a>(codegen: &mut CodeGen<'a>, _pos: &Position, args: Vec<Data<'a>>) -> Data<'a> {
+    let i64_tp = codegen.context.i64_type();
+    let ptr_tp = codegen.context.i8_type().ptr_type(AddressSpace::from(0u16));
+    let bool_tp = codegen.context.bool_type();
+    let fwrite = libc_fn(
+        codegen,
+        "fwrite",
+        i64_tp.fn_type(
+            &[ptr_tp.into(), i64_tp.into(), i64_tp.into(), ptr_tp.into()],
+            false,
+        ),
+    );
+
+    let file = fopen(codegen, &args[0], "wb");
+    let is_null = codegen.builder.build_is_null(file, "");
+
+    let open_block = codegen.builder.get_insert_block().unwrap();
+    let write_block = codegen
+        .context
+        .append_basic_block(codegen.cur_fn.unwrap(), "");
+    let done_block = codegen
+        .context
+        .append_basic_block(codegen.cur_fn.unwrap(), "");
+    codegen
+        .builder
+        .build_conditional_branch(is_null, done_block, write_block);
+
+    codegen.builder.position_at_end(write_block);
+    let contents = args[1].data.unwrap().into_struct_value();
+    let ptr = codegen.builder.build_extract_value(contents, 0, "").unwrap();
+    let len = codegen
+        .builder
+        .build_extract_value(contents, 1, "")
+        .unwrap()
+        .into_int_value();
+    let written = codegen
+        .builder
+        .build_call(
+            fwrite,
+            &[
+                ptr.into(),
+                i64_tp.const_int(1, false).into(),
+                len.into(),
+                file.into(),
+            ],
+            "",
+        )
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value();
+    let closed = fclose(codegen, file).data.unwrap().into_int_value();
+
+    let all_written = codegen
+        .builder
+        .build_int_compare(IntPredicate::EQ, written, len, "");
+    let closed = codegen.builder.build_int_compare(
+        IntPredicate::EQ,
+        closed,
+        closed.get_type().const_zero(),
+        "",
+    );
+    let ok = codegen.builder.build_and(all_written, closed, "");
+    codegen.builder.build_unconditional_branch(done_block);
+
+    codegen.builder.position_at_end(done_block);
+    let res = codegen.builder.build_phi(bool_tp, "");
+    res.add_incoming(&[(&bool_tp.const_zero(), open_block), (&ok, write_block)]);
+
+    Data {
+        data: Some(res.as_basic_value()),
+        tp: codegen.types.builtins.get(&BasicType::Bool).unwrap().clone(),
+    }
+}
+
+/// Every argument of a `std::fs` function is a `str`.
+fn check_str_args<'a>(mir: &Mir<'a>, pos: &Position, args: &[Type<'a>], n: usize) {
+    if args.len() != n {
+        raise_error(
+            &format!("Expected {n} arguments, got {}.", args.len()),
+            ErrorType::WrongArgumentCount,
+            pos,
+            &mir.session.info,
+        );
+    }
+    let str_tp = mir.types.builtins.get(&BasicType::Str).unwrap();
+    for tp in args {
+        if tp != str_tp {
+            raise_error(
+                &format!("Expected '{}', got '{}'", str_tp.qualname(), tp.qualname()),
+                ErrorType::TypeMismatch,
+                pos,
+                &mir.session.info,
+            );
+        }
+    }
+}
+
+fn read_to_string_skeleton<'a>(mir: &mut Mir<'a>, pos: &Position, args: Vec<Type<'a>>) -> Type<'a> {
+    check_str_args(mir, pos, &args, 1);
+    mir.types.builtins.get(&BasicType::Str).unwrap().clone()
+}
+
+fn write_skeleton<'a>(mir: &mut Mir<'a>, pos: &Position, args: Vec<Type<'a>>) -> Type<'a> {
+    check_str_args(mir, pos, &args, 2);
+    mir.types.builtins.get(&BasicType::Bool).unwrap().clone()
+}
+
+pub fn init_fs(codegen: &mut CodeGen) {
+    codegen.std_fns.insert(
+        String::from("std::fs::read_to_string"),
+        StdFunction {
+            code: read_to_string,
+            skeleton: read_to_string_skeleton,
+        },
+    );
+    codegen.std_fns.insert(
+        String::from("std::fs::write"),
+        StdFunction {
+            code: write,
+            skeleton: write_skeleton,
+        },
+    );
+}
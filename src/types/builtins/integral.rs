@@ -1,161 +1,195 @@
 use std::collections::HashMap;
 
-use inkwell::{intrinsics::Intrinsic, types::BasicTypeEnum};
+use inkwell::{intrinsics::Intrinsic, IntPredicate};
 use strum::IntoEnumIterator;
 
 use crate::{
-    codegen::{CodeGen, CurFunctionState, Data},
+    codegen::{CodeGen, Data},
     errors::{raise_error, ErrorType},
     mir::Mir,
-    types::{BasicType, Lifetime, Trait, TraitType, Type},
-    utils::{print_string, Position},
-    Flags,
+    types::{BasicType, Lifetime, Signedness, StdFunction, Trait, TraitCode, TraitType, Type},
+    session::Flags,
+    utils::{build_rt_check, Position},
 };
 
-fn integral_add<'a>(
+/// Adds, subtracts or multiplies with `llvm.{s,u}{name}.with.overflow`, so overflow is a runtime
+/// error. Without overflow checks the result wraps.
+fn integral_arith<'a>(
     codegen: &mut CodeGen<'a>,
     pos: &Position,
     this: Data<'a>,
     other: Data<'a>,
+    name: &str,
+    operation: &str,
 ) -> Data<'a> {
-    let tp = this.data.as_ref().unwrap().get_type();
-    let tpname = this.tp.basictype.to_string();
-    if !codegen.flags.contains(&Flags::NoOUChecks) {
-        let sadd_intrinsic =
-            Intrinsic::find(&format!("llvm.sadd.with.overflow.{}", tpname)).unwrap();
-        let expect_i1 = Intrinsic::find("llvm.expect.i1").unwrap();
-
-        let sadd_function = sadd_intrinsic
-            .get_declaration(&codegen.module, &[tp, tp])
-            .unwrap();
-
-        let expect_i1_function = expect_i1
-            .get_declaration(
-                &codegen.module,
-                &[
-                    codegen.context.bool_type().into(),
-                    codegen.context.bool_type().into(),
-                ],
-            )
-            .unwrap();
-
-        let res = codegen
-            .builder
-            .build_call(
-                sadd_function,
-                &[this.data.unwrap().into(), other.data.unwrap().into()],
-                "",
-            )
-            .try_as_basic_value()
-            .left();
-
-        let result = codegen
-            .builder
-            .build_extract_value(res.unwrap().into_struct_value(), 0, "");
-        let overflow = codegen
-            .builder
-            .build_extract_value(res.unwrap().into_struct_value(), 1, "");
+    let left = this.data.unwrap().into_int_value();
+    let right = other.data.unwrap().into_int_value();
 
-        let overflow_block: inkwell::basic_block::BasicBlock = codegen
-            .context
-            .append_basic_block(codegen.cur_fn.unwrap(), "");
-        let end_block: inkwell::basic_block::BasicBlock = codegen
-            .context
-            .append_basic_block(codegen.cur_fn.unwrap(), "");
+    if codegen.session.flags.contains(&Flags::NoOUChecks) {
+        let res = match name {
+            "add" => codegen.builder.build_int_add(left, right, ""),
+            "sub" => codegen.builder.build_int_sub(left, right, ""),
+            _ => codegen.builder.build_int_mul(left, right, ""),
+        };
+        return Data {
+            data: Some(res.into()),
+            tp: this.tp,
+        };
+    }
 
-        let done_block: inkwell::basic_block::BasicBlock = codegen
-            .context
-            .append_basic_block(codegen.cur_fn.unwrap(), "");
+    let intrinsic = Intrinsic::find(&match this.tp.signedness() {
+        Some(Signedness::Unsigned) => format!("llvm.u{name}.with.overflow"),
+        _ => format!("llvm.s{name}.with.overflow"),
+    })
+    .unwrap();
+    let function = intrinsic
+        .get_declaration(&codegen.module, &[left.get_type().into(), right.get_type().into()])
+        .unwrap();
 
-        let res = codegen
-            .builder
-            .build_call(
-                expect_i1_function,
-                &[
-                    overflow.unwrap().into(),
-                    codegen.context.bool_type().const_int(0, true).into(),
-                ],
-                "",
-            )
-            .try_as_basic_value()
-            .left();
-
-        codegen.builder.build_conditional_branch(
-            res.unwrap().into_int_value(),
-            overflow_block,
-            end_block,
-        );
+    let res = codegen
+        .builder
+        .build_call(function, &[left.into(), right.into()], "")
+        .try_as_basic_value()
+        .left();
 
-        codegen.builder.position_at_end(overflow_block);
-        codegen.block = Some(overflow_block);
+    let result = codegen
+        .builder
+        .build_extract_value(res.unwrap().into_struct_value(), 0, "");
+    let overflow = codegen
+        .builder
+        .build_extract_value(res.unwrap().into_struct_value(), 1, "");
 
-        print_string(
-            codegen,
-            &format!(
-                "Error: {} addition overflow!\n    {}:{}:{}\n",
-                this.tp.qualname,
-                codegen.info.name,
-                pos.line + 1,
-                pos.opcol.unwrap() + 1
-            ),
-        );
+    build_rt_check(
+        codegen,
+        overflow.unwrap().into_int_value(),
+        &format!("{} {operation} overflow!", this.tp.qualname),
+        pos,
+    );
 
-        codegen.builder.build_unconditional_branch(done_block);
+    Data {
+        data: result,
+        tp: this.tp,
+    }
+}
 
-        codegen.builder.position_at_end(end_block);
-        codegen.block = Some(end_block);
+fn integral_add<'a>(
+    codegen: &mut CodeGen<'a>,
+    pos: &Position,
+    this: Data<'a>,
+    other: Data<'a>,
+) -> Data<'a> {
+    integral_arith(codegen, pos, this, other, "add", "addition")
+}
 
-        codegen.builder.build_unconditional_branch(done_block);
+fn integral_sub<'a>(
+    codegen: &mut CodeGen<'a>,
+    pos: &Position,
+    this: Data<'a>,
+    other: Data<'a>,
+) -> Data<'a> {
+    integral_arith(codegen, pos, this, other, "sub", "subtraction")
+}
 
-        overflow_block
-            .move_after(codegen.cur_fnstate.as_ref().unwrap().cur_block.unwrap())
-            .unwrap();
-        end_block.move_after(overflow_block).unwrap();
+fn integral_mul<'a>(
+    codegen: &mut CodeGen<'a>,
+    pos: &Position,
+    this: Data<'a>,
+    other: Data<'a>,
+) -> Data<'a> {
+    integral_arith(codegen, pos, this, other, "mul", "multiplication")
+}
 
-        codegen.builder.position_at_end(done_block);
-        codegen.block = Some(done_block);
+/// Divides or takes the remainder, rounding toward zero. Dividing by zero, or the minimum of a
+/// signed type by -1, is undefined in LLVM, so both are checked even without overflow checks.
+fn integral_divide<'a>(
+    codegen: &mut CodeGen<'a>,
+    pos: &Position,
+    this: Data<'a>,
+    other: Data<'a>,
+    is_rem: bool,
+) -> Data<'a> {
+    let left = this.data.unwrap().into_int_value();
+    let right = other.data.unwrap().into_int_value();
+    let int_tp = left.get_type();
+    let operation = if is_rem { "remainder" } else { "division" };
+
+    let is_zero = codegen
+        .builder
+        .build_int_compare(IntPredicate::EQ, right, int_tp.const_zero(), "");
+    build_rt_check(
+        codegen,
+        is_zero,
+        &format!("{} {operation} by zero!", this.tp.qualname),
+        pos,
+    );
 
-        let phi = codegen
+    let signed = this.tp.signedness() == Some(Signedness::Signed);
+    if signed {
+        let min = int_tp.const_int(1, false).const_shl(int_tp.const_int(
+            int_tp.get_bit_width() as u64 - 1,
+            false,
+        ));
+        let is_min = codegen
             .builder
-            .build_phi(this.data.unwrap().into_int_value().get_type(), "");
+            .build_int_compare(IntPredicate::EQ, left, min, "");
+        let is_neg_one =
+            codegen
+                .builder
+                .build_int_compare(IntPredicate::EQ, right, int_tp.const_all_ones(), "");
+        let overflow = codegen.builder.build_and(is_min, is_neg_one, "");
+        build_rt_check(
+            codegen,
+            overflow,
+            &format!("{} {operation} overflow!", this.tp.qualname),
+            pos,
+        );
+    }
 
-        phi.add_incoming(&[(&result.unwrap(), end_block)]);
-        if let BasicTypeEnum::IntType(tp) = tp {
-            phi.add_incoming(&[(&tp.get_undef(), overflow_block)]);
-        }
+    let res = match (is_rem, signed) {
+        (false, true) => codegen.builder.build_int_signed_div(left, right, ""),
+        (false, false) => codegen.builder.build_int_unsigned_div(left, right, ""),
+        (true, true) => codegen.builder.build_int_signed_rem(left, right, ""),
+        (true, false) => codegen.builder.build_int_unsigned_rem(left, right, ""),
+    };
 
-        codegen.cur_fnstate = Some(CurFunctionState {
-            cur_block: Some(done_block),
-            returned: false,
-            rettp: codegen.cur_fnstate.as_ref().unwrap().rettp.clone(),
-        });
+    Data {
+        data: Some(res.into()),
+        tp: this.tp,
+    }
+}
 
-        Data {
-            data: Some(phi.as_basic_value()),
-            tp: this.tp,
-        }
-    } else {
-        let res = codegen.builder.build_int_add(
-            this.data.unwrap().into_int_value(),
-            other.data.unwrap().into_int_value(),
-            "",
-        );
+fn integral_div<'a>(
+    codegen: &mut CodeGen<'a>,
+    pos: &Position,
+    this: Data<'a>,
+    other: Data<'a>,
+) -> Data<'a> {
+    integral_divide(codegen, pos, this, other, false)
+}
 
-        Data {
-            data: Some(res.into()),
-            tp: this.tp,
-        }
-    }
+fn integral_rem<'a>(
+    codegen: &mut CodeGen<'a>,
+    pos: &Position,
+    this: Data<'a>,
+    other: Data<'a>,
+) -> Data<'a> {
+    integral_divide(codegen, pos, this, other, true)
 }
 
-fn integral_eq<'a>(
+/// Compares two integers of the same type with `signed` or `unsigned`, by the type's signedness.
+fn integral_compare<'a>(
     codegen: &mut CodeGen<'a>,
-    _pos: &Position,
     this: Data<'a>,
     other: Data<'a>,
+    signed: IntPredicate,
+    unsigned: IntPredicate,
 ) -> Data<'a> {
+    let predicate = match this.tp.signedness() {
+        Some(Signedness::Unsigned) => unsigned,
+        _ => signed,
+    };
     let res = codegen.builder.build_int_compare(
-        inkwell::IntPredicate::EQ,
+        predicate,
         this.data.unwrap().into_int_value(),
         other.data.unwrap().into_int_value(),
         "",
@@ -163,43 +197,134 @@ fn integral_eq<'a>(
 
     Data {
         data: Some(res.into()),
-        tp: codegen.builtins.get(&BasicType::Bool).unwrap().clone(),
+        tp: codegen.types.builtins.get(&BasicType::Bool).unwrap().clone(),
     }
 }
 
+fn integral_eq<'a>(
+    codegen: &mut CodeGen<'a>,
+    _pos: &Position,
+    this: Data<'a>,
+    other: Data<'a>,
+) -> Data<'a> {
+    integral_compare(codegen, this, other, IntPredicate::EQ, IntPredicate::EQ)
+}
+
 fn integral_ne<'a>(
     codegen: &mut CodeGen<'a>,
     _pos: &Position,
     this: Data<'a>,
     other: Data<'a>,
 ) -> Data<'a> {
-    let res = codegen.builder.build_int_compare(
-        inkwell::IntPredicate::NE,
-        this.data.unwrap().into_int_value(),
-        other.data.unwrap().into_int_value(),
-        "",
-    );
+    integral_compare(codegen, this, other, IntPredicate::NE, IntPredicate::NE)
+}
 
+fn integral_default<'a>(codegen: &mut CodeGen<'a>, _pos: &Position, tp: Type<'a>) -> Data<'a> {
     Data {
-        data: Some(res.into()),
-        tp: codegen.builtins.get(&BasicType::Bool).unwrap().clone(),
+        data: Some(
+            CodeGen::kestrel_to_inkwell_tp(codegen.context, &tp)
+                .into_int_type()
+                .const_zero()
+                .into(),
+        ),
+        tp,
     }
 }
 
-fn integral_skeleton_op<'a>(
+/// Both operands must have the same type. Two integer types are never converted implicitly, so
+/// mixing them gets an error suggesting a cast.
+fn check_integral_operands<'a>(
+    mir: &Mir,
+    pos: &Position,
+    verb: &str,
+    this: &Type<'a>,
+    other: &Type<'a>,
+) {
+    if this == other {
+        return;
+    }
+
+    let hint = format!(
+        "cast one side with 'as {}' or 'as {}'",
+        this.basictype, other.basictype
+    );
+    match (this.signedness(), other.signedness()) {
+        (Some(left), Some(right)) if left != right => raise_error(
+            &format!(
+                "Cannot {verb} signed and unsigned integers '{}' and '{}', {hint}.",
+                this.qualname(),
+                other.qualname()
+            ),
+            ErrorType::MixedSignedness,
+            pos,
+            &mir.session.info,
+        ),
+        (Some(_), Some(_)) => raise_error(
+            &format!(
+                "Cannot {verb} integers of different widths '{}' and '{}', {hint}.",
+                this.qualname(),
+                other.qualname()
+            ),
+            ErrorType::TypeMismatch,
+            pos,
+            &mir.session.info,
+        ),
+        _ => raise_error(
+            &format!("Expected '{}', got '{}'", this.qualname(), other.qualname()),
+            ErrorType::TypeMismatch,
+            pos,
+            &mir.session.info,
+        ),
+    }
+}
+
+fn integral_skeleton_add<'a>(
     mir: &mut Mir,
     pos: &Position,
     this: Type<'a>,
     other: Type<'a>,
 ) -> Type<'a> {
-    if this != other {
-        raise_error(
-            &format!("Expected 'std::i32', got '{}'", other.qualname()),
-            ErrorType::TypeMismatch,
-            pos,
-            &mir.info,
-        );
-    }
+    check_integral_operands(mir, pos, "add", &this, &other);
+    this
+}
+
+fn integral_skeleton_sub<'a>(
+    mir: &mut Mir,
+    pos: &Position,
+    this: Type<'a>,
+    other: Type<'a>,
+) -> Type<'a> {
+    check_integral_operands(mir, pos, "subtract", &this, &other);
+    this
+}
+
+fn integral_skeleton_mul<'a>(
+    mir: &mut Mir,
+    pos: &Position,
+    this: Type<'a>,
+    other: Type<'a>,
+) -> Type<'a> {
+    check_integral_operands(mir, pos, "multiply", &this, &other);
+    this
+}
+
+fn integral_skeleton_div<'a>(
+    mir: &mut Mir,
+    pos: &Position,
+    this: Type<'a>,
+    other: Type<'a>,
+) -> Type<'a> {
+    check_integral_operands(mir, pos, "divide", &this, &other);
+    this
+}
+
+fn integral_skeleton_rem<'a>(
+    mir: &mut Mir,
+    pos: &Position,
+    this: Type<'a>,
+    other: Type<'a>,
+) -> Type<'a> {
+    check_integral_operands(mir, pos, "take the remainder of", &this, &other);
     this
 }
 
@@ -209,53 +334,212 @@ fn integral_skeleton_cmp<'a>(
     this: Type<'a>,
     other: Type<'a>,
 ) -> Type<'a> {
-    if this != other {
+    check_integral_operands(mir, pos, "compare", &this, &other);
+    mir.types.builtins.get(&BasicType::Bool).unwrap().clone()
+}
+
+/// Calls the LLVM intrinsic `llvm.{name}`, overloaded on the type of the single argument.
+fn integral_intrinsic<'a>(codegen: &mut CodeGen<'a>, name: &str, args: Vec<Data<'a>>) -> Data<'a> {
+    let arg = args.into_iter().next().unwrap();
+    let value = arg.data.unwrap();
+
+    let function = Intrinsic::find(&format!("llvm.{name}"))
+        .unwrap()
+        .get_declaration(&codegen.module, &[value.get_type()])
+        .unwrap();
+
+    let mut intrinsic_args = vec![value.into()];
+    //ctlz and cttz take whether a zero input is poison, which it is not here
+    if matches!(name, "ctlz" | "cttz") {
+        intrinsic_args.push(codegen.context.bool_type().const_zero().into());
+    }
+
+    let res = codegen
+        .builder
+        .build_call(function, &intrinsic_args, "")
+        .try_as_basic_value()
+        .left();
+
+    Data {
+        data: res,
+        tp: arg.tp,
+    }
+}
+
+fn intrinsic_ctlz<'a>(codegen: &mut CodeGen<'a>, _pos: &Position, args: Vec<Data<'a>>) -> Data<'a> {
+    integral_intrinsic(codegen, "ctlz", args)
+}
+
+fn intrinsic_cttz<'a>(codegen: &mut CodeGen<'a>, _pos: &Position, args: Vec<Data<'a>>) -> Data<'a> {
+    integral_intrinsic(codegen, "cttz", args)
+}
+
+fn intrinsic_popcount<'a>(
+    codegen: &mut CodeGen<'a>,
+    _pos: &Position,
+    args: Vec<Data<'a>>,
+) -> Data<'a> {
+    integral_intrinsic(codegen, "ctpop", args)
+}
+
+fn intrinsic_bswap<'a>(codegen: &mut CodeGen<'a>, _pos: &Position, args: Vec<Data<'a>>) -> Data<'a> {
+    integral_intrinsic(codegen, "bswap", args)
+}
+
+fn intrinsic_skeleton<'a>(mir: &mut Mir<'a>, pos: &Position, args: Vec<Type<'a>>) -> Type<'a> {
+    if args.len() != 1 {
         raise_error(
-            &format!("Expected 'std::i32', got '{}'", other.qualname()),
+            &format!("Expected 1 argument, got {}.", args.len()),
+            ErrorType::WrongArgumentCount,
+            pos,
+            &mir.session.info,
+        );
+    }
+    let tp = args.into_iter().next().unwrap();
+    if tp.signedness().is_none() {
+        raise_error(
+            &format!("Expected an integer type, got '{}'", tp.qualname()),
+            ErrorType::TypeMismatch,
+            pos,
+            &mir.session.info,
+        );
+    }
+    tp
+}
+
+//A byte swap needs at least two bytes
+fn intrinsic_skeleton_bswap<'a>(mir: &mut Mir<'a>, pos: &Position, args: Vec<Type<'a>>) -> Type<'a> {
+    let tp = intrinsic_skeleton(mir, pos, args);
+    if matches!(tp.basictype, BasicType::I8 | BasicType::U8) {
+        raise_error(
+            &format!("Cannot swap the bytes of single byte type '{}'", tp.qualname()),
             ErrorType::TypeMismatch,
             pos,
-            &mir.info,
+            &mir.session.info,
         );
     }
-    mir.builtins.get(&BasicType::Bool).unwrap().clone()
+    tp
 }
 
 pub fn init_integral(codegen: &mut CodeGen) {
     for basictype in BasicType::iter() {
+        //These have their own implementations
+        if matches!(
+            basictype,
+            BasicType::Void
+                | BasicType::Bool
+                | BasicType::Str
+                | BasicType::RawPtr
+                | BasicType::F32
+                | BasicType::F64
+        ) {
+            continue;
+        }
+
         let tp = Type {
             basictype: basictype.clone(),
             traits: HashMap::from([
                 (
                     TraitType::Add,
-                    Trait::Add {
-                        code: integral_add,
-                        skeleton: integral_skeleton_op,
+                    Trait {
+                        code: TraitCode::Operator {
+                            code: integral_add,
+                            skeleton: integral_skeleton_add,
+                        },
+                        ref_n: 0,
+                    },
+                ),
+                (
+                    TraitType::Sub,
+                    Trait {
+                        code: TraitCode::Operator {
+                            code: integral_sub,
+                            skeleton: integral_skeleton_sub,
+                        },
+                        ref_n: 0,
+                    },
+                ),
+                (
+                    TraitType::Mul,
+                    Trait {
+                        code: TraitCode::Operator {
+                            code: integral_mul,
+                            skeleton: integral_skeleton_mul,
+                        },
+                        ref_n: 0,
+                    },
+                ),
+                (
+                    TraitType::Div,
+                    Trait {
+                        code: TraitCode::Operator {
+                            code: integral_div,
+                            skeleton: integral_skeleton_div,
+                        },
+                        ref_n: 0,
+                    },
+                ),
+                (
+                    TraitType::Rem,
+                    Trait {
+                        code: TraitCode::Operator {
+                            code: integral_rem,
+                            skeleton: integral_skeleton_rem,
+                        },
                         ref_n: 0,
                     },
                 ),
                 (
                     TraitType::Eq,
-                    Trait::Eq {
-                        code: integral_eq,
-                        skeleton: integral_skeleton_cmp,
+                    Trait {
+                        code: TraitCode::Operator {
+                            code: integral_eq,
+                            skeleton: integral_skeleton_cmp,
+                        },
                         ref_n: 0,
                     },
                 ),
                 (
                     TraitType::Ne,
-                    Trait::Ne {
-                        code: integral_ne,
-                        skeleton: integral_skeleton_cmp,
+                    Trait {
+                        code: TraitCode::Operator {
+                            code: integral_ne,
+                            skeleton: integral_skeleton_cmp,
+                        },
+                        ref_n: 0,
+                    },
+                ),
+                (
+                    TraitType::Copy,
+                    Trait {
+                        code: TraitCode::Marker,
+                        ref_n: 0,
+                    },
+                ),
+                (
+                    TraitType::Default,
+                    Trait {
+                        code: TraitCode::Default(integral_default),
                         ref_n: 0,
                     },
                 ),
-                (TraitType::Copy, Trait::Copy { ref_n: 0 }),
             ]),
             qualname: format!("std::{basictype}"),
             lifetime: Lifetime::Static,
             ref_n: 0,
             usertype: None,
         };
-        codegen.builtins.insert(basictype, tp);
+        codegen.types.builtins.insert(basictype, tp);
+    }
+    for (name, code, skeleton) in [
+        ("ctlz", intrinsic_ctlz as _, intrinsic_skeleton as _),
+        ("cttz", intrinsic_cttz as _, intrinsic_skeleton as _),
+        ("popcount", intrinsic_popcount as _, intrinsic_skeleton as _),
+        ("bswap", intrinsic_bswap as _, intrinsic_skeleton_bswap as _),
+    ] {
+        codegen.std_fns.insert(
+            format!("std::intrinsics::{name}"),
+            StdFunction { code, skeleton },
+        );
     }
 }
@@ -1,12 +1,45 @@
 use crate::codegen::CodeGen;
 
+use self::args::init_args;
+use self::bool::init_bool;
+use self::env::init_env;
+use self::float::init_float;
+use self::fs::init_fs;
 use self::integral::init_integral;
+use self::printf::init_printf;
+use self::rand::init_rand;
+use self::slice::init_slice;
+use self::str::init_str;
+use self::time::init_time;
+use self::to_string::init_to_string;
 use self::void::init_void;
 
+pub mod args;
+mod bool;
+mod env;
+mod float;
+mod fs;
 mod integral;
+pub mod printf;
+mod rand;
+pub mod slice;
+pub mod str;
+mod time;
+mod to_string;
 mod void;
 
 pub fn init_builtins(codegen: &mut CodeGen) {
     init_integral(codegen);
+    init_float(codegen);
     init_void(codegen);
+    init_bool(codegen);
+    init_args(codegen);
+    init_str(codegen);
+    init_slice(codegen);
+    init_env(codegen);
+    init_fs(codegen);
+    init_time(codegen);
+    init_rand(codegen);
+    init_to_string(codegen);
+    init_printf(codegen);
 }
@@ -0,0 +1,184 @@
+use inkwell::values::BasicMetadataValueEnum;
+
+use crate::{
+    codegen::{CodeGen, Data},
+    errors::{raise_error, raise_error_multi, ErrorType},
+    mir::Mir,
+    parser::nodes::{Node, NodeType},
+    types::{BasicType, StdFunction, Type},
+    utils::Position,
+};
+
+/// Lowered to the variadic `printf`. Arguments get C's default argument promotions, and a `str`
+/// is passed as its NUL-terminated bytes.
+fn printf<'a>(codegen: &mut CodeGen<'a>, _pos: &Position, args: Vec<Data<'a>>) -> Data<'a> {
+    let i32_tp = codegen.context.i32_type();
+
+    let args = args
+        .into_iter()
+        .map(|arg| -> BasicMetadataValueEnum {
+            let value = arg.data.unwrap();
+            match arg.tp.basictype {
+                BasicType::Str => codegen
+                    .builder
+                    .build_extract_value(value.into_struct_value(), 0, "")
+                    .unwrap()
+                    .into(),
+                BasicType::I8 | BasicType::I16 => codegen
+                    .builder
+                    .build_int_s_extend(value.into_int_value(), i32_tp, "")
+                    .into(),
+                BasicType::U8 | BasicType::U16 | BasicType::Bool => codegen
+                    .builder
+                    .build_int_z_extend(value.into_int_value(), i32_tp, "")
+                    .into(),
+                BasicType::F32 => codegen
+                    .builder
+                    .build_float_ext(value.into_float_value(), codegen.context.f64_type(), "")
+                    .into(),
+                _ => value.into(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let res = codegen
+        .builder
+        .build_call(*codegen.extern_fns.get("printf").unwrap(), &args, "")
+        .try_as_basic_value()
+        .left();
+
+    Data {
+        data: res,
+        tp: codegen.types.builtins.get(&BasicType::I32).unwrap().clone(),
+    }
+}
+
+fn printf_skeleton<'a>(mir: &mut Mir<'a>, pos: &Position, args: Vec<Type<'a>>) -> Type<'a> {
+    let str_tp = mir.types.builtins.get(&BasicType::Str).unwrap().clone();
+    match args.first() {
+        None => raise_error(
+            "Expected at least 1 argument, got 0.",
+            ErrorType::WrongArgumentCount,
+            pos,
+            &mir.session.info,
+        ),
+        Some(tp) if tp != &str_tp => raise_error(
+            &format!("Expected '{}', got '{}'", str_tp.qualname(), tp.qualname()),
+            ErrorType::TypeMismatch,
+            pos,
+            &mir.session.info,
+        ),
+        Some(_) => {}
+    }
+    mir.types.builtins.get(&BasicType::I32).unwrap().clone()
+}
+
+/// The types a conversion specification accepts, given its length modifier and conversion.
+fn specifier_types(length: &str, conversion: char) -> Option<&'static [BasicType]> {
+    match (length, conversion) {
+        ("", 'd' | 'i') => Some(&[BasicType::I8, BasicType::I16, BasicType::I32, BasicType::Bool]),
+        ("l" | "ll", 'd' | 'i') => Some(&[BasicType::I64]),
+        ("", 'u' | 'x' | 'X' | 'o') => {
+            Some(&[BasicType::U8, BasicType::U16, BasicType::U32, BasicType::Bool])
+        }
+        ("l" | "ll", 'u' | 'x' | 'X' | 'o') => Some(&[BasicType::U64]),
+        //`%c` takes an int, so it accepts any integer that promotes to one
+        ("", 'c') => Some(&[
+            BasicType::I8,
+            BasicType::U8,
+            BasicType::I16,
+            BasicType::U16,
+            BasicType::I32,
+            BasicType::U32,
+        ]),
+        ("" | "l", 'f' | 'F' | 'e' | 'E' | 'g' | 'G') => Some(&[BasicType::F32, BasicType::F64]),
+        ("", 's') => Some(&[BasicType::Str]),
+        ("", 'p') => Some(&[BasicType::RawPtr]),
+        _ => None,
+    }
+}
+
+/// Checks the arguments of a `std::printf` call against its format string, if that is a literal.
+pub fn check_format(mir: &Mir, fmt: &Node, args: &[Node], tps: &[Type]) {
+    if fmt.tp != NodeType::Str {
+        return;
+    }
+    let fmt_str = fmt.data.get_data().raw.get("value").unwrap().clone();
+
+    let mut specifiers = Vec::new();
+    let mut chars = fmt_str.chars().peekable();
+    while let Some(cur) = chars.next() {
+        if cur != '%' {
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            continue;
+        }
+
+        let mut specifier = String::from("%");
+        //Flags, width and precision do not change the argument type
+        while let Some(c) = chars.next_if(|c| "-+ #0123456789.".contains(*c)) {
+            specifier.push(c);
+        }
+        let mut length = String::new();
+        while let Some(c) = chars.next_if(|c| *c == 'l') {
+            length.push(c);
+        }
+        specifier.push_str(&length);
+
+        let conversion = chars.next();
+        if let Some(conversion) = conversion {
+            specifier.push(conversion);
+        }
+        match conversion.and_then(|conversion| specifier_types(&length, conversion)) {
+            Some(accepted) => specifiers.push((specifier, accepted)),
+            None => raise_error(
+                &format!("Unsupported format specifier '{specifier}'."),
+                ErrorType::FormatMismatch,
+                &fmt.pos,
+                &mir.session.info,
+            ),
+        }
+    }
+
+    if specifiers.len() != args.len() {
+        raise_error(
+            &format!(
+                "Format string has {} specifiers, got {} arguments.",
+                specifiers.len(),
+                args.len()
+            ),
+            ErrorType::FormatMismatch,
+            &fmt.pos,
+            &mir.session.info,
+        );
+    }
+
+    for ((specifier, accepted), (arg, tp)) in specifiers.iter().zip(args.iter().zip(tps)) {
+        if tp.ref_n != 0 || !accepted.contains(&tp.basictype) {
+            raise_error_multi(
+                vec![
+                    format!(
+                        "Format specifier '{specifier}' does not accept '{}'.",
+                        tp.qualname()
+                    ),
+                    "Format string here:".into(),
+                ],
+                ErrorType::FormatMismatch,
+                vec![Some(&arg.pos), Some(&fmt.pos)],
+                &mir.session.info,
+            );
+        }
+    }
+}
+
+pub fn init_printf(codegen: &mut CodeGen) {
+    codegen.std_fns.insert(
+        String::from("std::printf"),
+        StdFunction {
+            code: printf,
+            skeleton: printf_skeleton,
+        },
+    );
+}
@@ -0,0 +1,142 @@
+use inkwell::{module::Linkage, values::FunctionValue, AddressSpace, IntPredicate};
+
+use crate::{
+    codegen::{CodeGen, Data},
+    errors::{raise_error, ErrorType},
+    mir::Mir,
+    types::{BasicType, StdFunction, Type},
+    utils::{build_rt_check, Position},
+};
+
+const RAND_U64: &str = "kestrel_rand_u64";
+
+/// The runtime shim `i1 kestrel_rand_u64(i64*)`, which fills its argument from the OS with
+/// `getrandom`, `getentropy` on macOS or two calls to `rand_s` on Windows, and returns whether
+/// that succeeded.
+fn rand_u64_shim<'a>(codegen: &mut CodeGen<'a>) -> FunctionValue<'a> {
+    if let Some(function) = codegen.module.get_function(RAND_U64) {
+        return function;
+    }
+
+    let bool_tp = codegen.context.bool_type();
+    let i32_tp = codegen.context.i32_type();
+    let i64_tp = codegen.context.i64_type();
+    let fn_tp = bool_tp.fn_type(&[i64_tp.ptr_type(AddressSpace::from(0)).into()], false);
+    let function = codegen
+        .module
+        .add_function(RAND_U64, fn_tp, Some(Linkage::Private));
+
+    let builder = codegen.context.create_builder();
+    builder.position_at_end(codegen.context.append_basic_block(function, ""));
+    let out = function.get_first_param().unwrap().into_pointer_value();
+
+    let ok = if let Some(rand_s) = codegen.extern_fns.get("rand_s") {
+        //rand_s gives 32 bits at a time and returns 0 on success
+        let halves = builder.build_pointer_cast(out, i32_tp.ptr_type(AddressSpace::from(0)), "");
+        let mut ok = bool_tp.const_int(1, false);
+        for index in 0..2 {
+            let half = unsafe {
+                builder.build_in_bounds_gep(halves, &[i32_tp.const_int(index, false)], "")
+            };
+            let res = builder
+                .build_call(*rand_s, &[half.into()], "")
+                .try_as_basic_value()
+                .left()
+                .unwrap()
+                .into_int_value();
+            let res = builder.build_int_compare(IntPredicate::EQ, res, i32_tp.const_zero(), "");
+            ok = builder.build_and(ok, res, "");
+        }
+        ok
+    } else if let Some(getentropy) = codegen.extern_fns.get("getentropy") {
+        //macOS has no getrandom, and getentropy returns 0 on success
+        let buf = builder.build_pointer_cast(
+            out,
+            codegen.context.i8_type().ptr_type(AddressSpace::from(0)),
+            "",
+        );
+        let res = builder
+            .build_call(
+                *getentropy,
+                &[buf.into(), i64_tp.const_int(8, false).into()],
+                "",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+        builder.build_int_compare(IntPredicate::EQ, res, i32_tp.const_zero(), "")
+    } else {
+        let buf = builder.build_pointer_cast(
+            out,
+            codegen.context.i8_type().ptr_type(AddressSpace::from(0)),
+            "",
+        );
+        let read = builder
+            .build_call(
+                *codegen.extern_fns.get("getrandom").unwrap(),
+                &[
+                    buf.into(),
+                    i64_tp.const_int(8, false).into(),
+                    i32_tp.const_zero().into(),
+                ],
+                "",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+        builder.build_int_compare(IntPredicate::EQ, read, i64_tp.const_int(8, false), "")
+    };
+    builder.build_return(Some(&ok));
+
+    function
+}
+
+/// A random `u64` from the OS.
+fn rand_u64<'a>(codegen: &mut CodeGen<'a>, pos: &Position, _args: Vec<Data<'a>>) -> Data<'a> {
+    let shim = rand_u64_shim(codegen);
+    let out = codegen.build_entry_alloca(codegen.context.i64_type());
+
+    let ok = codegen
+        .builder
+        .build_call(shim, &[out.into()], "")
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value();
+    let failed = codegen.builder.build_not(ok, "");
+    build_rt_check(
+        codegen,
+        failed,
+        "std::rand::u64 could not get random bytes from the OS!",
+        pos,
+    );
+
+    Data {
+        data: Some(codegen.builder.build_load(out, "")),
+        tp: codegen.types.builtins.get(&BasicType::U64).unwrap().clone(),
+    }
+}
+
+fn rand_u64_skeleton<'a>(mir: &mut Mir<'a>, pos: &Position, args: Vec<Type<'a>>) -> Type<'a> {
+    if !args.is_empty() {
+        raise_error(
+            &format!("Expected 0 arguments, got {}.", args.len()),
+            ErrorType::WrongArgumentCount,
+            pos,
+            &mir.session.info,
+        );
+    }
+    mir.types.builtins.get(&BasicType::U64).unwrap().clone()
+}
+
+pub fn init_rand(codegen: &mut CodeGen) {
+    codegen.std_fns.insert(
+        String::from("std::rand::u64"),
+        StdFunction {
+            code: rand_u64,
+            skeleton: rand_u64_skeleton,
+        },
+    );
+}
@@ -0,0 +1,126 @@
+use inkwell::{
+    context::Context,
+    types::{BasicType as InkwellBasicType, BasicTypeEnum, StructType},
+    values::{IntValue, PointerValue},
+    AddressSpace, IntPredicate,
+};
+
+use crate::{
+    codegen::{CodeGen, Data},
+    errors::{raise_error, ErrorType},
+    mir::Mir,
+    types::{BasicType, StdFunction, Type},
+    utils::{build_rt_check, Position},
+};
+
+use super::str::index_value;
+
+/// A slice is a pointer to its first element and the number of elements.
+pub fn slice_type<'a>(context: &'a Context, element: BasicTypeEnum<'a>) -> StructType<'a> {
+    context.struct_type(
+        &[
+            element.ptr_type(AddressSpace::from(0u16)).into(),
+            context.i64_type().into(),
+        ],
+        false,
+    )
+}
+
+pub fn build_slice<'a>(
+    codegen: &mut CodeGen<'a>,
+    ptr: PointerValue<'a>,
+    len: IntValue<'a>,
+    tp: Type<'a>,
+) -> Data<'a> {
+    let element = BasicTypeEnum::try_from(ptr.get_type().get_element_type()).unwrap();
+    let mut res = slice_type(codegen.context, element).get_undef();
+    res = codegen
+        .builder
+        .build_insert_value(res, ptr, 0, "")
+        .unwrap()
+        .into_struct_value();
+    res = codegen
+        .builder
+        .build_insert_value(res, len, 1, "")
+        .unwrap()
+        .into_struct_value();
+
+    Data {
+        data: Some(res.into()),
+        tp,
+    }
+}
+
+/// The element at an index, which must be less than the length.
+fn at<'a>(codegen: &mut CodeGen<'a>, pos: &Position, args: Vec<Data<'a>>) -> Data<'a> {
+    let this = args[0].data.unwrap().into_struct_value();
+    let ptr = codegen
+        .builder
+        .build_extract_value(this, 0, "")
+        .unwrap()
+        .into_pointer_value();
+    let len = codegen
+        .builder
+        .build_extract_value(this, 1, "")
+        .unwrap()
+        .into_int_value();
+    let index = index_value(codegen, &args[1]);
+
+    let out_of_bounds = codegen
+        .builder
+        .build_int_compare(IntPredicate::UGE, index, len, "");
+    build_rt_check(codegen, out_of_bounds, "std::at index out of bounds!", pos);
+
+    let element_ptr = unsafe { codegen.builder.build_in_bounds_gep(ptr, &[index], "") };
+    let res = codegen.builder.build_load(element_ptr, "");
+
+    Data {
+        data: Some(res),
+        tp: args[0].tp.element().unwrap().clone(),
+    }
+}
+
+fn at_skeleton<'a>(mir: &mut Mir<'a>, pos: &Position, args: Vec<Type<'a>>) -> Type<'a> {
+    if args.len() != 2 {
+        raise_error(
+            &format!("Expected 2 arguments, got {}.", args.len()),
+            ErrorType::WrongArgumentCount,
+            pos,
+            &mir.session.info,
+        );
+    }
+    let Some(element) = args[0].element() else {
+        raise_error(
+            &format!("Expected a slice, got '{}'", args[0].qualname()),
+            ErrorType::TypeMismatch,
+            pos,
+            &mir.session.info,
+        );
+    };
+    let tp = &args[1];
+    if tp.ref_n != 0
+        || tp.signedness().is_none()
+        || matches!(tp.basictype, BasicType::I128 | BasicType::U128)
+    {
+        raise_error(
+            &format!(
+                "Expected an integer of at most 64 bits as the index, got '{}'",
+                tp.qualname()
+            ),
+            ErrorType::TypeMismatch,
+            pos,
+            &mir.session.info,
+        );
+    }
+    element.clone()
+}
+
+pub fn init_slice(codegen: &mut CodeGen) {
+    codegen.std_fns.insert(
+        String::from("std::at"),
+        StdFunction {
+            code: at,
+            skeleton: at_skeleton,
+        },
+    );
+}
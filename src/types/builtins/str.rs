@@ -0,0 +1,485 @@
+use std::collections::HashMap;
+
+use inkwell::{
+    context::Context,
+    module::Linkage,
+    types::StructType,
+    values::{FunctionValue, IntValue, PointerValue},
+    AddressSpace, IntPredicate,
+};
+
+use crate::{
+    codegen::{CodeGen, Data},
+    errors::{raise_error, ErrorType},
+    mir::Mir,
+    types::{BasicType, Lifetime, Signedness, StdFunction, Trait, TraitCode, TraitType, Type},
+    utils::{build_rt_check, global_string, Position},
+};
+
+/// A `str` is a pointer to NUL-terminated bytes and their length (excluding the NUL).
+pub fn str_type<'a>(context: &'a Context) -> StructType<'a> {
+    context.struct_type(
+        &[
+            context.i8_type().ptr_type(AddressSpace::from(0u16)).into(),
+            context.i64_type().into(),
+        ],
+        false,
+    )
+}
+
+pub fn build_str<'a>(
+    codegen: &mut CodeGen<'a>,
+    ptr: PointerValue<'a>,
+    len: IntValue<'a>,
+) -> Data<'a> {
+    let mut res = str_type(codegen.context).get_undef();
+    res = codegen
+        .builder
+        .build_insert_value(res, ptr, 0, "")
+        .unwrap()
+        .into_struct_value();
+    res = codegen
+        .builder
+        .build_insert_value(res, len, 1, "")
+        .unwrap()
+        .into_struct_value();
+
+    Data {
+        data: Some(res.into()),
+        tp: codegen.types.builtins.get(&BasicType::Str).unwrap().clone(),
+    }
+}
+
+/// Strings are equal if they have the same length and bytes. The length is checked first,
+/// so `memcmp` never reads past the end of the shorter string.
+fn str_eq<'a>(codegen: &mut CodeGen<'a>, _pos: &Position, this: Data<'a>, other: Data<'a>) -> Data<'a> {
+    let this = this.data.unwrap().into_struct_value();
+    let other = other.data.unwrap().into_struct_value();
+
+    let this_len = codegen
+        .builder
+        .build_extract_value(this, 1, "")
+        .unwrap()
+        .into_int_value();
+    let other_len = codegen
+        .builder
+        .build_extract_value(other, 1, "")
+        .unwrap()
+        .into_int_value();
+    let len_eq = codegen
+        .builder
+        .build_int_compare(IntPredicate::EQ, this_len, other_len, "");
+
+    let n = codegen
+        .builder
+        .build_select(len_eq, this_len, codegen.context.i64_type().const_zero(), "");
+    let cmp = codegen
+        .builder
+        .build_call(
+            *codegen.extern_fns.get("memcmp").unwrap(),
+            &[
+                codegen.builder.build_extract_value(this, 0, "").unwrap().into(),
+                codegen.builder.build_extract_value(other, 0, "").unwrap().into(),
+                n.into(),
+            ],
+            "",
+        )
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value();
+    let bytes_eq = codegen.builder.build_int_compare(
+        IntPredicate::EQ,
+        cmp,
+        codegen.context.i32_type().const_zero(),
+        "",
+    );
+
+    Data {
+        data: Some(codegen.builder.build_and(len_eq, bytes_eq, "").into()),
+        tp: codegen.types.builtins.get(&BasicType::Bool).unwrap().clone(),
+    }
+}
+
+fn str_ne<'a>(codegen: &mut CodeGen<'a>, pos: &Position, this: Data<'a>, other: Data<'a>) -> Data<'a> {
+    let eq = str_eq(codegen, pos, this, other);
+
+    Data {
+        data: Some(
+            codegen
+                .builder
+                .build_not(eq.data.unwrap().into_int_value(), "")
+                .into(),
+        ),
+        tp: eq.tp,
+    }
+}
+
+fn str_skeleton_cmp<'a>(
+    mir: &mut Mir<'a>,
+    pos: &Position,
+    this: Type<'a>,
+    other: Type<'a>,
+) -> Type<'a> {
+    if this != other {
+        raise_error(
+            &format!("Expected 'std::str', got '{}'", other.qualname()),
+            ErrorType::TypeMismatch,
+            pos,
+            &mir.session.info,
+        );
+    }
+    mir.types.builtins.get(&BasicType::Bool).unwrap().clone()
+}
+
+/// The 64-bit FNV-1a hash of the bytes of a `str`, as a function built on first use.
+fn str_hash_fn<'a>(codegen: &mut CodeGen<'a>) -> FunctionValue<'a> {
+    const NAME: &str = "__kestrel_str_hash";
+    if let Some(function) = codegen.module.get_function(NAME) {
+        return function;
+    }
+
+    let context = codegen.context;
+    let i64_tp = context.i64_type();
+    let function = codegen.module.add_function(
+        NAME,
+        i64_tp.fn_type(&[str_type(context).into()], false),
+        Some(Linkage::Private),
+    );
+
+    let entry = context.append_basic_block(function, "");
+    let cond = context.append_basic_block(function, "");
+    let body = context.append_basic_block(function, "");
+    let done = context.append_basic_block(function, "");
+
+    let builder = context.create_builder();
+
+    builder.position_at_end(entry);
+    let str = function.get_first_param().unwrap().into_struct_value();
+    let ptr = builder
+        .build_extract_value(str, 0, "")
+        .unwrap()
+        .into_pointer_value();
+    let len = builder
+        .build_extract_value(str, 1, "")
+        .unwrap()
+        .into_int_value();
+    builder.build_unconditional_branch(cond);
+
+    builder.position_at_end(cond);
+    let i = builder.build_phi(i64_tp, "");
+    let hash = builder.build_phi(i64_tp, "");
+    let finished = builder.build_int_compare(
+        IntPredicate::EQ,
+        i.as_basic_value().into_int_value(),
+        len,
+        "",
+    );
+    builder.build_conditional_branch(finished, done, body);
+
+    builder.position_at_end(body);
+    let byte_ptr = unsafe { builder.build_gep(ptr, &[i.as_basic_value().into_int_value()], "") };
+    let byte = builder.build_int_z_extend(
+        builder.build_load(byte_ptr, "").into_int_value(),
+        i64_tp,
+        "",
+    );
+    let mixed = builder.build_xor(hash.as_basic_value().into_int_value(), byte, "");
+    let next_hash = builder.build_int_mul(mixed, i64_tp.const_int(0x100000001b3, false), "");
+    let next_i = builder.build_int_add(
+        i.as_basic_value().into_int_value(),
+        i64_tp.const_int(1, false),
+        "",
+    );
+    builder.build_unconditional_branch(cond);
+
+    i.add_incoming(&[(&i64_tp.const_zero(), entry), (&next_i, body)]);
+    hash.add_incoming(&[
+        (&i64_tp.const_int(0xcbf29ce484222325, false), entry),
+        (&next_hash, body),
+    ]);
+
+    builder.position_at_end(done);
+    builder.build_return(Some(&hash.as_basic_value()));
+
+    function
+}
+
+fn hash<'a>(codegen: &mut CodeGen<'a>, _pos: &Position, args: Vec<Data<'a>>) -> Data<'a> {
+    let function = str_hash_fn(codegen);
+    let res = codegen
+        .builder
+        .build_call(function, &[args.first().unwrap().data.unwrap().into()], "")
+        .try_as_basic_value()
+        .left();
+
+    Data {
+        data: res,
+        tp: codegen.types.builtins.get(&BasicType::U64).unwrap().clone(),
+    }
+}
+
+fn hash_skeleton<'a>(mir: &mut Mir<'a>, pos: &Position, args: Vec<Type<'a>>) -> Type<'a> {
+    if args.len() != 1 {
+        raise_error(
+            &format!("Expected 1 argument, got {}.", args.len()),
+            ErrorType::WrongArgumentCount,
+            pos,
+            &mir.session.info,
+        );
+    }
+    let str_tp = mir.types.builtins.get(&BasicType::Str).unwrap().clone();
+    if args.first().unwrap() != &str_tp {
+        raise_error(
+            &format!(
+                "Expected '{}', got '{}'",
+                str_tp.qualname(),
+                args.first().unwrap().qualname()
+            ),
+            ErrorType::TypeMismatch,
+            pos,
+            &mir.session.info,
+        );
+    }
+    mir.types.builtins.get(&BasicType::U64).unwrap().clone()
+}
+
+/// The length in bytes, excluding the NUL.
+fn len<'a>(codegen: &mut CodeGen<'a>, _pos: &Position, args: Vec<Data<'a>>) -> Data<'a> {
+    let res = codegen
+        .builder
+        .build_extract_value(args.first().unwrap().data.unwrap().into_struct_value(), 1, "")
+        .unwrap();
+
+    Data {
+        data: Some(res),
+        tp: codegen.types.builtins.get(&BasicType::U64).unwrap().clone(),
+    }
+}
+
+/// An index as an `i64`. A negative index becomes larger than any length, so it fails the
+/// unsigned bounds check.
+pub fn index_value<'a>(codegen: &mut CodeGen<'a>, index: &Data<'a>) -> IntValue<'a> {
+    let value = index.data.unwrap().into_int_value();
+    let i64_tp = codegen.context.i64_type();
+    match index.tp.signedness() {
+        Some(Signedness::Signed) => codegen
+            .builder
+            .build_int_s_extend_or_bit_cast(value, i64_tp, ""),
+        _ => codegen
+            .builder
+            .build_int_z_extend_or_bit_cast(value, i64_tp, ""),
+    }
+}
+
+/// The byte at an index, which must be less than the length.
+fn byte_at<'a>(codegen: &mut CodeGen<'a>, pos: &Position, args: Vec<Data<'a>>) -> Data<'a> {
+    let this = args[0].data.unwrap().into_struct_value();
+    let ptr = codegen
+        .builder
+        .build_extract_value(this, 0, "")
+        .unwrap()
+        .into_pointer_value();
+    let len = codegen
+        .builder
+        .build_extract_value(this, 1, "")
+        .unwrap()
+        .into_int_value();
+    let index = index_value(codegen, &args[1]);
+
+    let out_of_bounds = codegen
+        .builder
+        .build_int_compare(IntPredicate::UGE, index, len, "");
+    build_rt_check(codegen, out_of_bounds, "std::str index out of bounds!", pos);
+
+    let byte_ptr = unsafe { codegen.builder.build_in_bounds_gep(ptr, &[index], "") };
+    let res = codegen.builder.build_load(byte_ptr, "");
+
+    Data {
+        data: Some(res),
+        tp: codegen.types.builtins.get(&BasicType::U8).unwrap().clone(),
+    }
+}
+
+/// The bytes from `start` up to `end`, with `start <= end <= len`. They are copied into a buffer
+/// from `malloc` so the result stays NUL-terminated, which is never freed like `std::to_string`.
+fn slice<'a>(codegen: &mut CodeGen<'a>, pos: &Position, args: Vec<Data<'a>>) -> Data<'a> {
+    let this = args[0].data.unwrap().into_struct_value();
+    let ptr = codegen
+        .builder
+        .build_extract_value(this, 0, "")
+        .unwrap()
+        .into_pointer_value();
+    let len = codegen
+        .builder
+        .build_extract_value(this, 1, "")
+        .unwrap()
+        .into_int_value();
+    let start = index_value(codegen, &args[1]);
+    let end = index_value(codegen, &args[2]);
+
+    let reversed = codegen
+        .builder
+        .build_int_compare(IntPredicate::UGT, start, end, "");
+    let past_end = codegen
+        .builder
+        .build_int_compare(IntPredicate::UGT, end, len, "");
+    let out_of_bounds = codegen.builder.build_or(reversed, past_end, "");
+    build_rt_check(codegen, out_of_bounds, "std::str slice out of bounds!", pos);
+
+    let i64_tp = codegen.context.i64_type();
+    let new_len = codegen.builder.build_int_sub(end, start, "");
+    let size = codegen
+        .builder
+        .build_int_add(new_len, i64_tp.const_int(1, false), "");
+    let buffer = codegen
+        .builder
+        .build_call(*codegen.extern_fns.get("malloc").unwrap(), &[size.into()], "")
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_pointer_value();
+
+    let src = unsafe { codegen.builder.build_in_bounds_gep(ptr, &[start], "") };
+    codegen
+        .builder
+        .build_memcpy(buffer, 1, src, 1, new_len)
+        .unwrap();
+    let nul = unsafe { codegen.builder.build_in_bounds_gep(buffer, &[new_len], "") };
+    codegen
+        .builder
+        .build_store(nul, codegen.context.i8_type().const_zero());
+
+    build_str(codegen, buffer, new_len)
+}
+
+/// A `str` followed by `indices` integer arguments of at most 64 bits.
+fn str_index_skeleton<'a>(
+    mir: &mut Mir<'a>,
+    pos: &Position,
+    args: &[Type<'a>],
+    indices: usize,
+) {
+    if args.len() != indices + 1 {
+        raise_error(
+            &format!("Expected {} arguments, got {}.", indices + 1, args.len()),
+            ErrorType::WrongArgumentCount,
+            pos,
+            &mir.session.info,
+        );
+    }
+    let str_tp = mir.types.builtins.get(&BasicType::Str).unwrap().clone();
+    if args[0] != str_tp {
+        raise_error(
+            &format!("Expected '{}', got '{}'", str_tp.qualname(), args[0].qualname()),
+            ErrorType::TypeMismatch,
+            pos,
+            &mir.session.info,
+        );
+    }
+    for tp in &args[1..] {
+        if tp.ref_n != 0
+            || tp.signedness().is_none()
+            || matches!(tp.basictype, BasicType::I128 | BasicType::U128)
+        {
+            raise_error(
+                &format!(
+                    "Expected an integer of at most 64 bits as the index, got '{}'",
+                    tp.qualname()
+                ),
+                ErrorType::TypeMismatch,
+                pos,
+                &mir.session.info,
+            );
+        }
+    }
+}
+
+fn len_skeleton<'a>(mir: &mut Mir<'a>, pos: &Position, args: Vec<Type<'a>>) -> Type<'a> {
+    //A slice keeps its length where a `str` does, so `len` works on both
+    if !(args.len() == 1 && args[0].element().is_some()) {
+        str_index_skeleton(mir, pos, &args, 0);
+    }
+    mir.types.builtins.get(&BasicType::U64).unwrap().clone()
+}
+
+fn byte_at_skeleton<'a>(mir: &mut Mir<'a>, pos: &Position, args: Vec<Type<'a>>) -> Type<'a> {
+    str_index_skeleton(mir, pos, &args, 1);
+    mir.types.builtins.get(&BasicType::U8).unwrap().clone()
+}
+
+fn slice_skeleton<'a>(mir: &mut Mir<'a>, pos: &Position, args: Vec<Type<'a>>) -> Type<'a> {
+    str_index_skeleton(mir, pos, &args, 2);
+    mir.types.builtins.get(&BasicType::Str).unwrap().clone()
+}
+
+/// The empty string.
+fn str_default<'a>(codegen: &mut CodeGen<'a>, _pos: &Position, _tp: Type<'a>) -> Data<'a> {
+    let ptr = global_string(codegen, "");
+    let len = codegen.context.i64_type().const_zero();
+    build_str(codegen, ptr, len)
+}
+
+pub fn init_str(codegen: &mut CodeGen) {
+    let tp = Type {
+        basictype: BasicType::Str,
+        traits: HashMap::from([
+            (
+                TraitType::Eq,
+                Trait {
+                    code: TraitCode::Operator {
+                        code: str_eq,
+                        skeleton: str_skeleton_cmp,
+                    },
+                    ref_n: 0,
+                },
+            ),
+            (
+                TraitType::Ne,
+                Trait {
+                    code: TraitCode::Operator {
+                        code: str_ne,
+                        skeleton: str_skeleton_cmp,
+                    },
+                    ref_n: 0,
+                },
+            ),
+            (
+                TraitType::Copy,
+                Trait {
+                    code: TraitCode::Marker,
+                    ref_n: 0,
+                },
+            ),
+            (
+                TraitType::Default,
+                Trait {
+                    code: TraitCode::Default(str_default),
+                    ref_n: 0,
+                },
+            ),
+        ]),
+        qualname: "std::str".into(),
+        lifetime: Lifetime::Static,
+        ref_n: 0,
+        usertype: None,
+    };
+    codegen.types.builtins.insert(BasicType::Str, tp);
+
+    codegen.std_fns.insert(
+        String::from("std::hash"),
+        StdFunction {
+            code: hash,
+            skeleton: hash_skeleton,
+        },
+    );
+    for (name, code, skeleton) in [
+        ("len", len as _, len_skeleton as _),
+        ("byte_at", byte_at as _, byte_at_skeleton as _),
+        ("slice", slice as _, slice_skeleton as _),
+    ] {
+        codegen
+            .std_fns
+            .insert(format!("std::{name}"), StdFunction { code, skeleton });
+    }
+}
@@ -0,0 +1,104 @@
+use inkwell::AddressSpace;
+
+use crate::{
+    codegen::{CodeGen, Data},
+    errors::{raise_error, ErrorType},
+    mir::Mir,
+    types::{BasicType, StdFunction, Type},
+    utils::Position,
+};
+
+const CLOCK_REALTIME: u64 = 0;
+
+/// 100ns intervals between the `FILETIME` epoch, 1601-01-01, and the Unix epoch.
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+
+/// Milliseconds since the Unix epoch, from `clock_gettime` or, on Windows,
+/// `GetSystemTimeAsFileTime`.
+fn now_millis<'a>(codegen: &mut CodeGen<'a>, _pos: &Position, _args: Vec<Data<'a>>) -> Data<'a> {
+    let i64_tp = codegen.context.i64_type();
+
+    let millis = if let Some(getsystemtime) = codegen.extern_fns.get("GetSystemTimeAsFileTime") {
+        let filetime = codegen.build_entry_alloca(i64_tp);
+        codegen
+            .builder
+            .build_call(*getsystemtime, &[filetime.into()], "");
+        let ticks = codegen
+            .builder
+            .build_load(filetime, "")
+            .into_int_value();
+        let ticks = codegen.builder.build_int_sub(
+            ticks,
+            i64_tp.const_int(FILETIME_UNIX_EPOCH, false),
+            "",
+        );
+        codegen
+            .builder
+            .build_int_unsigned_div(ticks, i64_tp.const_int(10_000, false), "")
+    } else {
+        //struct timespec is two 64-bit fields, tv_sec and tv_nsec
+        let timespec = codegen.build_entry_alloca(i64_tp.array_type(2));
+        codegen.builder.build_call(
+            *codegen.extern_fns.get("clock_gettime").unwrap(),
+            &[
+                codegen
+                    .context
+                    .i32_type()
+                    .const_int(CLOCK_REALTIME, false)
+                    .into(),
+                codegen
+                    .builder
+                    .build_pointer_cast(timespec, i64_tp.ptr_type(AddressSpace::from(0)), "")
+                    .into(),
+            ],
+            "",
+        );
+        let field = |codegen: &mut CodeGen<'a>, index| {
+            let ptr = unsafe {
+                codegen.builder.build_in_bounds_gep(
+                    timespec,
+                    &[i64_tp.const_zero(), i64_tp.const_int(index, false)],
+                    "",
+                )
+            };
+            codegen.builder.build_load(ptr, "").into_int_value()
+        };
+        let secs = field(codegen, 0);
+        let nsecs = field(codegen, 1);
+
+        let secs = codegen
+            .builder
+            .build_int_mul(secs, i64_tp.const_int(1_000, false), "");
+        let nsecs = codegen
+            .builder
+            .build_int_unsigned_div(nsecs, i64_tp.const_int(1_000_000, false), "");
+        codegen.builder.build_int_add(secs, nsecs, "")
+    };
+
+    Data {
+        data: Some(millis.into()),
+        tp: codegen.types.builtins.get(&BasicType::U64).unwrap().clone(),
+    }
+}
+
+fn now_millis_skeleton<'a>(mir: &mut Mir<'a>, pos: &Position, args: Vec<Type<'a>>) -> Type<'a> {
+    if !args.is_empty() {
+        raise_error(
+            &format!("Expected 0 arguments, got {}.", args.len()),
+            ErrorType::WrongArgumentCount,
+            pos,
+            &mir.session.info,
+        );
+    }
+    mir.types.builtins.get(&BasicType::U64).unwrap().clone()
+}
+
+pub fn init_time(codegen: &mut CodeGen) {
+    codegen.std_fns.insert(
+        String::from("std::time::now_millis"),
+        StdFunction {
+            code: now_millis,
+            skeleton: now_millis_skeleton,
+        },
+    );
+}
@@ -0,0 +1,122 @@
+use crate::{
+    codegen::{CodeGen, Data},
+    errors::{raise_error, ErrorType},
+    mir::Mir,
+    types::{BasicType, Signedness, StdFunction, Type},
+    utils::{global_string, Position},
+};
+
+use super::str::build_str;
+
+/// Enough for any 64-bit integer in decimal, with its sign and the NUL.
+const BUFFER_LEN: u64 = 21;
+
+/// Integers are formatted by `snprintf` into a buffer from `malloc`, which is never freed since a
+/// `str` does not own its bytes. Bools are the constants `true` and `false`.
+fn to_string<'a>(codegen: &mut CodeGen<'a>, _pos: &Position, args: Vec<Data<'a>>) -> Data<'a> {
+    let arg = args.first().unwrap();
+    let value = arg.data.unwrap().into_int_value();
+    let i64_tp = codegen.context.i64_type();
+
+    let signedness = match arg.tp.basictype.signedness() {
+        Some(signedness) => signedness,
+        None => {
+            let true_ptr = global_string(codegen, "true");
+            let false_ptr = global_string(codegen, "false");
+            let ptr = codegen
+                .builder
+                .build_select(value, true_ptr, false_ptr, "")
+                .into_pointer_value();
+            let len = codegen.builder.build_select(
+                value,
+                i64_tp.const_int(4, false),
+                i64_tp.const_int(5, false),
+                "",
+            );
+            return build_str(codegen, ptr, len.into_int_value());
+        }
+    };
+
+    //snprintf takes at least an int, so every integer is passed as 64 bits
+    let (fmt, value) = match signedness {
+        Signedness::Signed => (
+            "%lld",
+            codegen.builder.build_int_s_extend_or_bit_cast(value, i64_tp, ""),
+        ),
+        Signedness::Unsigned => (
+            "%llu",
+            codegen.builder.build_int_z_extend_or_bit_cast(value, i64_tp, ""),
+        ),
+    };
+    let fmt = global_string(codegen, fmt);
+
+    let buffer = codegen
+        .builder
+        .build_call(
+            *codegen.extern_fns.get("malloc").unwrap(),
+            &[i64_tp.const_int(BUFFER_LEN, false).into()],
+            "",
+        )
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_pointer_value();
+
+    let len = codegen
+        .builder
+        .build_call(
+            *codegen.extern_fns.get("snprintf").unwrap(),
+            &[
+                buffer.into(),
+                i64_tp.const_int(BUFFER_LEN, false).into(),
+                fmt.into(),
+                value.into(),
+            ],
+            "",
+        )
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value();
+    let len = codegen.builder.build_int_z_extend(len, i64_tp, "");
+
+    build_str(codegen, buffer, len)
+}
+
+fn to_string_skeleton<'a>(mir: &mut Mir<'a>, pos: &Position, args: Vec<Type<'a>>) -> Type<'a> {
+    if args.len() != 1 {
+        raise_error(
+            &format!("Expected 1 argument, got {}.", args.len()),
+            ErrorType::WrongArgumentCount,
+            pos,
+            &mir.session.info,
+        );
+    }
+    let tp = args.first().unwrap();
+    let convertible = match &tp.basictype {
+        BasicType::I128 | BasicType::U128 => false,
+        basictype => *basictype == BasicType::Bool || basictype.signedness().is_some(),
+    };
+    if tp.ref_n != 0 || !convertible {
+        raise_error(
+            &format!(
+                "Expected an integer of at most 64 bits or 'std::bool', got '{}'",
+                tp.qualname()
+            ),
+            ErrorType::TypeMismatch,
+            pos,
+            &mir.session.info,
+        );
+    }
+    mir.types.builtins.get(&BasicType::Str).unwrap().clone()
+}
+
+pub fn init_to_string(codegen: &mut CodeGen) {
+    codegen.std_fns.insert(
+        String::from("std::to_string"),
+        StdFunction {
+            code: to_string,
+            skeleton: to_string_skeleton,
+        },
+    );
+}
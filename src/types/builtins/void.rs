@@ -2,17 +2,23 @@ use std::collections::HashMap;
 
 use crate::{
     codegen::CodeGen,
-    types::{BasicType, Lifetime, Trait, TraitType, Type},
+    types::{BasicType, Lifetime, Trait, TraitCode, TraitType, Type},
 };
 
 pub fn init_void(codegen: &mut CodeGen) {
     let tp = Type {
         basictype: BasicType::Void,
-        traits: HashMap::from([(TraitType::Copy, Trait::Copy { ref_n: 0 })]),
+        traits: HashMap::from([(
+            TraitType::Copy,
+            Trait {
+                code: TraitCode::Marker,
+                ref_n: 0,
+            },
+        )]),
         qualname: "std::void".into(),
         lifetime: Lifetime::Static,
         ref_n: 0,
         usertype: None,
     };
-    codegen.builtins.insert(BasicType::Void, tp);
+    codegen.types.builtins.insert(BasicType::Void, tp);
 }
@@ -1,70 +1,96 @@
 use std::{collections::HashMap, fmt::Display};
 
+use indexmap::IndexMap;
 use inkwell::{AddressSpace, types::StructType};
-use strum::EnumIter;
+use strum::{EnumIter, IntoEnumIterator};
 
 use crate::{
-    codegen::{CodeGen, Data},
+    codegen::{is_darwin, is_msvc, CodeGen, Data},
     mir::Mir,
+    parser::nodes::OpType,
     utils::Position,
 };
 
 pub mod builtins;
+pub mod registry;
 
 pub type BuiltinTypes<'a> = HashMap<BasicType, Type<'a>>;
 pub type Traits<'a> = HashMap<TraitType, Trait<'a>>;
+pub type StdFunctions<'a> = HashMap<String, StdFunction<'a>>;
 
 pub fn implements_trait(tp: &Type<'_>, trait_tp: TraitType) -> bool {
-    let trait_opt = tp.traits.get(&trait_tp);
-    trait_opt.is_some()
-        && match trait_opt.unwrap() {
-            Trait::Add {
-                code: _,
-                skeleton: _,
-                ref_n,
-            } => tp.ref_n == *ref_n,
-            Trait::Eq {
-                code: _,
-                skeleton: _,
-                ref_n,
-            } => tp.ref_n == *ref_n,
-            Trait::Ne {
-                code: _,
-                skeleton: _,
-                ref_n,
-            } => tp.ref_n == *ref_n,
-            Trait::Copy { ref_n } => tp.ref_n == *ref_n,
-        }
+    tp.traits
+        .get(&trait_tp)
+        .is_some_and(|imp| tp.ref_n == imp.ref_n)
+}
+
+/// The trait implementing each binary operator, and the name used in errors.
+pub fn operator_trait(op: &OpType) -> (TraitType, &'static str) {
+    match op {
+        OpType::Add => (TraitType::Add, "Add"),
+        OpType::Sub => (TraitType::Sub, "Sub"),
+        OpType::Mul => (TraitType::Mul, "Mul"),
+        OpType::Div => (TraitType::Div, "Div"),
+        OpType::Rem => (TraitType::Rem, "Rem"),
+        OpType::Eq => (TraitType::Eq, "Eq"),
+        OpType::Ne => (TraitType::Ne, "Ne"),
+    }
+}
+
+/// The builtin trait a `Type::method` path calls, by the method's name.
+pub fn method_trait(method: &str) -> Option<(TraitType, &'static str)> {
+    match method {
+        "add" => Some((TraitType::Add, "Add")),
+        "sub" => Some((TraitType::Sub, "Sub")),
+        "mul" => Some((TraitType::Mul, "Mul")),
+        "div" => Some((TraitType::Div, "Div")),
+        "rem" => Some((TraitType::Rem, "Rem")),
+        "eq" => Some((TraitType::Eq, "Eq")),
+        "ne" => Some((TraitType::Ne, "Ne")),
+        "default" => Some((TraitType::Default, "Default")),
+        _ => None,
+    }
+}
+
+pub type OperatorCode<'a> = fn(&mut CodeGen<'a>, &Position, Data<'a>, Data<'a>) -> Data<'a>;
+pub type OperatorSkeleton<'a> = fn(&mut Mir<'a>, &Position, Type<'a>, Type<'a>) -> Type<'a>;
+
+/// An implementation of a builtin trait, keyed by its `TraitType` in `Traits`.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+pub struct Trait<'a> {
+    pub code: TraitCode<'a>,
+    pub ref_n: usize,
 }
 
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
-pub enum Trait<'a> {
-    Add {
-        code: fn(&mut CodeGen<'a>, &Position, Data<'a>, Data<'a>) -> Data<'a>,
-        skeleton: fn(&mut Mir, &Position, Type<'a>, Type<'a>) -> Type<'a>,
-        ref_n: usize,
-    },
-    Eq {
-        code: fn(&mut CodeGen<'a>, &Position, Data<'a>, Data<'a>) -> Data<'a>,
-        skeleton: fn(&mut Mir<'a>, &Position, Type<'a>, Type<'a>) -> Type<'a>,
-        ref_n: usize,
-    },
-    Ne {
-        code: fn(&mut CodeGen<'a>, &Position, Data<'a>, Data<'a>) -> Data<'a>,
-        skeleton: fn(&mut Mir<'a>, &Position, Type<'a>, Type<'a>) -> Type<'a>,
-        ref_n: usize,
-    },
-    Copy {
-        ref_n: usize,
+pub enum TraitCode<'a> {
+    /// A binary operator, compiled by `code` and typed in the MIR by `skeleton`.
+    Operator {
+        code: OperatorCode<'a>,
+        skeleton: OperatorSkeleton<'a>,
     },
+    Default(fn(&mut CodeGen<'a>, &Position, Type<'a>) -> Data<'a>),
+    /// A trait with no code, such as `Copy`.
+    Marker,
+}
+
+#[derive(Clone, Debug)]
+pub struct StdFunction<'a> {
+    pub code: fn(&mut CodeGen<'a>, &Position, Vec<Data<'a>>) -> Data<'a>,
+    pub skeleton: fn(&mut Mir<'a>, &Position, Vec<Type<'a>>) -> Type<'a>,
 }
 
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub enum TraitType {
     Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
     Copy,
     Eq,
     Ne,
+    Default,
 }
 
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, EnumIter)]
@@ -81,7 +107,33 @@ pub enum BasicType {
     U32,
     U64,
     U128,
+    F32,
+    F64,
     Enum,
+    Str,
+    RawPtr,
+    Slice,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Signedness {
+    Signed,
+    Unsigned,
+}
+
+impl BasicType {
+    /// The signedness of an integer type, or `None` for any other type.
+    pub fn signedness(&self) -> Option<Signedness> {
+        match self {
+            BasicType::I8 | BasicType::I16 | BasicType::I32 | BasicType::I64 | BasicType::I128 => {
+                Some(Signedness::Signed)
+            }
+            BasicType::U8 | BasicType::U16 | BasicType::U32 | BasicType::U64 | BasicType::U128 => {
+                Some(Signedness::Unsigned)
+            }
+            _ => None,
+        }
+    }
 }
 
 impl Display for BasicType {
@@ -123,16 +175,51 @@ impl Display for BasicType {
             BasicType::U128 => {
                 write!(f, "u128")
             }
+            BasicType::F32 => {
+                write!(f, "f32")
+            }
+            BasicType::F64 => {
+                write!(f, "f64")
+            }
             BasicType::Enum => {
                 write!(f, "enum")
             }
+            BasicType::Str => {
+                write!(f, "str")
+            }
+            BasicType::RawPtr => {
+                write!(f, "rawptr")
+            }
+            BasicType::Slice => {
+                write!(f, "slice")
+            }
         }
     }
 }
 
+/// Whether `name` names a builtin type, which bindings, functions and user types may not shadow.
+pub fn is_builtin_type_name(name: &str) -> bool {
+    BasicType::iter()
+        .filter(|basictype| {
+            !matches!(
+                basictype,
+                BasicType::Enum | BasicType::RawPtr | BasicType::Slice
+            )
+        })
+        .any(|basictype| basictype.to_string() == name)
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Mutability {
+    Const,
+    Mut,
+}
+
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub enum CustomTypeInternals<'a> {
-    Enum{variants: HashMap<String, Type<'a>>, tp: StructType<'a>}
+    Enum{variants: IndexMap<String, Type<'a>>, tp: StructType<'a>},
+    RawPtr{pointee: Box<Type<'a>>, mutability: Mutability},
+    Slice{element: Box<Type<'a>>},
 }
 
 #[derive(Eq, Clone, Debug)]
@@ -146,6 +233,90 @@ pub struct Type<'a> {
 }
 
 impl<'a> Type<'a> {
+    /// The code and skeleton of the operator implemented by `traittp`, if the type implements it.
+    pub fn operator(&self, traittp: &TraitType) -> Option<(OperatorCode<'a>, OperatorSkeleton<'a>)> {
+        match self.traits.get(traittp)?.code {
+            TraitCode::Operator { code, skeleton } => Some((code, skeleton)),
+            _ => None,
+        }
+    }
+
+    /// Raw pointers are Copy and are not tracked by the borrow checker.
+    pub fn raw_ptr(&self, mutability: Mutability) -> Type<'a> {
+        Type {
+            basictype: BasicType::RawPtr,
+            traits: HashMap::from([(
+                TraitType::Copy,
+                Trait {
+                    code: TraitCode::Marker,
+                    ref_n: 0,
+                },
+            )]),
+            qualname: format!(
+                "*{} {}",
+                match mutability {
+                    Mutability::Const => "const",
+                    Mutability::Mut => "mut",
+                },
+                self.qualname()
+            ),
+            lifetime: Lifetime::Static,
+            ref_n: 0,
+            usertype: Some(CustomTypeInternals::RawPtr {
+                pointee: Box::new(self.clone()),
+                mutability,
+            }),
+        }
+    }
+
+    /// A slice is a pointer to its elements and their number. It does not own the elements, so
+    /// it is Copy, and only `std` creates slices for now.
+    pub fn slice(&self) -> Type<'a> {
+        Type {
+            basictype: BasicType::Slice,
+            traits: HashMap::from([(
+                TraitType::Copy,
+                Trait {
+                    code: TraitCode::Marker,
+                    ref_n: 0,
+                },
+            )]),
+            qualname: format!("[{}]", self.qualname()),
+            lifetime: Lifetime::Static,
+            ref_n: 0,
+            usertype: Some(CustomTypeInternals::Slice {
+                element: Box::new(self.clone()),
+            }),
+        }
+    }
+
+    /// The element type of a slice, if this is one (and not a reference to one).
+    pub fn element(&self) -> Option<&Type<'a>> {
+        match &self.usertype {
+            Some(CustomTypeInternals::Slice { element }) if self.ref_n == 0 => Some(element),
+            _ => None,
+        }
+    }
+
+    /// The pointee of a raw pointer, if this is one (and not a reference to one).
+    pub fn pointee(&self) -> Option<(&Type<'a>, Mutability)> {
+        match &self.usertype {
+            Some(CustomTypeInternals::RawPtr {
+                pointee,
+                mutability,
+            }) if self.ref_n == 0 => Some((pointee, *mutability)),
+            _ => None,
+        }
+    }
+
+    /// The signedness of an integer, or `None` if this is not one (references to integers are not).
+    pub fn signedness(&self) -> Option<Signedness> {
+        if self.ref_n > 0 {
+            return None;
+        }
+        self.basictype.signedness()
+    }
+
     pub fn qualname(&self) -> String {
         if self.ref_n > 0 {
             "&".to_string().repeat(self.ref_n) + &self.qualname
@@ -191,6 +362,86 @@ impl Display for Lifetime {
     }
 }
 
+/// Checks that an integer literal fits in its type, returning the error message if it does not.
+pub fn check_int_literal(basictype: &BasicType, value: &str) -> Result<(), String> {
+    macro_rules! check {
+        ($tp:ty) => {
+            if value.parse::<$tp>().is_err() {
+                return Err(format!(
+                    "{} literal in radix 10 out of bounds ({} to {}).",
+                    basictype,
+                    <$tp>::MAX,
+                    <$tp>::MIN
+                ));
+            }
+        };
+    }
+
+    match basictype {
+        BasicType::I8 => check!(i8),
+        BasicType::I16 => check!(i16),
+        BasicType::I32 => check!(i32),
+        BasicType::I64 => check!(i64),
+        BasicType::I128 => check!(i128),
+        BasicType::U8 => check!(u8),
+        BasicType::U16 => check!(u16),
+        BasicType::U32 => check!(u32),
+        BasicType::U64 => check!(u64),
+        BasicType::U128 => check!(u128),
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+/// Checks that a float literal is finite in its type, returning the error message if it is not.
+pub fn check_float_literal(basictype: &BasicType, value: &str) -> Result<(), String> {
+    macro_rules! check {
+        ($tp:ty) => {
+            if value.parse::<$tp>().map_or(true, |value| value.is_infinite()) {
+                return Err(format!(
+                    "{} literal out of bounds, the largest finite value is {:e}.",
+                    basictype,
+                    <$tp>::MAX
+                ));
+            }
+        };
+    }
+
+    match basictype {
+        BasicType::F32 => check!(f32),
+        BasicType::F64 => check!(f64),
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+/// The value of an integer type's associated constant, such as `i32::MAX`, as a decimal literal.
+pub fn int_assoc_const(basictype: &BasicType, name: &str) -> Option<String> {
+    macro_rules! limits {
+        ($tp:ty) => {
+            match name {
+                "MAX" => Some(<$tp>::MAX.to_string()),
+                "MIN" => Some(<$tp>::MIN.to_string()),
+                _ => None,
+            }
+        };
+    }
+
+    match basictype {
+        BasicType::I8 => limits!(i8),
+        BasicType::I16 => limits!(i16),
+        BasicType::I32 => limits!(i32),
+        BasicType::I64 => limits!(i64),
+        BasicType::I128 => limits!(i128),
+        BasicType::U8 => limits!(u8),
+        BasicType::U16 => limits!(u16),
+        BasicType::U32 => limits!(u32),
+        BasicType::U64 => limits!(u64),
+        BasicType::U128 => limits!(u128),
+        _ => None,
+    }
+}
+
 pub fn init_extern_fns(codegen: &mut CodeGen) {
     let printftp = codegen.context.i32_type().fn_type(
         &[codegen
@@ -198,7 +449,7 @@ pub fn init_extern_fns(codegen: &mut CodeGen) {
             .i8_type()
             .ptr_type(AddressSpace::from(0))
             .into()],
-        false,
+        true,
     );
     let printf =
         codegen
@@ -206,4 +457,225 @@ pub fn init_extern_fns(codegen: &mut CodeGen) {
             .add_function("printf", printftp, Some(inkwell::module::Linkage::External));
 
     codegen.extern_fns.insert(String::from("printf"), printf);
+
+    let getenvtp = codegen
+        .context
+        .i8_type()
+        .ptr_type(AddressSpace::from(0))
+        .fn_type(
+            &[codegen
+                .context
+                .i8_type()
+                .ptr_type(AddressSpace::from(0))
+                .into()],
+            false,
+        );
+    let getenv =
+        codegen
+            .module
+            .add_function("getenv", getenvtp, Some(inkwell::module::Linkage::External));
+
+    codegen.extern_fns.insert(String::from("getenv"), getenv);
+
+    let strlentp = codegen.context.i64_type().fn_type(
+        &[codegen
+            .context
+            .i8_type()
+            .ptr_type(AddressSpace::from(0))
+            .into()],
+        false,
+    );
+    let strlen =
+        codegen
+            .module
+            .add_function("strlen", strlentp, Some(inkwell::module::Linkage::External));
+
+    codegen.extern_fns.insert(String::from("strlen"), strlen);
+
+    let memcmptp = codegen.context.i32_type().fn_type(
+        &[
+            codegen
+                .context
+                .i8_type()
+                .ptr_type(AddressSpace::from(0))
+                .into(),
+            codegen
+                .context
+                .i8_type()
+                .ptr_type(AddressSpace::from(0))
+                .into(),
+            codegen.context.i64_type().into(),
+        ],
+        false,
+    );
+    let memcmp =
+        codegen
+            .module
+            .add_function("memcmp", memcmptp, Some(inkwell::module::Linkage::External));
+
+    codegen.extern_fns.insert(String::from("memcmp"), memcmp);
+
+    let dprintftp = codegen.context.i32_type().fn_type(
+        &[
+            codegen.context.i32_type().into(),
+            codegen
+                .context
+                .i8_type()
+                .ptr_type(AddressSpace::from(0))
+                .into(),
+        ],
+        true,
+    );
+    let dprintf =
+        codegen
+            .module
+            .add_function("dprintf", dprintftp, Some(inkwell::module::Linkage::External));
+
+    codegen.extern_fns.insert(String::from("dprintf"), dprintf);
+
+    let aborttp = codegen.context.void_type().fn_type(&[], false);
+    let abort =
+        codegen
+            .module
+            .add_function("abort", aborttp, Some(inkwell::module::Linkage::External));
+
+    codegen.extern_fns.insert(String::from("abort"), abort);
+
+    let malloctp = codegen
+        .context
+        .i8_type()
+        .ptr_type(AddressSpace::from(0))
+        .fn_type(&[codegen.context.i64_type().into()], false);
+    let malloc =
+        codegen
+            .module
+            .add_function("malloc", malloctp, Some(inkwell::module::Linkage::External));
+
+    codegen.extern_fns.insert(String::from("malloc"), malloc);
+
+    let snprintftp = codegen.context.i32_type().fn_type(
+        &[
+            codegen
+                .context
+                .i8_type()
+                .ptr_type(AddressSpace::from(0))
+                .into(),
+            codegen.context.i64_type().into(),
+            codegen
+                .context
+                .i8_type()
+                .ptr_type(AddressSpace::from(0))
+                .into(),
+        ],
+        true,
+    );
+    let snprintf =
+        codegen
+            .module
+            .add_function("snprintf", snprintftp, Some(inkwell::module::Linkage::External));
+
+    codegen.extern_fns.insert(String::from("snprintf"), snprintf);
+
+    //The wall clock, for `std::time`
+    if is_msvc(codegen.module.get_triple().as_str().to_str().unwrap()) {
+        let getsystemtimetp = codegen.context.void_type().fn_type(
+            &[codegen
+                .context
+                .i64_type()
+                .ptr_type(AddressSpace::from(0))
+                .into()],
+            false,
+        );
+        let getsystemtime = codegen.module.add_function(
+            "GetSystemTimeAsFileTime",
+            getsystemtimetp,
+            Some(inkwell::module::Linkage::External),
+        );
+
+        codegen
+            .extern_fns
+            .insert(String::from("GetSystemTimeAsFileTime"), getsystemtime);
+    } else {
+        let clock_gettimetp = codegen.context.i32_type().fn_type(
+            &[
+                codegen.context.i32_type().into(),
+                codegen
+                    .context
+                    .i64_type()
+                    .ptr_type(AddressSpace::from(0))
+                    .into(),
+            ],
+            false,
+        );
+        let clock_gettime = codegen.module.add_function(
+            "clock_gettime",
+            clock_gettimetp,
+            Some(inkwell::module::Linkage::External),
+        );
+
+        codegen
+            .extern_fns
+            .insert(String::from("clock_gettime"), clock_gettime);
+    }
+
+    //Random bytes from the OS, for `std::rand`
+    if is_msvc(codegen.module.get_triple().as_str().to_str().unwrap()) {
+        let rand_stp = codegen.context.i32_type().fn_type(
+            &[codegen
+                .context
+                .i32_type()
+                .ptr_type(AddressSpace::from(0))
+                .into()],
+            false,
+        );
+        let rand_s =
+            codegen
+                .module
+                .add_function("rand_s", rand_stp, Some(inkwell::module::Linkage::External));
+
+        codegen.extern_fns.insert(String::from("rand_s"), rand_s);
+    } else if is_darwin(codegen.module.get_triple().as_str().to_str().unwrap()) {
+        let getentropytp = codegen.context.i32_type().fn_type(
+            &[
+                codegen
+                    .context
+                    .i8_type()
+                    .ptr_type(AddressSpace::from(0))
+                    .into(),
+                codegen.context.i64_type().into(),
+            ],
+            false,
+        );
+        let getentropy = codegen.module.add_function(
+            "getentropy",
+            getentropytp,
+            Some(inkwell::module::Linkage::External),
+        );
+
+        codegen
+            .extern_fns
+            .insert(String::from("getentropy"), getentropy);
+    } else {
+        let getrandomtp = codegen.context.i64_type().fn_type(
+            &[
+                codegen
+                    .context
+                    .i8_type()
+                    .ptr_type(AddressSpace::from(0))
+                    .into(),
+                codegen.context.i64_type().into(),
+                codegen.context.i32_type().into(),
+            ],
+            false,
+        );
+        let getrandom = codegen.module.add_function(
+            "getrandom",
+            getrandomtp,
+            Some(inkwell::module::Linkage::External),
+        );
+
+        codegen
+            .extern_fns
+            .insert(String::from("getrandom"), getrandom);
+    }
 }
@@ -0,0 +1,118 @@
+use indexmap::IndexMap;
+use strum::IntoEnumIterator;
+
+use crate::{
+    errors::{raise_error, raise_error_multi, ErrorType},
+    parser::nodes::{Node, NodeType},
+    utils::{FileInfo, Position},
+};
+
+use super::{int_assoc_const, BasicType, BuiltinTypes, Mutability, Type};
+
+/// Every type a name can resolve to: the builtins, and the user types (enums and aliases) that
+/// register themselves while their definitions are hoisted.
+#[derive(Clone, Debug, Default)]
+pub struct TypeRegistry<'a> {
+    pub builtins: BuiltinTypes<'a>,
+    /// Each user type, with the position of its definition.
+    user: IndexMap<String, (Type<'a>, Position)>,
+}
+
+impl<'a> TypeRegistry<'a> {
+    /// Registers a user type. An alias registers the type it names, so it is equal to that type.
+    pub fn register(&mut self, name: String, tp: Type<'a>, pos: &Position, info: &FileInfo) {
+        if let Some((_, first)) = self.user.get(&name) {
+            raise_error_multi(
+                vec![
+                    format!("Type '{name}' is defined multiple times."),
+                    "First definition here:".into(),
+                ],
+                ErrorType::MultipleTypeDefinitions,
+                vec![Some(pos), Some(first)],
+                info,
+            );
+        }
+        self.user.insert(name, (tp, pos.clone()));
+    }
+
+    pub fn get(&self, name: &str) -> Option<Type<'a>> {
+        for basictype in BasicType::iter() {
+            if name == basictype.to_string() {
+                if let Some(tp) = self.builtins.get(&basictype) {
+                    return Some(tp.clone());
+                }
+            }
+        }
+
+        self.user.get(name).map(|(tp, _)| tp.clone())
+    }
+
+    /// Resolves a path like `i32::MAX` to the type and value of the associated constant. Returns
+    /// None if the path does not start with a type.
+    pub fn assoc_const(
+        &self,
+        info: &FileInfo,
+        path: &str,
+        pos: &Position,
+    ) -> Option<(BasicType, String)> {
+        let (tpname, name) = path.rsplit_once("::")?;
+        let tp = self.get(tpname)?;
+
+        match int_assoc_const(&tp.basictype, name).filter(|_| tp.ref_n == 0) {
+            Some(value) => Some((tp.basictype, value)),
+            None => {
+                let fmt: String = format!(
+                    "Associated constant '{}' not found on type '{}'.",
+                    name,
+                    tp.qualname()
+                );
+                raise_error(&fmt, ErrorType::BindingNotFound, pos, info);
+            }
+        }
+    }
+
+    /// Resolves a type written in the source, such as `&Color`, `*const i32` or `[str]`.
+    pub fn resolve(&self, info: &FileInfo, name: &Node) -> Type<'a> {
+        match name.tp {
+            NodeType::Identifier => {}
+            NodeType::Reference => {
+                let mut tp = self.resolve(info, name.data.get_data().nodes.get("expr").unwrap());
+                tp.ref_n += 1;
+                return tp;
+            }
+            NodeType::RawPtr => {
+                let data = name.data.get_data();
+                let pointee = self.resolve(info, data.nodes.get("tp").unwrap());
+                return pointee.raw_ptr(if *data.booleans.get("is_mut").unwrap() {
+                    Mutability::Mut
+                } else {
+                    Mutability::Const
+                });
+            }
+            NodeType::Slice => {
+                let data = name.data.get_data();
+                let element = self.resolve(info, data.nodes.get("tp").unwrap());
+                if element.basictype == BasicType::Void && element.ref_n == 0 {
+                    raise_error(
+                        "Expected a type with values as the element of a slice, got 'std::void'.",
+                        ErrorType::TypeMismatch,
+                        &name.pos,
+                        info,
+                    );
+                }
+                return element.slice();
+            }
+            _ => raise_error("Expected a type.", ErrorType::TypeNotFound, &name.pos, info),
+        }
+        let data = name.data.get_data();
+        let name_str = data.raw.get("value").unwrap();
+
+        match self.get(name_str) {
+            Some(tp) => tp,
+            None => {
+                let fmt: String = format!("Type '{}' not found.", name_str);
+                raise_error(&fmt, ErrorType::TypeNotFound, &name.pos, info);
+            }
+        }
+    }
+}
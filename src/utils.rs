@@ -1,14 +1,21 @@
-use std::str::Chars;
+use std::{fs::File, io::Write, str::Chars};
 
-use inkwell::{module::Linkage, values::BasicValue, AddressSpace};
+use inkwell::{
+    attributes::{Attribute, AttributeLoc},
+    intrinsics::Intrinsic,
+    module::Linkage,
+    values::{BasicValue, FunctionValue, IntValue, PointerValue},
+    AddressSpace,
+};
 
-use crate::codegen::CodeGen;
+use crate::codegen::{is_msvc, CodeGen, CurFunctionState};
 
 #[derive(Clone, Debug)]
 pub struct FileInfo<'a> {
     pub data: Chars<'a>,
     pub name: String,
     pub dir: String,
+    pub tab_width: usize,
 }
 
 #[derive(Clone, Debug)]
@@ -19,17 +26,45 @@ pub struct Position {
     pub opcol: Option<usize>,
 }
 
-pub fn print_string(codegen: &CodeGen, message: &str) {
-    let str = codegen.context.const_string(message.as_bytes(), true);
+impl Position {
+    /// Just the operator of this expression, which is `len` columns wide, or the whole expression
+    /// if it has no operator.
+    pub fn operator(&self, len: usize) -> Position {
+        match self.opcol {
+            Some(opcol) => Position {
+                line: self.line,
+                startcol: opcol,
+                endcol: opcol + len,
+                opcol: None,
+            },
+            None => self.clone(),
+        }
+    }
+}
 
-    let global = codegen
-        .module
-        .add_global(str.get_type(), Some(AddressSpace::from(0u16)), "");
-    global.set_constant(true);
-    global.set_linkage(Linkage::Private);
-    global.set_initializer(&str.as_basic_value_enum());
+/// Identical strings share one private global, which is created on first use.
+pub fn global_string<'a>(codegen: &mut CodeGen<'a>, message: &str) -> PointerValue<'a> {
+    let global = match codegen.strings.get(message) {
+        Some(global) => *global,
+        None => {
+            let str = codegen.context.const_string(message.as_bytes(), true);
 
-    let ptr = unsafe {
+            let global =
+                codegen
+                    .module
+                    .add_global(str.get_type(), Some(AddressSpace::from(0u16)), "");
+            global.set_constant(true);
+            global.set_linkage(Linkage::Private);
+            //Only the contents of a string are observable, so identical constants may be merged
+            global.set_unnamed_addr(true);
+            global.set_initializer(&str.as_basic_value_enum());
+
+            codegen.strings.insert(message.to_string(), global);
+            global
+        }
+    };
+
+    unsafe {
         codegen.builder.build_gep(
             global.as_pointer_value(),
             &[
@@ -38,11 +73,261 @@ pub fn print_string(codegen: &CodeGen, message: &str) {
             ],
             "",
         )
+    }
+}
+
+const RT_ERROR: &str = "kestrel_rt_error";
+
+/// `kestrel_rt_error(msg, loc)` writes a runtime error to stderr and aborts. `loc` indexes the
+/// module's location table, so a checked operation only passes one integer for its position.
+/// The function is declared the first time a runtime check needs it, and defined by
+/// `define_rt_error` once every location is known.
+fn rt_error<'a>(codegen: &mut CodeGen<'a>) -> FunctionValue<'a> {
+    if let Some(function) = codegen.module.get_function(RT_ERROR) {
+        return function;
+    }
+
+    let str_tp = codegen.context.i8_type().ptr_type(AddressSpace::from(0u16));
+    let fn_tp = codegen.context.void_type().fn_type(
+        &[str_tp.into(), codegen.context.i32_type().into()],
+        false,
+    );
+    let function = codegen
+        .module
+        .add_function(RT_ERROR, fn_tp, Some(Linkage::Private));
+    for attr in ["noreturn", "cold", "noinline"] {
+        function.add_attribute(
+            AttributeLoc::Function,
+            codegen
+                .context
+                .create_enum_attribute(Attribute::get_named_enum_kind_id(attr), 0),
+        );
+    }
+
+    function
+}
+
+/// Emits the location table, one `{line, col}` per runtime check, and the body of
+/// `kestrel_rt_error` that looks the location up in it.
+pub fn define_rt_error(codegen: &mut CodeGen) {
+    let Some(function) = codegen.module.get_function(RT_ERROR) else {
+        return;
     };
 
-    codegen.builder.build_call(
-        *codegen.extern_fns.get("printf").unwrap(),
-        &[ptr.into()],
-        "",
+    let i32_tp = codegen.context.i32_type();
+    let location_tp = codegen
+        .context
+        .struct_type(&[i32_tp.into(), i32_tp.into()], false);
+    let locations = codegen
+        .rt_locations
+        .iter()
+        .map(|(line, col)| {
+            location_tp.const_named_struct(&[
+                i32_tp.const_int(*line as u64, false).into(),
+                i32_tp.const_int(*col as u64, false).into(),
+            ])
+        })
+        .collect::<Vec<_>>();
+    let table_tp = location_tp.array_type(locations.len() as u32);
+    let table = codegen
+        .module
+        .add_global(table_tp, Some(AddressSpace::from(0u16)), "");
+    table.set_constant(true);
+    table.set_linkage(Linkage::Private);
+    table.set_initializer(&location_tp.const_array(&locations));
+
+    let block = codegen.context.append_basic_block(function, "");
+    codegen.builder.position_at_end(block);
+
+    let loc = function.get_nth_param(1).unwrap().into_int_value();
+    let field = |n: u64| {
+        let ptr = unsafe {
+            codegen.builder.build_in_bounds_gep(
+                table.as_pointer_value(),
+                &[i32_tp.const_zero(), loc, i32_tp.const_int(n, false)],
+                "",
+            )
+        };
+        codegen.builder.build_load(ptr, "")
+    };
+    let line = field(0);
+    let col = field(1);
+
+    //Flush what the program already wrote, which abort would otherwise discard when stdout is
+    //buffered
+    let str_tp = codegen.context.i8_type().ptr_type(AddressSpace::from(0u16));
+    let fflush = codegen.module.add_function(
+        "fflush",
+        i32_tp.fn_type(&[str_tp.into()], false),
+        Some(Linkage::External),
     );
+    codegen
+        .builder
+        .build_call(fflush, &[str_tp.const_null().into()], "");
+
+    let format = global_string(codegen, "Error: %s\n    %s:%d:%d\n");
+    let file = global_string(codegen, &codegen.session.info.name.clone());
+    let args = [
+        format.into(),
+        function.get_nth_param(0).unwrap().into(),
+        file.into(),
+        line.into(),
+        col.into(),
+    ];
+    if is_msvc(codegen.module.get_triple().as_str().to_str().unwrap()) {
+        //The UCRT has no dprintf, and its stderr is only reachable through __acrt_iob_func
+        let iob = codegen.module.add_function(
+            "__acrt_iob_func",
+            str_tp.fn_type(&[i32_tp.into()], false),
+            Some(Linkage::External),
+        );
+        let fprintf = codegen.module.add_function(
+            "fprintf",
+            i32_tp.fn_type(&[str_tp.into(), str_tp.into()], true),
+            Some(Linkage::External),
+        );
+        let stderr = codegen
+            .builder
+            .build_call(iob, &[i32_tp.const_int(2, false).into()], "")
+            .try_as_basic_value()
+            .left()
+            .unwrap();
+        codegen
+            .builder
+            .build_call(fprintf, &[&[stderr.into()], &args[..]].concat(), "");
+    } else {
+        let stderr = i32_tp.const_int(2, false);
+        codegen.builder.build_call(
+            *codegen.extern_fns.get("dprintf").unwrap(),
+            &[&[stderr.into()], &args[..]].concat(),
+            "",
+        );
+    }
+    codegen
+        .builder
+        .build_call(*codegen.extern_fns.get("abort").unwrap(), &[], "");
+    codegen.builder.build_unreachable();
+}
+
+/// Reports a runtime error at `pos` and aborts, which terminates the current block.
+pub fn build_rt_error(codegen: &mut CodeGen, message: &str, pos: &Position) {
+    let function = rt_error(codegen);
+    let message = global_string(codegen, message);
+
+    codegen.rt_locations.push((
+        pos.line as u32 + 1,
+        pos.opcol.unwrap_or(pos.startcol) as u32 + 1,
+    ));
+    let loc = codegen
+        .context
+        .i32_type()
+        .const_int(codegen.rt_locations.len() as u64 - 1, false);
+
+    codegen
+        .builder
+        .build_call(function, &[message.into(), loc.into()], "");
+    codegen.builder.build_unreachable();
+}
+
+/// Branches to a runtime error with `message` if `failed` is set, which is expected not to be.
+pub fn build_rt_check<'a>(
+    codegen: &mut CodeGen<'a>,
+    failed: IntValue<'a>,
+    message: &str,
+    pos: &Position,
+) {
+    let expect_i1 = Intrinsic::find("llvm.expect.i1").unwrap();
+    let expect_i1_function = expect_i1
+        .get_declaration(
+            &codegen.module,
+            &[
+                codegen.context.bool_type().into(),
+                codegen.context.bool_type().into(),
+            ],
+        )
+        .unwrap();
+
+    let error_block: inkwell::basic_block::BasicBlock = codegen
+        .context
+        .append_basic_block(codegen.cur_fn.unwrap(), "");
+    let end_block: inkwell::basic_block::BasicBlock = codegen
+        .context
+        .append_basic_block(codegen.cur_fn.unwrap(), "");
+
+    let res = codegen
+        .builder
+        .build_call(
+            expect_i1_function,
+            &[
+                failed.into(),
+                codegen.context.bool_type().const_int(0, true).into(),
+            ],
+            "",
+        )
+        .try_as_basic_value()
+        .left();
+
+    codegen.builder.build_conditional_branch(
+        res.unwrap().into_int_value(),
+        error_block,
+        end_block,
+    );
+
+    codegen.builder.position_at_end(error_block);
+    codegen.block = Some(error_block);
+
+    build_rt_error(codegen, message, pos);
+
+    error_block
+        .move_after(codegen.cur_fnstate.as_ref().unwrap().cur_block.unwrap())
+        .unwrap();
+    end_block.move_after(error_block).unwrap();
+
+    codegen.builder.position_at_end(end_block);
+    codegen.block = Some(end_block);
+
+    codegen.cur_fnstate = Some(CurFunctionState {
+        cur_block: Some(end_block),
+        returned: false,
+        rettp: codegen.cur_fnstate.as_ref().unwrap().rettp.clone(),
+        rettp_pos: codegen.cur_fnstate.as_ref().unwrap().rettp_pos.clone(),
+    });
+}
+
+fn escape_depinfo_path(path: &str) -> String {
+    path.replace(' ', "\\ ")
+}
+
+/// Write a Makefile-style dependency file: the target depends on every source, and each
+/// source gets an empty rule so make does not fail when one of them is removed.
+pub fn write_depinfo(path: &str, target: &str, sources: &[String]) -> std::io::Result<()> {
+    let sources = sources
+        .iter()
+        .map(|x| escape_depinfo_path(x))
+        .collect::<Vec<_>>();
+
+    let mut out = format!("{}: {}\n", escape_depinfo_path(target), sources.join(" "));
+    for source in &sources {
+        out.push_str(&format!("\n{source}:\n"));
+    }
+
+    File::create(path)?.write_all(out.as_bytes())
+}
+
+/// The Levenshtein distance between `a` and `b`, counted in chars.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diag = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let next = (diag + usize::from(ca != *cb))
+                .min(row[j] + 1)
+                .min(row[j + 1] + 1);
+            diag = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
 }
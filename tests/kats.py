@@ -6,12 +6,18 @@ import sys
 
 print("Kestrel Automated Test Suite (KATS)")
 
+#The name may be followed by arguments for the compiler. An aborting test compiles cleanly and
+#is then run, and its own stderr is compared.
 def check(title: str, name: str, expected: str):
-    result = subprocess.run(["./kestrel", "./tests/"+name], capture_output=True)
+    name, *args = name.split()
+    result = subprocess.run(["./kestrel", "./tests/"+name, *args], capture_output=True)
+    if title.startswith("Expected aborting") and result.returncode == 0:
+        result = subprocess.run(["./a.out"], capture_output=True)
 
     expected = expected.replace("\\n", "\n")
     result = result.stderr.replace(b"\\n", b"\n").decode()
-    if expected != result and not (expected == "\n" and expected.replace("\n", "") != result+"\n"):
+    result = "\n".join(map(lambda x: x.rstrip(), result.split("\n")))
+    if expected.rstrip("\n") != result.rstrip("\n"):
         print(f"{title}: ❌")
         print(f"Expected:\n'{expected}'\n\nGot:\n'{result}'")
         return False